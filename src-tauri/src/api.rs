@@ -0,0 +1,540 @@
+//! Public API surface of a package: exported functions, types with their
+//! fields and methods, constants and variables, with full signatures and docs.
+
+use crate::parser::{file_dir, DetectedLanguage, Entity, EntityGraph, EntityKind, Visibility};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ApiItem {
+    pub id: String,
+    pub name: String,
+    pub kind: EntityKind,
+    /// Declaration up to (not including) the body.
+    pub signature: String,
+    pub doc_comment: String,
+    pub file: String,
+    pub line: usize,
+    pub owner: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiSurface {
+    pub package: String,
+    /// Repo-relative directory path.
+    pub dir: String,
+    pub items: Vec<ApiItem>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ApiChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// One exported item that differs between two revisions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiChange {
+    pub name: String,
+    pub kind: EntityKind,
+    pub change: ApiChangeKind,
+    /// Whether existing callers can break.
+    pub breaking: bool,
+    pub old_signature: Option<String>,
+    pub new_signature: Option<String>,
+    /// Location in the newer revision, or the older one for removals.
+    pub file: String,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageApiDiff {
+    pub package: String,
+    pub dir: String,
+    pub changes: Vec<ApiChange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiDiff {
+    pub base: String,
+    pub head: String,
+    pub packages: Vec<PackageApiDiff>,
+    pub breaking: usize,
+    pub compatible: usize,
+}
+
+fn is_api_kind(kind: &EntityKind) -> bool {
+    matches!(
+        kind,
+        EntityKind::Function | EntityKind::Method | EntityKind::Field | EntityKind::Constant | EntityKind::Variable
+    ) || kind.is_type()
+}
+
+/// Declaration text up to the body: the first `{` or `;` outside brackets
+/// and strings, or for Python `def`/`class` the header's closing `:`.
+/// Constants keep their whole source.
+pub fn full_signature(entity: &Entity) -> String {
+    // Specs from grouped Go declarations lack their keyword
+    let go_keyword = match &entity.kind {
+        EntityKind::Constant => "const ",
+        EntityKind::Variable => "var ",
+        k if k.is_type() => "type ",
+        _ => "",
+    };
+    if entity.file.ends_with(".go")
+        && entity.owner.is_none()
+        && !go_keyword.is_empty()
+        && !entity.source.starts_with(go_keyword)
+    {
+        let mut spec = entity.clone();
+        spec.source = format!("{}{}", go_keyword, entity.source);
+        return full_signature(&spec);
+    }
+    if entity.kind == EntityKind::Constant {
+        return entity.source.trim().to_string();
+    }
+    let python = entity.file.ends_with(".py");
+    // Rust lifetimes (`'a`) are not quotes
+    let single_quotes = !entity.file.ends_with(".rs");
+
+    let mut depth = 0i32;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    let mut end = entity.source.len();
+    for (i, c) in entity.source.char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '"' | '`' => quote = Some(c),
+            '\'' if single_quotes => quote = Some(c),
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            ':' if python && depth == 0 => {
+                // Decorator lines contain no top-level `:`; the first one ends the header
+                end = i;
+                break;
+            }
+            '{' | ';' if !python && depth == 0 => {
+                end = i;
+                break;
+            }
+            _ => {}
+        }
+    }
+    entity.source[..end].trim_end().to_string()
+}
+
+/// Repo-relative directories that contain entities, sorted.
+pub fn package_dirs(graph: &EntityGraph) -> Vec<String> {
+    let dirs: BTreeSet<String> = graph.entities.iter().map(|e| file_dir(&e.file)).collect();
+    dirs.into_iter().collect()
+}
+
+/// Resolve `package` as a directory, else as a unique package name.
+fn resolve_dir(graph: &EntityGraph, package: &str) -> Result<String, String> {
+    let package = package.trim_end_matches('/');
+    if graph.entities.iter().any(|e| file_dir(&e.file) == package) {
+        return Ok(package.to_string());
+    }
+    let dirs: BTreeSet<String> = graph
+        .entities
+        .iter()
+        .filter(|e| e.package == package)
+        .map(|e| file_dir(&e.file))
+        .collect();
+    match dirs.len() {
+        0 => Err(format!("Package not found: {}", package)),
+        1 => Ok(dirs.into_iter().next().unwrap()),
+        _ => Err(format!(
+            "Package name {} is ambiguous; use a directory: {}",
+            package,
+            dirs.into_iter().collect::<Vec<_>>().join(", ")
+        )),
+    }
+}
+
+/// Exported API of the package at `package` (directory or package name).
+/// Types are followed by their fields and methods; members of non-public
+/// types are left out since callers cannot reach them by name.
+pub fn api_surface(graph: &EntityGraph, package: &str) -> Result<ApiSurface, String> {
+    let dir = resolve_dir(graph, package)?;
+    let in_dir: Vec<&Entity> = graph
+        .entities
+        .iter()
+        .filter(|e| file_dir(&e.file) == dir)
+        .collect();
+
+    let public_types: HashSet<&str> = in_dir
+        .iter()
+        .filter(|e| e.kind.is_type() && e.visibility == Visibility::Public)
+        .map(|e| e.name.as_str())
+        .collect();
+
+    let mut items: Vec<ApiItem> = in_dir
+        .iter()
+        .filter(|e| e.visibility == Visibility::Public && is_api_kind(&e.kind))
+        .filter(|e| e.owner.as_deref().is_none_or(|o| public_types.contains(o)))
+        .map(|e| ApiItem {
+            id: e.id.clone(),
+            name: e.name.clone(),
+            kind: e.kind.clone(),
+            signature: full_signature(e),
+            doc_comment: e.doc_comment.clone(),
+            file: e.file.clone(),
+            line: e.line,
+            owner: e.owner.clone(),
+        })
+        .collect();
+
+    // Constants and variables, then each type with its fields and methods,
+    // then functions
+    items.sort_by(|a, b| {
+        let key = |i: &ApiItem| {
+            let group = match i.kind {
+                EntityKind::Constant | EntityKind::Variable if i.owner.is_none() => 0,
+                EntityKind::Function => 2,
+                _ => 1,
+            };
+            let type_name = i.owner.clone().unwrap_or_else(|| i.name.clone());
+            let member_rank = if i.kind == EntityKind::Field { 0 } else { 1 };
+            (group, type_name, i.owner.is_some(), member_rank, i.name.clone())
+        };
+        key(a).cmp(&key(b))
+    });
+
+    let package = in_dir
+        .first()
+        .map(|e| e.package.clone())
+        .unwrap_or_default();
+    Ok(ApiSurface { package, dir, items })
+}
+
+/// Item identity across revisions. A Go package spans its directory, so
+/// moving an item between its files is not a change; elsewhere each file is
+/// its own module.
+fn item_key(item: &ApiItem) -> String {
+    if item.file.ends_with(".go") {
+        item.name.clone()
+    } else {
+        format!("{}::{}", item.file, item.name)
+    }
+}
+
+fn normalize_whitespace(signature: &str) -> String {
+    signature.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Split a signature into the text before its parameter list, the top-level
+/// parameters, and the text after the list.
+fn split_params(signature: &str) -> Option<(&str, Vec<&str>, &str)> {
+    let open = signature.find('(')?;
+    let mut depth = 0i32;
+    let mut params = Vec::new();
+    let mut start = open + 1;
+    for (i, c) in signature.char_indices().skip_while(|(i, _)| *i <= open) {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' if depth == 0 => {
+                let last = signature[start..i].trim();
+                if !last.is_empty() {
+                    params.push(last);
+                }
+                return Some((&signature[..open], params, &signature[i + 1..]));
+            }
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                params.push(signature[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// A signature change is compatible when it only appends Python/JS
+/// parameters that have defaults (or rest parameters) to an otherwise
+/// identical declaration.
+fn is_compatible_change(file: &str, old: &str, new: &str) -> bool {
+    if !matches!(DetectedLanguage::of_file(file), Some(DetectedLanguage::Python | DetectedLanguage::JavaScript)) {
+        return false;
+    }
+    let (Some((old_head, old_params, old_tail)), Some((new_head, new_params, new_tail))) =
+        (split_params(old), split_params(new))
+    else {
+        return false;
+    };
+    old_head == new_head
+        && old_tail == new_tail
+        && new_params.len() > old_params.len()
+        && new_params.starts_with(&old_params)
+        && new_params[old_params.len()..]
+            .iter()
+            .all(|p| p.contains('=') || p.starts_with('*') || p.starts_with("..."))
+}
+
+fn diff_package(old: Option<ApiSurface>, new: Option<ApiSurface>) -> Option<PackageApiDiff> {
+    let (package, dir) = match (&old, &new) {
+        (_, Some(s)) | (Some(s), None) => (s.package.clone(), s.dir.clone()),
+        (None, None) => return None,
+    };
+    let old_items: BTreeMap<String, ApiItem> = old
+        .map(|s| s.items.into_iter().map(|i| (item_key(&i), i)).collect())
+        .unwrap_or_default();
+    let new_items: BTreeMap<String, ApiItem> = new
+        .map(|s| s.items.into_iter().map(|i| (item_key(&i), i)).collect())
+        .unwrap_or_default();
+
+    let mut changes = Vec::new();
+    for (key, before) in &old_items {
+        match new_items.get(key) {
+            None => changes.push(ApiChange {
+                name: before.name.clone(),
+                kind: before.kind.clone(),
+                change: ApiChangeKind::Removed,
+                breaking: true,
+                old_signature: Some(normalize_whitespace(&before.signature)),
+                new_signature: None,
+                file: before.file.clone(),
+                line: before.line,
+            }),
+            Some(after) => {
+                let old_sig = normalize_whitespace(&before.signature);
+                let new_sig = normalize_whitespace(&after.signature);
+                if old_sig == new_sig && before.kind == after.kind {
+                    continue;
+                }
+                changes.push(ApiChange {
+                    name: after.name.clone(),
+                    kind: after.kind.clone(),
+                    change: ApiChangeKind::Changed,
+                    breaking: before.kind != after.kind || !is_compatible_change(&after.file, &old_sig, &new_sig),
+                    old_signature: Some(old_sig),
+                    new_signature: Some(new_sig),
+                    file: after.file.clone(),
+                    line: after.line,
+                });
+            }
+        }
+    }
+    for (key, after) in &new_items {
+        if !old_items.contains_key(key) {
+            changes.push(ApiChange {
+                name: after.name.clone(),
+                kind: after.kind.clone(),
+                change: ApiChangeKind::Added,
+                breaking: false,
+                old_signature: None,
+                new_signature: Some(normalize_whitespace(&after.signature)),
+                file: after.file.clone(),
+                line: after.line,
+            });
+        }
+    }
+    if changes.is_empty() {
+        return None;
+    }
+    changes.sort_by(|a, b| (!a.breaking, &a.name).cmp(&(!b.breaking, &b.name)));
+    Some(PackageApiDiff { package, dir, changes })
+}
+
+/// Exported API changes from `old` to `new`, per package directory.
+/// Removals, incompatible signature changes and kind changes are breaking;
+/// additions are compatible.
+pub fn api_diff(base: &str, head: &str, old: &EntityGraph, new: &EntityGraph) -> ApiDiff {
+    let dirs: BTreeSet<String> = package_dirs(old).into_iter().chain(package_dirs(new)).collect();
+    let packages: Vec<PackageApiDiff> = dirs
+        .iter()
+        .filter_map(|dir| diff_package(api_surface(old, dir).ok(), api_surface(new, dir).ok()))
+        .collect();
+    let (breaking, compatible) = packages
+        .iter()
+        .flat_map(|p| &p.changes)
+        .fold((0, 0), |(b, c), change| if change.breaking { (b + 1, c) } else { (b, c + 1) });
+    ApiDiff { base: base.to_string(), head: head.to_string(), packages, breaking, compatible }
+}
+
+/// Plain-text report: one block per item, members indented under their type.
+pub fn render_report(surfaces: &[ApiSurface]) -> String {
+    let mut out = String::new();
+    for surface in surfaces.iter().filter(|s| !s.items.is_empty()) {
+        out.push_str(&format!("package {} ({})\n\n", surface.package, surface.dir));
+        for item in &surface.items {
+            let indent = if item.owner.is_some() { "    " } else { "  " };
+            for line in item.signature.lines() {
+                out.push_str(&format!("{}{}\n", indent, line.trim_end()));
+            }
+            for line in item.doc_comment.lines() {
+                out.push_str(&format!("{}    {}\n", indent, line.trim()));
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Plain-text API diff: breaking changes first within each package.
+pub fn render_diff_report(diff: &ApiDiff) -> String {
+    let mut out = format!(
+        "API changes {}..{}: {} breaking, {} compatible\n",
+        diff.base, diff.head, diff.breaking, diff.compatible
+    );
+    for package in &diff.packages {
+        out.push_str(&format!("\npackage {} ({})\n", package.package, package.dir));
+        for change in &package.changes {
+            let flag = if change.breaking { "breaking  " } else { "compatible" };
+            let (label, signature) = match change.change {
+                ApiChangeKind::Added => ("added  ", &change.new_signature),
+                ApiChangeKind::Removed => ("removed", &change.old_signature),
+                ApiChangeKind::Changed => ("changed", &change.old_signature),
+            };
+            out.push_str(&format!("  {}  {}  {}\n", flag, label, signature.as_deref().unwrap_or("")));
+            if change.change == ApiChangeKind::Changed {
+                out.push_str(&format!("{:>23}{}\n", "-> ", change.new_signature.as_deref().unwrap_or("")));
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(file: &str, name: &str, kind: EntityKind, source: &str) -> Entity {
+        Entity {
+            source: source.to_string(),
+            signature: source.lines().next().unwrap_or("").to_string(),
+            package: "store".to_string(),
+            owner: name.split_once('.').map(|(o, _)| o.to_string()),
+            visibility: if name.rsplit('.').next().unwrap().starts_with(char::is_uppercase) {
+                Visibility::Public
+            } else {
+                Visibility::Private
+            },
+            ..Entity::stub(file, name, kind, 1, 1)
+        }
+    }
+
+    #[test]
+    fn signatures_stop_at_the_body() {
+        let go = entity(
+            "store/store.go",
+            "Load",
+            EntityKind::Function,
+            "func Load(cb func() error,\n\topts map[string]int) (*Cache, error) {\n\treturn nil, nil\n}",
+        );
+        assert_eq!(full_signature(&go), "func Load(cb func() error,\n\topts map[string]int) (*Cache, error)");
+
+        let rs = entity(
+            "src/lib.rs",
+            "Get",
+            EntityKind::Function,
+            "pub fn get<'a>(m: &'a Map, key: &str) -> Option<&'a str> where Map: Sized {\n    None\n}",
+        );
+        assert_eq!(full_signature(&rs), "pub fn get<'a>(m: &'a Map, key: &str) -> Option<&'a str> where Map: Sized");
+
+        let py = entity(
+            "app/views.py",
+            "Index",
+            EntityKind::Function,
+            "@route(\"/a:b\")\ndef index(req: Request) -> Dict[str, int]:\n    return {}",
+        );
+        assert_eq!(full_signature(&py), "@route(\"/a:b\")\ndef index(req: Request) -> Dict[str, int]");
+    }
+
+    #[test]
+    fn surface_lists_public_items_grouped_by_type() {
+        let graph = EntityGraph {
+            entities: vec![
+                entity("store/store.go", "New", EntityKind::Function, "func New() *Cache {}"),
+                entity("store/store.go", "Cache", EntityKind::Struct, "type Cache struct {\n\titems map[string]string\n}"),
+                entity("store/store.go", "Cache.Get", EntityKind::Method, "func (c *Cache) Get(k string) string {}"),
+                entity("store/store.go", "Cache.evict", EntityKind::Method, "func (c *Cache) evict() {}"),
+                entity("store/store.go", "entry", EntityKind::Struct, "type entry struct {}"),
+                entity("store/store.go", "entry.Key", EntityKind::Method, "func (e entry) Key() string {}"),
+                entity("store/store.go", "MaxSize", EntityKind::Constant, "MaxSize = 10"),
+                entity("store/store.go", "Cache.items", EntityKind::Field, "items map[string]string"),
+                entity("store/store.go", "Cache.Timeout", EntityKind::Field, "Timeout time.Duration `json:\"timeout\"`"),
+                entity("store/store.go", "Default", EntityKind::Variable, "Default = New()"),
+            ],
+            relations: Vec::new(),
+            external_deps: Default::default(),
+        };
+
+        let surface = api_surface(&graph, "store").unwrap();
+        assert_eq!(surface.dir, "store");
+        let names: Vec<&str> = surface.items.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, vec!["Default", "MaxSize", "Cache", "Cache.Timeout", "Cache.Get", "New"]);
+        assert_eq!(surface.items[0].signature, "var Default = New()");
+        assert_eq!(surface.items[3].signature, "Timeout time.Duration `json:\"timeout\"`");
+        assert_eq!(surface.items[3].owner.as_deref(), Some("Cache"));
+
+        assert!(api_surface(&graph, "missing").is_err());
+    }
+
+    #[test]
+    fn diff_flags_breaking_and_compatible_changes() {
+        let graph = |entities: Vec<Entity>| EntityGraph {
+            entities,
+            relations: Vec::new(),
+            external_deps: Default::default(),
+        };
+        let old = graph(vec![
+            entity("store/store.go", "Get", EntityKind::Function, "func Get(k string) string {}"),
+            entity("store/store.go", "Put", EntityKind::Function, "func Put(k, v string) {}"),
+            entity("store/a.go", "Keep", EntityKind::Function, "func Keep() {}"),
+            entity("store/a.go", "Cache", EntityKind::Struct, "type Cache struct {}"),
+            entity("store/a.go", "Cache.Timeout", EntityKind::Field, "Timeout int"),
+            entity("app/views.py", "Index", EntityKind::Function, "def index(req):\n    pass"),
+        ]);
+        let new = graph(vec![
+            entity("store/store.go", "Get", EntityKind::Function, "func Get(k string,\n\tdef string) string {}"),
+            entity("store/store.go", "Delete", EntityKind::Function, "func Delete(k string) {}"),
+            entity("store/b.go", "Keep", EntityKind::Function, "func  Keep() {}"),
+            entity("store/a.go", "Cache", EntityKind::Struct, "type Cache struct {}"),
+            entity("store/a.go", "Cache.Timeout", EntityKind::Field, "Timeout time.Duration"),
+            entity("app/views.py", "Index", EntityKind::Function, "def index(req, page=1):\n    pass"),
+        ]);
+
+        let diff = api_diff("v1", "v2", &old, &new);
+        assert_eq!((diff.breaking, diff.compatible), (3, 2));
+
+        let changes: Vec<(&str, &str, ApiChangeKind, bool)> = diff
+            .packages
+            .iter()
+            .flat_map(|p| p.changes.iter().map(move |c| (p.dir.as_str(), c.name.as_str(), c.change, c.breaking)))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("app", "Index", ApiChangeKind::Changed, false),
+                ("store", "Cache.Timeout", ApiChangeKind::Changed, true),
+                ("store", "Get", ApiChangeKind::Changed, true),
+                ("store", "Put", ApiChangeKind::Removed, true),
+                ("store", "Delete", ApiChangeKind::Added, false),
+            ]
+        );
+        assert_eq!(
+            diff.packages[1].changes[1].new_signature.as_deref(),
+            Some("func Get(k string, def string) string")
+        );
+    }
+
+    #[test]
+    fn optional_parameters_are_compatible_in_every_js_file() {
+        for file in ["app/view.py", "ui/App.tsx", "ui/menu.jsx", "lib/index.mjs", "lib/index.cjs"] {
+            assert!(is_compatible_change(file, "function f(a)", "function f(a, b = 1)"), "{}", file);
+        }
+        assert!(!is_compatible_change("store/store.go", "func f(a int)", "func f(a int, b = 1)"));
+    }
+}
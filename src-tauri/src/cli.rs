@@ -0,0 +1,312 @@
+//! Command-line reports. `fode <subcommand> ...` runs a report and exits
+//! instead of opening the window:
+//!
+//! ```text
+//! fode api <repo> [package] [--json] [--index <file>] [--rev <ref>] [--lsp <command>]
+//! fode api-diff <repo> <base> [head] [--json]
+//! fode impact <repo> [range] [--depth <n>] [--json]
+//! fode tests <repo> [range] [--json]
+//! fode tests <repo> --map [--json]
+//! fode dead <repo> [--json]
+//! fode check <repo> [--json]
+//! fode hotspots <repo> [--since <date>] [--top <n>] [--json]
+//! fode owners <repo> [--json]
+//! fode duplicates <repo> [--similarity <0..1>] [--json]
+//! ```
+//!
+//! Checks exit with 1 when they find a problem (e.g. a breaking API change)
+//! and 2 on errors.
+
+use crate::api;
+use crate::dead;
+use crate::deps;
+use crate::duplicates;
+use crate::git;
+use crate::hotspots;
+use crate::impact;
+use crate::ownership::{self, CodeOwners};
+use crate::test_map;
+use crate::commands::{change_impact, load_config, load_repo, test_selection};
+use crate::parser::{EntityGraph, RepoInfo};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+const USAGE: &str = "\
+usage: fode                                       open the app
+       fode api <repo> [package] [--json]         exported API of a package (default: all)
+       fode api-diff <repo> <base> [head] [--json]
+                                                  API changes between git revisions (default head:
+                                                  working tree); exits 1 on breaking changes
+       fode impact <repo> [range] [--depth <n>] [--json]
+                                                  entities, packages and tests affected by the
+                                                  uncommitted changes, or by a git range (A..B)
+       fode tests <repo> [range] [--json]
+                                                  tests reaching the changed entities, and changed
+                                                  entities no test reaches
+       fode tests <repo> --map [--json]           every test with the production entities it reaches
+       fode dead <repo> [--json]                  entities nothing calls or references, outside the
+                                                  roots configured in .fode.toml
+       fode check <repo> [--json]                 package dependency cycles and [dependencies]
+                                                  rules from .fode.toml; exits 1 on violations
+       fode hotspots <repo> [--since <date>] [--top <n>] [--json]
+                                                  functions ranked by commits since <date> (default:
+                                                  12 months ago) times complexity; top 20 by default
+       fode owners <repo> [--json]                primary owners and bus factor per package from
+                                                  CODEOWNERS and git blame
+       fode duplicates <repo> [--similarity <0..1>] [--json]
+                                                  clusters of functions with near-identical bodies
+                                                  (default similarity: 0.8)
+
+options:
+       --index <file>   SCIP/LSIF index to use instead of the one in the repo root
+       --lsp <command>  language server to resolve references with, e.g. \"gopls\"
+       --rev <ref>      read the repo at a git branch, tag or commit instead of the working tree
+       --json           print JSON instead of text";
+
+/// Parsed arguments: positionals, `--flag`s and `--option value`s.
+struct Args {
+    positional: Vec<String>,
+    flags: HashSet<String>,
+    options: HashMap<String, String>,
+}
+
+/// Options that take a value; every other `--name` is a flag.
+const VALUE_OPTIONS: &[&str] = &["index", "rev", "lsp", "depth", "since", "top", "similarity"];
+
+impl Args {
+    fn parse(args: &[String]) -> Args {
+        let mut parsed = Args { positional: Vec::new(), flags: HashSet::new(), options: HashMap::new() };
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match arg.strip_prefix("--") {
+                Some(name) if VALUE_OPTIONS.contains(&name) => {
+                    if let Some(value) = iter.next() {
+                        parsed.options.insert(name.to_string(), value.clone());
+                    }
+                }
+                Some(name) => {
+                    parsed.flags.insert(name.to_string());
+                }
+                None => parsed.positional.push(arg.clone()),
+            }
+        }
+        parsed
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.contains(name)
+    }
+}
+
+/// Run a subcommand. Returns the process exit code, or `None` when there
+/// are no arguments and the app should start.
+pub fn run(args: &[String]) -> Option<i32> {
+    let (command, rest) = args.split_first()?;
+    let args = Args::parse(rest);
+    let result = match command.as_str() {
+        "api" => api_report(&args),
+        "api-diff" => api_diff_check(&args),
+        "impact" => impact_report(&args),
+        "tests" => tests_report(&args),
+        "dead" => dead_report(&args),
+        "check" => dependency_check(&args),
+        "hotspots" => hotspot_report(&args),
+        "owners" => ownership_report(&args),
+        "duplicates" => duplicate_report(&args),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(0)
+        }
+        _ => {
+            eprintln!("fode: unknown command `{}`\n{}", command, USAGE);
+            return Some(2);
+        }
+    };
+    Some(result.unwrap_or_else(|e| {
+        eprintln!("fode {}: {}", command, e);
+        2
+    }))
+}
+
+fn load(args: &Args) -> Result<(PathBuf, RepoInfo, EntityGraph), String> {
+    let repo = args
+        .positional
+        .first()
+        .ok_or_else(|| format!("missing <repo>\n{}", USAGE))?;
+    let repo_path = PathBuf::from(repo);
+    if !repo_path.exists() {
+        return Err(format!("Path does not exist: {}", repo));
+    }
+    let index = args.options.get("index").map(PathBuf::from);
+    let rev = args.options.get("rev").map(String::as_str);
+    let lsp = args.options.get("lsp").map(String::as_str);
+    let (info, graph) = load_repo(Path::new(&repo_path), index, rev, lsp)?;
+    Ok((repo_path, info, graph))
+}
+
+fn print_json<T: serde::Serialize>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    println!("{}", json);
+    Ok(())
+}
+
+fn api_report(args: &Args) -> Result<i32, String> {
+    let (_, _, graph) = load(args)?;
+    let surfaces = match args.positional.get(1) {
+        Some(package) => vec![api::api_surface(&graph, package)?],
+        None => api::package_dirs(&graph)
+            .iter()
+            .filter_map(|dir| api::api_surface(&graph, dir).ok())
+            .collect(),
+    };
+    if args.flag("json") {
+        print_json(&surfaces)?;
+    } else {
+        print!("{}", api::render_report(&surfaces));
+    }
+    Ok(0)
+}
+
+fn api_diff_check(args: &Args) -> Result<i32, String> {
+    let (repo, base) = match args.positional.as_slice() {
+        [repo, base, ..] => (PathBuf::from(repo), base),
+        _ => return Err(format!("missing <repo> or <base>\n{}", USAGE)),
+    };
+    let (_, old) = git::parse_revision(&repo, base)?;
+    let (head, new) = match args.positional.get(2) {
+        Some(head) => (head.clone(), git::parse_revision(&repo, head)?.1),
+        None => {
+            let label = args.options.get("rev").cloned().unwrap_or_else(|| "working tree".to_string());
+            (label, load(args)?.2)
+        }
+    };
+
+    let diff = api::api_diff(base, &head, &old, &new);
+    if args.flag("json") {
+        print_json(&diff)?;
+    } else {
+        print!("{}", api::render_diff_report(&diff));
+    }
+    Ok(if diff.breaking > 0 { 1 } else { 0 })
+}
+
+fn repo_arg(args: &Args) -> Result<PathBuf, String> {
+    args.positional
+        .first()
+        .map(PathBuf::from)
+        .ok_or_else(|| format!("missing <repo>\n{}", USAGE))
+}
+
+fn impact_report(args: &Args) -> Result<i32, String> {
+    let repo = repo_arg(args)?;
+    let depth = match args.options.get("depth") {
+        Some(d) => Some(d.parse::<usize>().map_err(|_| format!("invalid --depth: {}", d))?),
+        None => None,
+    };
+
+    let report = change_impact(&repo, args.positional.get(1).map(String::as_str), depth, None)?;
+    if args.flag("json") {
+        print_json(&report)?;
+    } else {
+        print!("{}", impact::render_report(&report));
+    }
+    Ok(0)
+}
+
+fn tests_report(args: &Args) -> Result<i32, String> {
+    if args.flag("map") {
+        let (_, _, graph) = load(args)?;
+        let map = test_map::test_map(&graph);
+        if args.flag("json") {
+            print_json(&map)?;
+        } else {
+            print!("{}", test_map::render_map(&map));
+        }
+        return Ok(0);
+    }
+
+    let repo = repo_arg(args)?;
+    let selection = test_selection(&repo, args.positional.get(1).map(String::as_str), None)?;
+    if args.flag("json") {
+        print_json(&selection)?;
+    } else {
+        print!("{}", test_map::render_report(&selection));
+    }
+    Ok(0)
+}
+
+fn dead_report(args: &Args) -> Result<i32, String> {
+    let (repo, _, graph) = load(args)?;
+    let config = load_config(&repo, args.options.get("rev").map(String::as_str))?;
+    let unused = dead::dead_code(&graph, &config.dead_code);
+    if args.flag("json") {
+        print_json(&unused)?;
+    } else {
+        print!("{}", dead::render_report(&unused));
+    }
+    Ok(0)
+}
+
+fn dependency_check(args: &Args) -> Result<i32, String> {
+    let (repo, _, graph) = load(args)?;
+    let config = load_config(&repo, args.options.get("rev").map(String::as_str))?;
+    let report = deps::check(&graph, &config.dependencies);
+    if args.flag("json") {
+        print_json(&report)?;
+    } else {
+        print!("{}", deps::render_report(&report));
+    }
+    Ok(if report.passed() { 0 } else { 1 })
+}
+
+fn hotspot_report(args: &Args) -> Result<i32, String> {
+    let (repo, _, graph) = load(args)?;
+    let since = args.options.get("since").map(String::as_str).unwrap_or(hotspots::DEFAULT_SINCE);
+    let top = match args.options.get("top") {
+        Some(n) => n.parse::<usize>().map_err(|_| format!("invalid --top: {}", n))?,
+        None => 20,
+    };
+
+    let commits = git::log_changes(&repo, args.options.get("rev").map(String::as_str), since)?;
+    let report = hotspots::hotspots(&graph, &commits, since, Some(top));
+    if args.flag("json") {
+        print_json(&report)?;
+    } else {
+        print!("{}", hotspots::render_report(&report));
+    }
+    Ok(0)
+}
+
+fn ownership_report(args: &Args) -> Result<i32, String> {
+    let (repo, _, graph) = load(args)?;
+    let rev = args.options.get("rev").map(String::as_str);
+    let code_owners = CodeOwners::load(&repo, rev);
+    let blame = ownership::blame_files(&repo, rev, graph.entities.iter().map(|e| e.file.as_str()));
+    let report = ownership::ownership(&graph, &blame, &code_owners);
+    if args.flag("json") {
+        print_json(&report)?;
+    } else {
+        print!("{}", ownership::render_report(&report));
+    }
+    Ok(0)
+}
+
+fn duplicate_report(args: &Args) -> Result<i32, String> {
+    let (_, _, graph) = load(args)?;
+    let similarity = match args.options.get("similarity") {
+        Some(s) => s
+            .parse::<f64>()
+            .ok()
+            .filter(|s| (0.0..=1.0).contains(s))
+            .ok_or_else(|| format!("invalid --similarity: {}", s))?,
+        None => duplicates::DEFAULT_SIMILARITY,
+    };
+
+    let report = duplicates::duplicates(&graph, similarity);
+    if args.flag("json") {
+        print_json(&report)?;
+    } else {
+        print!("{}", duplicates::render_report(&report));
+    }
+    Ok(0)
+}
@@ -0,0 +1,467 @@
+//! Precise references from precomputed code intelligence indexes.
+//!
+//! Supports SCIP (`index.scip`, as produced by scip-go and rust-analyzer) and
+//! LSIF JSON dumps. Occurrences are mapped onto parsed entities by file and
+//! line: a definition belongs to the entity declared on that line, a reference
+//! belongs to the innermost entity whose line range encloses it.
+
+use crate::parser::{Entity, EntityKind, Relation, RelationKind};
+use prost::Message;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Index files looked up in the repo root when no explicit path is given.
+const DEFAULT_INDEX_FILES: &[&str] = &["index.scip", "dump.lsif", "index.lsif"];
+
+/// Subset of the SCIP protobuf schema (sourcegraph/scip, `scip.proto`).
+/// Fields not declared here are skipped by the decoder.
+mod scip {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Index {
+        #[prost(message, repeated, tag = "2")]
+        pub documents: Vec<Document>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Document {
+        #[prost(string, tag = "1")]
+        pub relative_path: String,
+        #[prost(message, repeated, tag = "2")]
+        pub occurrences: Vec<Occurrence>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Occurrence {
+        /// `[start_line, start_char, end_char]` or
+        /// `[start_line, start_char, end_line, end_char]`, 0-based.
+        #[prost(int32, repeated, tag = "1")]
+        pub range: Vec<i32>,
+        #[prost(string, tag = "2")]
+        pub symbol: String,
+        #[prost(int32, tag = "3")]
+        pub symbol_roles: i32,
+    }
+
+    pub const ROLE_DEFINITION: i32 = 0x1;
+    pub const ROLE_IMPORT: i32 = 0x2;
+}
+
+/// A single symbol occurrence, normalized across index formats.
+#[derive(Debug, Clone)]
+pub struct Occurrence {
+    /// Repo-relative file path.
+    pub file: String,
+    /// 1-based line number.
+    pub line: usize,
+    pub symbol: String,
+    pub is_definition: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CodeIndex {
+    /// Index file this was loaded from.
+    pub source: PathBuf,
+    /// Repo-relative paths of documents present in the index.
+    pub files: HashSet<String>,
+    pub occurrences: Vec<Occurrence>,
+}
+
+impl CodeIndex {
+    /// Find an index file in the repo root.
+    pub fn find_in_repo(repo_path: &Path) -> Option<PathBuf> {
+        DEFAULT_INDEX_FILES
+            .iter()
+            .map(|name| repo_path.join(name))
+            .find(|p| p.is_file())
+    }
+
+    /// Load a SCIP (`.scip`) or LSIF (anything else) index. LSIF document
+    /// URIs are made relative to `repo_path`.
+    pub fn load(path: &Path, repo_path: &Path) -> Result<CodeIndex, String> {
+        let data = std::fs::read(path)
+            .map_err(|e| format!("Failed to read index {}: {}", path.display(), e))?;
+        let mut index = if path.extension().and_then(|e| e.to_str()) == Some("scip") {
+            Self::from_scip(&data)?
+        } else {
+            let text = String::from_utf8(data)
+                .map_err(|e| format!("LSIF dump is not UTF-8: {}", e))?;
+            Self::from_lsif(&text, repo_path)?
+        };
+        index.source = path.to_path_buf();
+        Ok(index)
+    }
+
+    pub fn from_scip(data: &[u8]) -> Result<CodeIndex, String> {
+        let decoded = scip::Index::decode(data)
+            .map_err(|e| format!("Invalid SCIP index: {}", e))?;
+
+        let mut index = CodeIndex::default();
+        for doc in decoded.documents {
+            let file = doc.relative_path.trim_start_matches("./").to_string();
+            for occ in doc.occurrences {
+                // Local symbols never cross entity boundaries; imports are not uses
+                if occ.symbol.is_empty()
+                    || occ.symbol.starts_with("local ")
+                    || occ.symbol_roles & scip::ROLE_IMPORT != 0
+                {
+                    continue;
+                }
+                let Some(&start_line) = occ.range.first() else { continue };
+                index.occurrences.push(Occurrence {
+                    file: file.clone(),
+                    line: start_line.max(0) as usize + 1,
+                    symbol: occ.symbol,
+                    is_definition: occ.symbol_roles & scip::ROLE_DEFINITION != 0,
+                });
+            }
+            index.files.insert(file);
+        }
+        Ok(index)
+    }
+
+    /// Parse an LSIF dump (JSON lines, or a single JSON array).
+    ///
+    /// Ranges are grouped into symbols by the result set they chain to via
+    /// `next` edges; ranges listed by a `definitionResult` are definitions.
+    pub fn from_lsif(text: &str, repo_path: &Path) -> Result<CodeIndex, String> {
+        let items: Vec<serde_json::Value> = if text.trim_start().starts_with('[') {
+            serde_json::from_str(text).map_err(|e| format!("Invalid LSIF dump: {}", e))?
+        } else {
+            text.lines()
+                .filter(|l| !l.trim().is_empty())
+                .map(serde_json::from_str)
+                .collect::<Result<_, _>>()
+                .map_err(|e| format!("Invalid LSIF dump: {}", e))?
+        };
+
+        fn id_of(v: &serde_json::Value) -> Option<String> {
+            match v {
+                serde_json::Value::String(s) => Some(s.clone()),
+                serde_json::Value::Number(n) => Some(n.to_string()),
+                _ => None,
+            }
+        }
+
+        let mut project_root = repo_path.to_string_lossy().to_string();
+        let mut documents: HashMap<String, String> = HashMap::new(); // doc id -> uri
+        let mut range_lines: HashMap<String, usize> = HashMap::new(); // range id -> 0-based line
+        let mut range_docs: HashMap<String, String> = HashMap::new(); // range id -> doc id
+        let mut next: HashMap<String, String> = HashMap::new();
+        let mut definition_results: HashSet<String> = HashSet::new();
+        let mut definitions: HashSet<String> = HashSet::new(); // range ids
+
+        // First pass: vertices
+        for item in &items {
+            if item["type"] != "vertex" {
+                continue;
+            }
+            let Some(id) = id_of(&item["id"]) else { continue };
+            match item["label"].as_str() {
+                Some("metaData") => {
+                    if let Some(root) = item["projectRoot"].as_str() {
+                        project_root = root.to_string();
+                    }
+                }
+                Some("document") => {
+                    if let Some(uri) = item["uri"].as_str() {
+                        documents.insert(id, uri.to_string());
+                    }
+                }
+                Some("range") => {
+                    if let Some(line) = item["start"]["line"].as_u64() {
+                        range_lines.insert(id, line as usize);
+                    }
+                }
+                Some("definitionResult") => {
+                    definition_results.insert(id);
+                }
+                _ => {}
+            }
+        }
+
+        // Second pass: edges
+        for item in &items {
+            if item["type"] != "edge" {
+                continue;
+            }
+            let out_v = id_of(&item["outV"]);
+            let in_vs: Vec<String> = match item["inVs"].as_array() {
+                Some(vs) => vs.iter().filter_map(id_of).collect(),
+                None => id_of(&item["inV"]).into_iter().collect(),
+            };
+            let Some(out_v) = out_v else { continue };
+            match item["label"].as_str() {
+                Some("contains") if documents.contains_key(&out_v) => {
+                    for range in in_vs {
+                        range_docs.insert(range, out_v.clone());
+                    }
+                }
+                Some("next") => {
+                    if let Some(target) = in_vs.into_iter().next() {
+                        next.insert(out_v, target);
+                    }
+                }
+                Some("item") if definition_results.contains(&out_v) => {
+                    definitions.extend(in_vs);
+                }
+                _ => {}
+            }
+        }
+
+        let root_uri = format!("file://{}", project_root.trim_start_matches("file://"));
+        let root_uri = root_uri.trim_end_matches('/');
+        let relative = |uri: &str| -> String {
+            uri.strip_prefix(root_uri)
+                .unwrap_or(uri)
+                .trim_start_matches('/')
+                .to_string()
+        };
+
+        let mut index = CodeIndex::default();
+        for uri in documents.values() {
+            index.files.insert(relative(uri));
+        }
+        for (range, line) in &range_lines {
+            let Some(uri) = range_docs.get(range).and_then(|d| documents.get(d)) else {
+                continue;
+            };
+            // Follow `next` edges to the outermost result set
+            let mut symbol = range.clone();
+            let mut hops = 0;
+            while let Some(target) = next.get(&symbol) {
+                symbol = target.clone();
+                hops += 1;
+                if hops > 32 {
+                    break;
+                }
+            }
+            if symbol == *range {
+                continue; // range without a result set carries no symbol
+            }
+            index.occurrences.push(Occurrence {
+                file: relative(uri),
+                line: line + 1,
+                symbol,
+                is_definition: definitions.contains(range),
+            });
+        }
+        Ok(index)
+    }
+
+    /// Whether the index has a document for this repo-relative path.
+    pub fn covers(&self, file: &str) -> bool {
+        self.files.contains(file)
+    }
+
+    /// Build `References`/`Calls` relations from index occurrences.
+    pub fn build_relations(&self, entities: &[Entity]) -> Vec<Relation> {
+        let locator = EntityLocator::new(entities);
+
+        // Symbol -> defining entity
+        let mut symbol_defs: HashMap<&str, &Entity> = HashMap::new();
+        for occ in self.occurrences.iter().filter(|o| o.is_definition) {
+            if let Some(def) = locator.defined_at(&occ.file, occ.line, &occ.symbol) {
+                symbol_defs.insert(occ.symbol.as_str(), def);
+            }
+        }
+
+        let mut seen: HashSet<(&str, &str)> = HashSet::new();
+        let mut relations = Vec::new();
+        for occ in self.occurrences.iter().filter(|o| !o.is_definition) {
+            let Some(target) = symbol_defs.get(occ.symbol.as_str()) else { continue };
+            let Some(source) = locator.enclosing(&occ.file, occ.line) else { continue };
+            if source.id == target.id || !seen.insert((&source.id, &target.id)) {
+                continue;
+            }
+            relations.push(Relation {
+                from_id: source.id.clone(),
+                to_id: target.id.clone(),
+                kind: use_relation_kind(target),
+            });
+        }
+        relations
+    }
+}
+
+/// Relation kind for a use of `target`: calls for callables, references otherwise.
+pub(crate) fn use_relation_kind(target: &Entity) -> RelationKind {
+    match target.kind {
+        EntityKind::Function | EntityKind::Method => RelationKind::Calls,
+        _ => RelationKind::References,
+    }
+}
+
+/// Maps (file, line) positions reported by external tools onto entities.
+pub(crate) struct EntityLocator<'a> {
+    by_file: HashMap<&'a str, Vec<&'a Entity>>,
+}
+
+impl<'a> EntityLocator<'a> {
+    pub(crate) fn new(entities: &'a [Entity]) -> Self {
+        let mut by_file: HashMap<&str, Vec<&Entity>> = HashMap::new();
+        for e in entities {
+            by_file.entry(e.file.as_str()).or_default().push(e);
+        }
+        EntityLocator { by_file }
+    }
+
+    /// Innermost entity whose line range encloses `line` (1-based).
+    pub(crate) fn enclosing(&self, file: &str, line: usize) -> Option<&'a Entity> {
+        self.by_file
+            .get(file)?
+            .iter()
+            .filter(|e| e.line <= line && line <= e.end_line)
+            .min_by_key(|e| e.end_line - e.line)
+            .copied()
+    }
+
+    /// Entity declared on `line`. Several entities can share a line
+    /// (`X, Y int`); prefer the one whose member name appears in `hint`.
+    pub(crate) fn defined_at(&self, file: &str, line: usize, hint: &str) -> Option<&'a Entity> {
+        let on_line: Vec<&'a Entity> = self
+            .by_file
+            .get(file)?
+            .iter()
+            .filter(|e| e.line == line)
+            .copied()
+            .collect();
+        on_line
+            .iter()
+            .find(|e| hint.contains(e.name.rsplit('.').next().unwrap_or(&e.name)))
+            .or_else(|| on_line.iter().min_by_key(|e| e.end_line - e.line))
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    fn fixture_entities() -> Vec<Entity> {
+        vec![
+            Entity::stub("a.go", "Run", EntityKind::Function, 3, 6),
+            Entity::stub("b.go", "Config", EntityKind::Struct, 1, 4),
+            Entity::stub("b.go", "Config.Timeout", EntityKind::Field, 2, 2),
+            Entity::stub("b.go", "Load", EntityKind::Function, 6, 8),
+        ]
+    }
+
+    #[test]
+    fn scip_occurrences_become_relations() {
+        let occ = |range: Vec<i32>, symbol: &str, roles: i32| scip::Occurrence {
+            range,
+            symbol: symbol.to_string(),
+            symbol_roles: roles,
+        };
+        let raw = scip::Index {
+            documents: vec![
+                scip::Document {
+                    relative_path: "a.go".to_string(),
+                    occurrences: vec![
+                        occ(vec![2, 5, 8], "go . m Run().", scip::ROLE_DEFINITION),
+                        occ(vec![3, 1, 5], "go . m Load().", 0),
+                        occ(vec![4, 1, 3], "local 1", 0),
+                        occ(vec![4, 4, 11], "go . m Config#Timeout.", 0),
+                    ],
+                },
+                scip::Document {
+                    relative_path: "b.go".to_string(),
+                    occurrences: vec![
+                        occ(vec![1, 1, 8], "go . m Config#Timeout.", scip::ROLE_DEFINITION),
+                        occ(vec![5, 5, 9], "go . m Load().", scip::ROLE_DEFINITION),
+                    ],
+                },
+            ],
+        };
+
+        let index = CodeIndex::from_scip(&raw.encode_to_vec()).unwrap();
+        assert!(index.covers("a.go") && !index.covers("c.go"));
+
+        let relations = index.build_relations(&fixture_entities());
+        let edges: HashSet<(&str, &str, RelationKind)> = relations
+            .iter()
+            .map(|r| (r.from_id.as_str(), r.to_id.as_str(), r.kind.clone()))
+            .collect();
+        assert_eq!(
+            edges,
+            HashSet::from([
+                ("a.go::function::Run", "b.go::function::Load", RelationKind::Calls),
+                ("a.go::function::Run", "b.go::field::Config.Timeout", RelationKind::References),
+            ])
+        );
+    }
+
+    #[test]
+    fn lsif_result_sets_group_ranges_into_symbols() {
+        let dump = r#"
+{"id":1,"type":"vertex","label":"metaData","projectRoot":"file:///repo"}
+{"id":2,"type":"vertex","label":"document","uri":"file:///repo/a.go"}
+{"id":3,"type":"vertex","label":"document","uri":"file:///repo/b.go"}
+{"id":4,"type":"vertex","label":"range","start":{"line":3,"character":1},"end":{"line":3,"character":5}}
+{"id":5,"type":"vertex","label":"range","start":{"line":5,"character":5},"end":{"line":5,"character":9}}
+{"id":6,"type":"vertex","label":"resultSet"}
+{"id":7,"type":"vertex","label":"definitionResult"}
+{"id":8,"type":"edge","label":"contains","outV":2,"inVs":[4]}
+{"id":9,"type":"edge","label":"contains","outV":3,"inVs":[5]}
+{"id":10,"type":"edge","label":"next","outV":4,"inV":6}
+{"id":11,"type":"edge","label":"next","outV":5,"inV":6}
+{"id":12,"type":"edge","label":"textDocument/definition","outV":6,"inV":7}
+{"id":13,"type":"edge","label":"item","outV":7,"inVs":[5],"document":3}
+"#;
+        let index = CodeIndex::from_lsif(dump, Path::new("/elsewhere")).unwrap();
+        assert!(index.covers("a.go") && index.covers("b.go"));
+
+        let relations = index.build_relations(&fixture_entities());
+        assert_eq!(relations.len(), 1);
+        assert_eq!(relations[0].from_id, "a.go::function::Run");
+        assert_eq!(relations[0].to_id, "b.go::function::Load");
+        assert_eq!(relations[0].kind, RelationKind::Calls);
+    }
+
+    #[test]
+    fn indexed_files_keep_source_references() {
+        let repo = std::env::temp_dir().join(format!("fode-index-refs-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&repo);
+        std::fs::create_dir_all(&repo).unwrap();
+        std::fs::write(repo.join("go.mod"), "module example.com/app\n").unwrap();
+        std::fs::write(
+            repo.join("a.go"),
+            "package app\n\nfunc Run() {\n\tLoad()\n\tlocal()\n}\n\nfunc Load() {}\n\nfunc local() {}\n",
+        )
+        .unwrap();
+
+        // The index knows the call to Load but not the one to local
+        let occ = |range: Vec<i32>, symbol: &str, roles: i32| scip::Occurrence {
+            range,
+            symbol: symbol.to_string(),
+            symbol_roles: roles,
+        };
+        let raw = scip::Index {
+            documents: vec![scip::Document {
+                relative_path: "a.go".to_string(),
+                occurrences: vec![
+                    occ(vec![2, 5, 8], "go . m Run().", scip::ROLE_DEFINITION),
+                    occ(vec![3, 1, 5], "go . m Load().", 0),
+                    occ(vec![7, 5, 9], "go . m Load().", scip::ROLE_DEFINITION),
+                ],
+            }],
+        };
+        let index = CodeIndex::from_scip(&raw.encode_to_vec()).unwrap();
+        let (_, graph) = parser::parse_repo(&repo, Some(&index)).unwrap();
+
+        let run = graph.entities.iter().find(|e| e.name == "Run").unwrap();
+        let located: Vec<&str> = run.references.iter().map(|r| &run.source[r.start..r.end]).collect();
+        assert_eq!(located, vec!["Load", "local"]);
+        // Relations come from the index alone
+        let targets: Vec<&str> = graph
+            .relations
+            .iter()
+            .filter(|r| r.from_id == run.id)
+            .map(|r| r.to_id.as_str())
+            .collect();
+        assert_eq!(targets, vec!["a.go::function::Load"]);
+
+        std::fs::remove_dir_all(&repo).unwrap();
+    }
+}
@@ -1,7 +1,23 @@
-use crate::parser::{self, Entity, EntityGraph, RelationKind, RepoInfo};
+use crate::api::{self, ApiDiff, ApiSurface};
+use crate::git::{self, ChangedLines, EntityHistory};
+use crate::highlight::{self, HighlightSpan};
+use crate::hotspots::{self, HotspotReport};
+use crate::impact::{self, ImpactReport};
+use crate::code_index::CodeIndex;
+use crate::config::{FodeConfig, UserConfig};
+use crate::dead;
+use crate::diff::{self, EntityDiff};
+use crate::duplicates::{self, DuplicateReport, SimilarEntity};
+use crate::lsp;
+use crate::metrics::{self, MetricsReport};
+use crate::ownership::{self, CodeOwners, EntityOwnership, OwnershipReport, PackageOwnership};
+use crate::parser::{
+    self, Entity, EntityGraph, RelationKind, RepoInfo, SourceReference, Visibility, WorkingTree,
+};
+use crate::test_map::{self, TestSelection, TestTarget};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use tauri::State;
 
@@ -9,6 +25,8 @@ pub struct AppState {
     pub repo_info: Mutex<Option<RepoInfo>>,
     pub entity_graph: Mutex<Option<EntityGraph>>,
     pub repo_path: Mutex<Option<PathBuf>>,
+    /// Git revision the repo was opened at; `None` for the working tree.
+    pub repo_rev: Mutex<Option<String>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -28,6 +46,24 @@ pub struct FocusView {
     pub same_module: Vec<ModulePkgGroup>,
     /// External module/package dependencies
     pub external_deps: Vec<String>,
+    /// Transitive supertypes (extends/implements/embeds), depth-first
+    pub ancestors: Vec<HierarchyEntry>,
+    /// Transitive subtypes, depth-first
+    pub descendants: Vec<HierarchyEntry>,
+    /// Tests reaching this entity through calls/references
+    pub tested_by: Vec<TestTarget>,
+    /// Functions with a near-identical body shape, most similar first
+    pub similar: Vec<SimilarEntity>,
+}
+
+/// One type in a hierarchy walk; `depth` 1 is a direct super/subtype.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HierarchyEntry {
+    pub id: String,
+    pub name: String,
+    pub kind: parser::EntityKind,
+    pub relation: String,
+    pub depth: usize,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -57,26 +93,84 @@ pub struct ModulePkgGroup {
     pub type_count: usize,
 }
 
+/// Parse a repository with its SCIP/LSIF index and language server, if any;
+/// `lsp_command` runs that server instead of the one in the user's settings.
+/// With `rev`, parses that git revision from the object database instead;
+/// the index and language server describe the working tree, so they are not
+/// used then. Shared by `open_repo` and the CLI.
+pub(crate) fn load_repo(
+    repo_path: &Path,
+    index_path: Option<PathBuf>,
+    rev: Option<&str>,
+    lsp_command: Option<&str>,
+) -> Result<(RepoInfo, EntityGraph), String> {
+    if let Some(rev) = rev {
+        return git::parse_revision(repo_path, rev);
+    }
+
+    let index_file = index_path.or_else(|| CodeIndex::find_in_repo(repo_path));
+    // A broken index should not prevent opening the repo
+    let index = index_file.and_then(|f| match CodeIndex::load(&f, repo_path) {
+        Ok(idx) => Some(idx),
+        Err(e) => {
+            eprintln!("[fode] ignoring index: {}", e);
+            None
+        }
+    });
+    let (info, mut graph) = parser::parse_repo(repo_path, index.as_ref())
+        .ok_or("Failed to parse repository. No supported language files found.")?;
+
+    // The language server refines heuristic edges; failures keep them as-is
+    if let Some(lang) = parser::detect_language(repo_path) {
+        let repo_config = FodeConfig::load(&WorkingTree(repo_path)).lsp.unwrap_or_default();
+        let server = lsp::ServerCommand::choose(&lang, lsp_command, &UserConfig::load(), &repo_config);
+        if let Some(server) = server {
+            if let Err(e) = lsp::resolve(repo_path, &server, &mut graph) {
+                eprintln!("[fode] language server skipped: {}", e);
+            }
+        }
+    }
+    Ok((info, graph))
+}
+
+/// The repo's `.fode.toml` as of `rev`, the revision its graph is parsed at,
+/// or from the working tree.
+pub(crate) fn load_config(repo_path: &Path, rev: Option<&str>) -> Result<FodeConfig, String> {
+    Ok(match rev {
+        Some(rev) => FodeConfig::load(&git::RevisionTree::open(repo_path, rev)?),
+        None => FodeConfig::load(&WorkingTree(repo_path)),
+    })
+}
+
+/// Open and parse a repository. `index_path` optionally points at a SCIP or
+/// LSIF index; otherwise `index.scip`/`dump.lsif` in the repo root is used
+/// when present. `rev` opens a branch, tag or commit without checking it out.
 #[tauri::command]
-pub async fn open_repo(path: String, state: State<'_, AppState>) -> Result<RepoInfo, String> {
+pub async fn open_repo(
+    path: String,
+    index_path: Option<String>,
+    rev: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<RepoInfo, String> {
     let repo_path = PathBuf::from(&path);
     if !repo_path.exists() {
         return Err(format!("Path does not exist: {}", path));
     }
 
-    eprintln!("[fode] open_repo command called with: {}", path);
+    eprintln!("[fode] open_repo command called with: {} {:?}", path, rev);
 
     // Run parsing on a blocking thread so we don't freeze the UI
+    let parse_rev = rev.clone();
     let (info, graph) = tokio::task::spawn_blocking(move || {
-        parser::parse_repo(&repo_path)
+        load_repo(&repo_path, index_path.map(PathBuf::from), parse_rev.as_deref(), None)
     })
     .await
-    .map_err(|e| format!("Parse task failed: {}", e))?
-    .ok_or_else(|| "Failed to parse repository. No supported language files found.".to_string())?;
+    .map_err(|e| format!("Parse task failed: {}", e))??;
 
     *state.repo_info.lock().unwrap() = Some(info.clone());
     *state.entity_graph.lock().unwrap() = Some(graph);
     *state.repo_path.lock().unwrap() = Some(PathBuf::from(&path));
+    *state.repo_rev.lock().unwrap() = rev;
 
     eprintln!("[fode] open_repo complete: {} entities", info.total_entities);
     Ok(info)
@@ -92,23 +186,38 @@ pub fn get_repo_info(state: State<AppState>) -> Result<RepoInfo, String> {
         .ok_or_else(|| "No repo loaded".to_string())
 }
 
+/// Whether an entity passes an optional visibility filter; `None` keeps all.
+fn visibility_matches(filter: &Option<Vec<Visibility>>, entity: &Entity) -> bool {
+    filter.as_ref().is_none_or(|allowed| allowed.contains(&entity.visibility))
+}
+
 #[tauri::command]
-pub fn search_entities(query: String, state: State<AppState>) -> Result<Vec<SearchResult>, String> {
+pub fn search_entities(
+    query: String,
+    visibility: Option<Vec<Visibility>>,
+    state: State<AppState>,
+) -> Result<Vec<SearchResult>, String> {
     let graph = state.entity_graph.lock().unwrap();
     let graph = graph.as_ref().ok_or("No repo loaded")?;
 
     let query_lower = query.to_lowercase();
     let mut results: Vec<SearchResult> = Vec::new();
 
-    for entity in &graph.entities {
+    for entity in graph.entities.iter().filter(|e| visibility_matches(&visibility, e)) {
         let name_lower = entity.name.to_lowercase();
         let kind_label = entity.kind.label().to_lowercase();
+        // Member part of qualified names like `Server.Start`
+        let member_lower = name_lower.rsplit('.').next().unwrap_or(&name_lower);
 
         // Score based on match quality
         let score = if name_lower == query_lower {
             1.0 // Exact match
+        } else if member_lower == query_lower {
+            0.95 // Exact member match
         } else if name_lower.starts_with(&query_lower) {
             0.9 // Prefix match
+        } else if member_lower.starts_with(&query_lower) {
+            0.8 // Member prefix match
         } else if name_lower.contains(&query_lower) {
             0.7 // Substring match
         } else if kind_label.contains(&query_lower) {
@@ -133,7 +242,11 @@ pub fn search_entities(query: String, state: State<AppState>) -> Result<Vec<Sear
 }
 
 #[tauri::command]
-pub fn get_entity_focus(entity_id: String, state: State<AppState>) -> Result<FocusView, String> {
+pub fn get_entity_focus(
+    entity_id: String,
+    visibility: Option<Vec<Visibility>>,
+    state: State<AppState>,
+) -> Result<FocusView, String> {
     let graph = state.entity_graph.lock().unwrap();
     let graph = graph.as_ref().ok_or("No repo loaded")?;
 
@@ -158,12 +271,15 @@ pub fn get_entity_focus(entity_id: String, state: State<AppState>) -> Result<Foc
             outgoing_ids.push(relation.to_id.clone());
         }
         if relation.to_id == entity_id {
-            if let Some(source) = graph.entities.iter().find(|e| e.id == relation.from_id) {
+            let source = graph.entities.iter().find(|e| e.id == relation.from_id);
+            if let Some(source) = source.filter(|e| visibility_matches(&visibility, e)) {
                 let relation_label = match &relation.kind {
                     RelationKind::Calls => "called by",
                     RelationKind::References => "referenced by",
                     RelationKind::Contains => "contained in",
                     RelationKind::Implements => "implemented by",
+                    RelationKind::Extends => "extended by",
+                    RelationKind::Embeds => "embedded in",
                     RelationKind::Returns => "returned by",
                     RelationKind::Accepts => "accepted by",
                     _ => "related to",
@@ -182,7 +298,8 @@ pub fn get_entity_focus(entity_id: String, state: State<AppState>) -> Result<Foc
     let mut cross_pkg_counts: HashMap<String, (usize, usize)> = HashMap::new();
 
     for target_id in &outgoing_ids {
-        if let Some(target) = graph.entities.iter().find(|e| e.id == *target_id) {
+        let target = graph.entities.iter().find(|e| e.id == *target_id);
+        if let Some(target) = target.filter(|e| visibility_matches(&visibility, e)) {
             let target_dir = std::path::Path::new(&target.file)
                 .parent()
                 .and_then(|p| p.to_str())
@@ -228,15 +345,73 @@ pub fn get_entity_focus(entity_id: String, state: State<AppState>) -> Result<Foc
         .cloned()
         .unwrap_or_default();
 
+    let ancestors = type_hierarchy(graph, &entity_id, true);
+    let descendants = type_hierarchy(graph, &entity_id, false);
+    let tested_by = test_map::tests_reaching(graph, &entity_id);
+    let similar = duplicates::similar_to(graph, &center, duplicates::DEFAULT_SIMILARITY);
+
     Ok(FocusView {
         center,
         incoming,
         same_pkg,
         same_module,
         external_deps,
+        ancestors,
+        descendants,
+        tested_by,
+        similar,
     })
 }
 
+fn is_inheritance(kind: &RelationKind) -> bool {
+    matches!(kind, RelationKind::Extends | RelationKind::Implements | RelationKind::Embeds)
+}
+
+/// Walk inheritance edges from `root` up (supertypes) or down (subtypes).
+/// Entries come out depth-first so they render as an indented tree; a type
+/// reached twice (diamonds) is listed once.
+pub(crate) fn type_hierarchy(graph: &EntityGraph, root: &str, upward: bool) -> Vec<HierarchyEntry> {
+    let mut edges: HashMap<&str, Vec<(&str, &RelationKind)>> = HashMap::new();
+    for r in graph.relations.iter().filter(|r| is_inheritance(&r.kind)) {
+        let (from, to) = if upward { (&r.from_id, &r.to_id) } else { (&r.to_id, &r.from_id) };
+        edges.entry(from.as_str()).or_default().push((to.as_str(), &r.kind));
+    }
+    let by_id: HashMap<&str, &Entity> = graph.entities.iter().map(|e| (e.id.as_str(), e)).collect();
+
+    let mut out = Vec::new();
+    let mut visited: HashSet<&str> = HashSet::new();
+    // (id, edge kind that reached it, depth); the root has no edge
+    let mut stack: Vec<(&str, Option<&RelationKind>, usize)> = vec![(root, None, 0)];
+
+    while let Some((id, kind, depth)) = stack.pop() {
+        if !visited.insert(id) {
+            continue;
+        }
+        if let (Some(kind), Some(entity)) = (kind, by_id.get(id)) {
+            let relation = match (kind, upward) {
+                (RelationKind::Extends, true) => "extends",
+                (RelationKind::Extends, false) => "extended by",
+                (RelationKind::Implements, true) => "implements",
+                (RelationKind::Implements, false) => "implemented by",
+                (_, true) => "embeds",
+                (_, false) => "embedded in",
+            };
+            out.push(HierarchyEntry {
+                id: entity.id.clone(),
+                name: entity.name.clone(),
+                kind: entity.kind.clone(),
+                relation: relation.to_string(),
+                depth,
+            });
+        }
+        // Push in reverse so children pop in declaration order
+        for (to, kind) in edges.get(id).into_iter().flatten().rev() {
+            stack.push((to, Some(kind), depth + 1));
+        }
+    }
+    out
+}
+
 // === Graph visualization data (lightweight, no source code) ===
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -247,6 +422,8 @@ pub struct GraphNode {
     pub package: String,
     pub file: String,
     pub line: usize,
+    /// `None` for package nodes.
+    pub visibility: Option<Visibility>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -272,13 +449,17 @@ pub struct GraphData {
 }
 
 #[tauri::command]
-pub fn get_graph_data(state: State<AppState>) -> Result<GraphData, String> {
+pub fn get_graph_data(
+    visibility: Option<Vec<Visibility>>,
+    state: State<AppState>,
+) -> Result<GraphData, String> {
     let graph = state.entity_graph.lock().unwrap();
     let graph = graph.as_ref().ok_or("No repo loaded")?;
 
     let mut nodes: Vec<GraphNode> = graph
         .entities
         .iter()
+        .filter(|e| visibility_matches(&visibility, e))
         .map(|e| GraphNode {
             id: e.id.clone(),
             name: e.name.clone(),
@@ -286,6 +467,7 @@ pub fn get_graph_data(state: State<AppState>) -> Result<GraphData, String> {
             package: e.package.clone(),
             file: e.file.clone(),
             line: e.line,
+            visibility: Some(e.visibility),
         })
         .collect();
 
@@ -315,6 +497,7 @@ pub fn get_graph_data(state: State<AppState>) -> Result<GraphData, String> {
             package: pkg.clone(),
             file: String::new(),
             line: 0,
+            visibility: None,
         });
     }
 
@@ -334,7 +517,7 @@ pub fn get_graph_data(state: State<AppState>) -> Result<GraphData, String> {
         .collect();
 
     // Add Contains edges from package nodes to their member entities
-    for e in &graph.entities {
+    for e in graph.entities.iter().filter(|e| visibility_matches(&visibility, e)) {
         if !e.package.is_empty() {
             edges.push(GraphEdge {
                 source: format!("pkg::{}", e.package),
@@ -381,14 +564,70 @@ pub fn get_graph_data(state: State<AppState>) -> Result<GraphData, String> {
 }
 
 #[tauri::command]
-pub fn get_all_entities(state: State<AppState>) -> Result<Vec<Entity>, String> {
+pub fn get_all_entities(
+    visibility: Option<Vec<Visibility>>,
+    dead_only: Option<bool>,
+    state: State<AppState>,
+) -> Result<Vec<Entity>, String> {
+    let graph = state.entity_graph.lock().unwrap();
+    let graph = graph.as_ref().ok_or("No repo loaded")?;
+    let entities: Vec<&Entity> = if dead_only.unwrap_or(false) {
+        let repo_path = state.repo_path.lock().unwrap().clone().ok_or("No repo loaded")?;
+        let rev = state.repo_rev.lock().unwrap().clone();
+        dead::dead_code(graph, &load_config(&repo_path, rev.as_deref())?.dead_code)
+    } else {
+        graph.entities.iter().collect()
+    };
+    Ok(entities
+        .into_iter()
+        .filter(|e| visibility_matches(&visibility, e))
+        .cloned()
+        .collect())
+}
+
+/// Size, complexity and coupling of every entity, plus package aggregates.
+#[tauri::command]
+pub fn get_metrics(visibility: Option<Vec<Visibility>>, state: State<AppState>) -> Result<MetricsReport, String> {
+    let graph = state.entity_graph.lock().unwrap();
+    let graph = graph.as_ref().ok_or("No repo loaded")?;
+    Ok(metrics::metrics(graph, graph.entities.iter().filter(|e| visibility_matches(&visibility, e))))
+}
+
+/// Clusters of functions with near-identical bodies; `min_similarity` is a
+/// Jaccard index in 0..=1.
+#[tauri::command]
+pub fn get_duplicates(min_similarity: Option<f64>, state: State<AppState>) -> Result<DuplicateReport, String> {
     let graph = state.entity_graph.lock().unwrap();
     let graph = graph.as_ref().ok_or("No repo loaded")?;
-    Ok(graph.entities.clone())
+    Ok(duplicates::duplicates(graph, min_similarity.unwrap_or(duplicates::DEFAULT_SIMILARITY)))
+}
+
+/// An entity's source as parsed, with highlight spans when asked for.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EntitySource {
+    pub source: String,
+    /// Byte ranges of `source` with their highlight capture.
+    pub highlights: Vec<HighlightSpan>,
+    /// Byte ranges of `source` that resolved to another entity.
+    pub references: Vec<SourceReference>,
 }
 
+/// The text of `file` in the open repo: at `rev`, or in the working tree.
+fn read_repo_file(repo_path: &Path, rev: Option<&str>, file: &str) -> Option<String> {
+    match rev {
+        // `<rev>:./<path>` is relative to the directory git runs in
+        Some(rev) => git::run(repo_path, &["show", &format!("{}:./{}", rev, file)]).ok(),
+        None => std::fs::read_to_string(repo_path.join(file)).ok(),
+    }
+}
+
+/// Source of an entity with the names in it that resolve to other entities;
+/// `highlight` adds spans from the grammar's highlight query so the UI can
+/// color it.
 #[tauri::command]
-pub fn get_entity_source(entity_id: String, state: State<AppState>) -> Result<String, String> {
+pub fn get_entity_source(entity_id: String, highlight: Option<bool>, state: State<AppState>) -> Result<EntitySource, String> {
+    let repo_path = state.repo_path.lock().unwrap().clone();
+    let rev = state.repo_rev.lock().unwrap().clone();
     let graph = state.entity_graph.lock().unwrap();
     let graph = graph.as_ref().ok_or("No repo loaded")?;
     let entity = graph
@@ -396,5 +635,276 @@ pub fn get_entity_source(entity_id: String, state: State<AppState>) -> Result<St
         .iter()
         .find(|e| e.id == entity_id)
         .ok_or("Entity not found")?;
-    Ok(entity.source.clone())
+
+    let highlights = if highlight.unwrap_or(false) {
+        let file = repo_path.and_then(|repo| read_repo_file(&repo, rev.as_deref(), &entity.file));
+        highlight::highlight_entity(entity, file.as_deref())
+    } else {
+        Vec::new()
+    };
+    Ok(EntitySource {
+        source: entity.source.clone(),
+        highlights,
+        references: entity.references.clone(),
+    })
+}
+
+/// Exported API of a package, given as a directory path or package name.
+#[tauri::command]
+pub fn get_api_surface(package: String, state: State<AppState>) -> Result<ApiSurface, String> {
+    let graph = state.entity_graph.lock().unwrap();
+    let graph = graph.as_ref().ok_or("No repo loaded")?;
+    api::api_surface(graph, &package)
+}
+
+/// Parse `base` and `head` (git revisions of the open repo) off the UI
+/// thread. Without `head`, the loaded repo stands in for it.
+async fn revision_graphs(
+    base: &str,
+    head: Option<String>,
+    state: &State<'_, AppState>,
+) -> Result<(EntityGraph, EntityGraph, String), String> {
+    let repo_path = state.repo_path.lock().unwrap().clone().ok_or("No repo loaded")?;
+    let working = match head {
+        Some(_) => None,
+        None => Some(state.entity_graph.lock().unwrap().clone().ok_or("No repo loaded")?),
+    };
+    let head = head.or_else(|| state.repo_rev.lock().unwrap().clone());
+    let base = base.to_string();
+
+    tokio::task::spawn_blocking(move || {
+        let (_, old) = git::parse_revision(&repo_path, &base)?;
+        let new = match working {
+            Some(graph) => graph,
+            None => git::parse_revision(&repo_path, head.as_deref().unwrap_or("HEAD"))?.1,
+        };
+        Ok((old, new, head.unwrap_or_else(|| "working tree".to_string())))
+    })
+    .await
+    .map_err(|e| format!("Parse task failed: {}", e))?
+}
+
+/// Exported API changes between `base` and `head`.
+#[tauri::command]
+pub async fn get_api_diff(
+    base: String,
+    head: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ApiDiff, String> {
+    let (old, new, head) = revision_graphs(&base, head, &state).await?;
+    Ok(api::api_diff(&base, &head, &old, &new))
+}
+
+/// Entities added, deleted, moved or modified between `base` and `head`,
+/// with per-entity source diffs.
+#[tauri::command]
+pub async fn get_entity_diff(
+    base: String,
+    head: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<EntityDiff, String> {
+    let (old, new, head) = revision_graphs(&base, head, &state).await?;
+    Ok(diff::diff_graphs(&base, &head, &old, &new))
+}
+
+/// Commits that touched an entity's lines and who wrote its current body.
+#[tauri::command]
+pub async fn get_entity_history(entity_id: String, state: State<'_, AppState>) -> Result<EntityHistory, String> {
+    let repo_path = state.repo_path.lock().unwrap().clone().ok_or("No repo loaded")?;
+    let rev = state.repo_rev.lock().unwrap().clone();
+    let entity = {
+        let graph = state.entity_graph.lock().unwrap();
+        let graph = graph.as_ref().ok_or("No repo loaded")?;
+        graph
+            .entities
+            .iter()
+            .find(|e| e.id == entity_id)
+            .cloned()
+            .ok_or_else(|| format!("Entity not found: {}", entity_id))?
+    };
+
+    tokio::task::spawn_blocking(move || git::entity_history(&repo_path, &entity, rev.as_deref()))
+        .await
+        .map_err(|e| format!("History task failed: {}", e))?
+}
+
+/// Ownership of an entity and of its package, for the focus view header.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EntityOwners {
+    pub entity: EntityOwnership,
+    pub package: PackageOwnership,
+}
+
+/// Who owns an entity and its package, from CODEOWNERS and `git blame`.
+#[tauri::command]
+pub async fn get_entity_owners(entity_id: String, state: State<'_, AppState>) -> Result<EntityOwners, String> {
+    let repo_path = state.repo_path.lock().unwrap().clone().ok_or("No repo loaded")?;
+    let rev = state.repo_rev.lock().unwrap().clone();
+    let (entity, files) = {
+        let graph = state.entity_graph.lock().unwrap();
+        let graph = graph.as_ref().ok_or("No repo loaded")?;
+        let entity = graph
+            .entities
+            .iter()
+            .find(|e| e.id == entity_id)
+            .cloned()
+            .ok_or_else(|| format!("Entity not found: {}", entity_id))?;
+        let files: Vec<String> =
+            ownership::package_files(graph, &parser::file_dir(&entity.file)).into_iter().map(str::to_string).collect();
+        (entity, files)
+    };
+
+    tokio::task::spawn_blocking(move || {
+        let code_owners = CodeOwners::load(&repo_path, rev.as_deref());
+        let files: Vec<&str> = files.iter().map(String::as_str).collect();
+        let blame = ownership::blame_files(&repo_path, rev.as_deref(), files.iter().copied());
+        let package = ownership::package_ownership(&parser::file_dir(&entity.file), &files, &blame, &code_owners);
+        EntityOwners { entity: ownership::entity_ownership(&entity, &blame, &code_owners), package }
+    })
+    .await
+    .map_err(|e| format!("Ownership task failed: {}", e))
+}
+
+/// Ownership of every package and entity. Blames every file, so slow on
+/// large repos.
+#[tauri::command]
+pub async fn get_ownership(state: State<'_, AppState>) -> Result<OwnershipReport, String> {
+    let repo_path = state.repo_path.lock().unwrap().clone().ok_or("No repo loaded")?;
+    let rev = state.repo_rev.lock().unwrap().clone();
+    let graph = state.entity_graph.lock().unwrap().clone().ok_or("No repo loaded")?;
+
+    tokio::task::spawn_blocking(move || {
+        let code_owners = CodeOwners::load(&repo_path, rev.as_deref());
+        let blame = ownership::blame_files(&repo_path, rev.as_deref(), graph.entities.iter().map(|e| e.file.as_str()));
+        ownership::ownership(&graph, &blame, &code_owners)
+    })
+    .await
+    .map_err(|e| format!("Ownership task failed: {}", e))
+}
+
+/// Run `f` over the lines changed in `range` (see `git::changed_lines`) and
+/// the graph of the revision on the new side of that diff. `loaded` is an
+/// already parsed graph and the revision it was parsed at, reused when it is
+/// that side.
+fn with_changes<T>(
+    repo_path: &Path,
+    range: Option<&str>,
+    loaded: Option<(Option<&str>, &EntityGraph)>,
+    f: impl FnOnce(&EntityGraph, &ChangedLines) -> T,
+) -> Result<T, String> {
+    // "A..B" and "A...B" end at B (HEAD when empty); a single revision or
+    // no range at all compares against the working tree
+    let new_side = range
+        .and_then(|r| r.split_once(".."))
+        .map(|(_, head)| head.trim_start_matches('.'))
+        .map(|head| if head.is_empty() { "HEAD" } else { head });
+    let lines = git::changed_lines(repo_path, range)?;
+
+    let parsed;
+    let graph = match loaded {
+        Some((rev, graph)) if rev == new_side => graph,
+        _ => {
+            parsed = load_repo(repo_path, None, new_side, None)?.1;
+            &parsed
+        }
+    };
+    Ok(f(graph, &lines))
+}
+
+/// Impact of the changes in `range`; see `with_changes`.
+pub(crate) fn change_impact(
+    repo_path: &Path,
+    range: Option<&str>,
+    max_depth: Option<usize>,
+    loaded: Option<(Option<&str>, &EntityGraph)>,
+) -> Result<ImpactReport, String> {
+    with_changes(repo_path, range, loaded, |graph, lines| {
+        impact::analyze(graph, range.unwrap_or("working tree"), lines, max_depth)
+    })
+}
+
+/// Tests to run for the changes in `range`; see `with_changes`.
+pub(crate) fn test_selection(
+    repo_path: &Path,
+    range: Option<&str>,
+    loaded: Option<(Option<&str>, &EntityGraph)>,
+) -> Result<TestSelection, String> {
+    with_changes(repo_path, range, loaded, |graph, lines| {
+        let changed: Vec<String> = impact::changed_entities(graph, lines)
+            .into_iter()
+            .map(|e| e.id.clone())
+            .collect();
+        test_map::select_tests(graph, &changed)
+    })
+}
+
+/// Entities changed in `range` (uncommitted changes when `None`) and the
+/// callers and tests they affect, up to `max_depth` steps away.
+#[tauri::command]
+pub async fn get_change_impact(
+    range: Option<String>,
+    max_depth: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<ImpactReport, String> {
+    let repo_path = state.repo_path.lock().unwrap().clone().ok_or("No repo loaded")?;
+    let rev = state.repo_rev.lock().unwrap().clone();
+    let graph = state.entity_graph.lock().unwrap().clone().ok_or("No repo loaded")?;
+
+    tokio::task::spawn_blocking(move || {
+        change_impact(&repo_path, range.as_deref(), max_depth, Some((rev.as_deref(), &graph)))
+    })
+    .await
+    .map_err(|e| format!("Impact task failed: {}", e))?
+}
+
+/// The tests worth running: those reaching `entity_ids`, or when not given,
+/// the entities changed in `range` (uncommitted changes when `None`).
+#[tauri::command]
+pub async fn get_test_selection(
+    range: Option<String>,
+    entity_ids: Option<Vec<String>>,
+    state: State<'_, AppState>,
+) -> Result<TestSelection, String> {
+    let repo_path = state.repo_path.lock().unwrap().clone().ok_or("No repo loaded")?;
+    let rev = state.repo_rev.lock().unwrap().clone();
+    let graph = state.entity_graph.lock().unwrap().clone().ok_or("No repo loaded")?;
+
+    if let Some(ids) = entity_ids {
+        return Ok(test_map::select_tests(&graph, &ids));
+    }
+    tokio::task::spawn_blocking(move || {
+        test_selection(&repo_path, range.as_deref(), Some((rev.as_deref(), &graph)))
+    })
+    .await
+    .map_err(|e| format!("Test selection task failed: {}", e))?
+}
+
+/// Every test with the production entities it reaches.
+#[tauri::command]
+pub fn get_test_map(state: State<AppState>) -> Result<Vec<TestTarget>, String> {
+    let graph = state.entity_graph.lock().unwrap();
+    let graph = graph.as_ref().ok_or("No repo loaded")?;
+    Ok(test_map::test_map(graph))
+}
+
+/// Functions ranked by commits since `since` (a `git log --since` date,
+/// default `hotspots::DEFAULT_SINCE`) times complexity; all of them when
+/// `limit` is `None`.
+#[tauri::command]
+pub async fn get_hotspots(
+    since: Option<String>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<HotspotReport, String> {
+    let repo_path = state.repo_path.lock().unwrap().clone().ok_or("No repo loaded")?;
+    let rev = state.repo_rev.lock().unwrap().clone();
+    let graph = state.entity_graph.lock().unwrap().clone().ok_or("No repo loaded")?;
+    let since = since.unwrap_or_else(|| hotspots::DEFAULT_SINCE.to_string());
+
+    tokio::task::spawn_blocking(move || {
+        let commits = git::log_changes(&repo_path, rev.as_deref(), &since)?;
+        Ok(hotspots::hotspots(&graph, &commits, &since, limit))
+    })
+    .await
+    .map_err(|e| format!("Hotspot task failed: {}", e))?
 }
@@ -0,0 +1,211 @@
+//! Per-repository settings read from `.fode.toml` in the repo root (at the
+//! revision the repo is opened at), and
+//! per-user settings from `~/.config/fode/config.toml`.
+//!
+//! A repository is untrusted input, so nothing in `.fode.toml` may start a
+//! program; that is only taken from the user's own settings.
+
+use crate::parser::SourceTree;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+pub const CONFIG_FILE: &str = ".fode.toml";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FodeConfig {
+    /// Tuning for a language server the user has enabled.
+    #[serde(default)]
+    pub lsp: Option<RepoLspConfig>,
+    #[serde(default)]
+    pub dead_code: DeadCodeConfig,
+    #[serde(default)]
+    pub dependencies: DependencyConfig,
+}
+
+/// `[lsp]` section of `.fode.toml`. Only applies when the user runs a
+/// server for the repo's language; it never starts one.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RepoLspConfig {
+    /// Per-request timeout in seconds, e.g. for a large repo.
+    pub timeout_secs: Option<u64>,
+}
+
+/// A language server from the user's settings, `[lsp.<language>]`. An
+/// empty section enables the default server for that language (gopls,
+/// rust-analyzer, pyright, typescript-language-server).
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LspConfig {
+    /// Server executable; defaults per language when omitted.
+    pub command: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Per-request timeout in seconds.
+    pub timeout_secs: Option<u64>,
+}
+
+/// Settings of the user running fode, for every repository they open.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UserConfig {
+    /// Language servers by lowercase language name, e.g. `[lsp.go]`.
+    #[serde(default)]
+    pub lsp: HashMap<String, LspConfig>,
+}
+
+/// `[dead_code]` section: what counts as used without being called or
+/// referenced. Patterns use `*` as a wildcard.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DeadCodeConfig {
+    /// Entry points, matched against entity names and IDs.
+    pub roots: Vec<String>,
+    /// Exported API is used by other modules.
+    pub exported: bool,
+    /// Decorated entities are registered with a framework (routes, commands).
+    pub decorated: bool,
+    /// Trait/interface implementations and overrides are called dynamically.
+    pub trait_impls: bool,
+    /// Files left out of the report, e.g. generated code.
+    pub ignore: Vec<String>,
+}
+
+impl Default for DeadCodeConfig {
+    fn default() -> Self {
+        DeadCodeConfig {
+            roots: vec!["main".to_string(), "init".to_string()],
+            exported: true,
+            decorated: true,
+            trait_impls: true,
+            ignore: Vec::new(),
+        }
+    }
+}
+
+/// `[dependencies]` section: rules `fode check` enforces on the package
+/// dependency graph.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct DependencyConfig {
+    /// Tolerate dependency cycles between packages.
+    pub allow_cycles: bool,
+    /// `[[dependencies.forbid]]` entries.
+    pub forbid: Vec<LayerRule>,
+}
+
+/// Packages matching `from` may not depend on packages matching `to`.
+/// Both are package directories, with `*` as a wildcard.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayerRule {
+    pub from: String,
+    pub to: String,
+    /// Shown with violations, e.g. "api goes through service".
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// Match `text` against a pattern where `*` stands for any run of characters.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// Parse a TOML settings file read from `path`. A missing file yields the
+/// defaults; a malformed one is reported and ignored.
+fn load_toml<T: Default + for<'de> Deserialize<'de>>(text: std::io::Result<String>, path: &Path) -> T {
+    let Ok(text) = text else {
+        return T::default();
+    };
+    match toml::from_str(&text) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("[fode] ignoring {}: {}", path.display(), e);
+            T::default()
+        }
+    }
+}
+
+impl FodeConfig {
+    /// Load `.fode.toml` from the root of `tree`, the same tree the graph
+    /// was parsed from.
+    pub fn load(tree: &impl SourceTree) -> FodeConfig {
+        load_toml(tree.read(Path::new(CONFIG_FILE)), &tree.root().join(CONFIG_FILE))
+    }
+}
+
+impl UserConfig {
+    /// `$XDG_CONFIG_HOME/fode/config.toml`, else `~/.config/fode/config.toml`.
+    pub fn path() -> Option<PathBuf> {
+        let base = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(base.join("fode").join("config.toml"))
+    }
+
+    pub fn load() -> UserConfig {
+        UserConfig::path()
+            .map(|path| load_toml(std::fs::read_to_string(&path), &path))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs_match_names() {
+        assert!(glob_match("main", "main"));
+        assert!(!glob_match("main", "domain"));
+        assert!(glob_match("Handle*", "HandleGet"));
+        assert!(glob_match("*_handler", "get_handler"));
+        assert!(glob_match("gen/*.go", "gen/a/b.go"));
+        assert!(!glob_match("a*b*a", "ab"));
+    }
+
+    #[test]
+    fn sections_default_when_absent() {
+        let config: FodeConfig = toml::from_str(
+            r#"
+            [dependencies]
+            [[dependencies.forbid]]
+            from = "api"
+            to = "storage"
+            reason = "go through service"
+            "#,
+        )
+        .unwrap();
+        assert!(config.lsp.is_none());
+        assert_eq!(config.dead_code.roots, vec!["main", "init"]);
+        assert!(!config.dependencies.allow_cycles);
+        assert_eq!(config.dependencies.forbid[0].to, "storage");
+    }
+
+    #[test]
+    fn user_servers_are_keyed_by_language() {
+        let config: UserConfig = toml::from_str(
+            r#"
+            [lsp.python]
+            command = "basedpyright-langserver"
+            args = ["--stdio"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.lsp["python"].command.as_deref(), Some("basedpyright-langserver"));
+        assert!(!config.lsp.contains_key("go"));
+    }
+}
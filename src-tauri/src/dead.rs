@@ -0,0 +1,251 @@
+//! Dead code: entities nothing outside test code calls or references, less
+//! the roots a program is entered through (see `DeadCodeConfig`).
+
+use crate::config::{glob_match, DeadCodeConfig};
+use crate::parser::{file_dir, Entity, EntityGraph, EntityKind, RelationKind, Visibility};
+use std::collections::{HashMap, HashSet};
+
+fn short_name(name: &str) -> &str {
+    name.rsplit('.').next().unwrap_or(name)
+}
+
+/// Kinds worth reporting; imports, packages, modules and fields are left out
+/// since uses of them are not tracked as relations.
+fn is_candidate(kind: &EntityKind) -> bool {
+    !matches!(kind, EntityKind::Import | EntityKind::Package | EntityKind::Module | EntityKind::Field)
+}
+
+/// Methods reached through dynamic dispatch: Rust trait impls, Python
+/// dunders, Go methods named like an interface method, and overrides of a
+/// member of any supertype.
+struct TraitImpls<'a> {
+    /// (dir, type name) -> type entity ID
+    types: HashMap<(String, &'a str), &'a str>,
+    /// type entity ID -> short names of its members
+    members: HashMap<&'a str, HashSet<&'a str>>,
+    /// type entity ID -> supertype IDs
+    supertypes: HashMap<&'a str, Vec<&'a str>>,
+    /// Short names of Go interface methods
+    interface_methods: HashSet<&'a str>,
+}
+
+impl<'a> TraitImpls<'a> {
+    fn new(graph: &'a EntityGraph) -> Self {
+        let types: HashMap<(String, &str), &str> = graph
+            .entities
+            .iter()
+            .filter(|e| e.kind.is_type())
+            .map(|e| ((file_dir(&e.file), e.name.as_str()), e.id.as_str()))
+            .collect();
+        let interfaces: HashSet<&str> = graph
+            .entities
+            .iter()
+            .filter(|e| e.kind == EntityKind::Interface)
+            .map(|e| e.id.as_str())
+            .collect();
+
+        let mut members: HashMap<&str, HashSet<&str>> = HashMap::new();
+        let mut interface_methods = HashSet::new();
+        for e in &graph.entities {
+            let Some(owner) = e.owner.as_deref() else { continue };
+            let Some(&type_id) = types.get(&(file_dir(&e.file), owner)) else { continue };
+            members.entry(type_id).or_default().insert(short_name(&e.name));
+            if interfaces.contains(type_id) && e.file.ends_with(".go") {
+                interface_methods.insert(short_name(&e.name));
+            }
+        }
+
+        let mut supertypes: HashMap<&str, Vec<&str>> = HashMap::new();
+        for r in &graph.relations {
+            if matches!(r.kind, RelationKind::Extends | RelationKind::Implements | RelationKind::Embeds) {
+                supertypes.entry(r.from_id.as_str()).or_default().push(r.to_id.as_str());
+            }
+        }
+        TraitImpls { types, members, supertypes, interface_methods }
+    }
+
+    fn owner_id(&self, e: &'a Entity) -> Option<&'a str> {
+        let owner = e.owner.as_deref()?;
+        self.types.get(&(file_dir(&e.file), owner)).copied()
+    }
+
+    fn contains(&self, e: &'a Entity) -> bool {
+        if e.kind != EntityKind::Method {
+            return false;
+        }
+        let name = short_name(&e.name);
+        // Items of `impl Trait for T` have no `pub` yet take the trait's visibility
+        if e.file.ends_with(".rs") && e.visibility == Visibility::Public && !e.signature.starts_with("pub") {
+            return true;
+        }
+        if e.file.ends_with(".py") && name.len() > 4 && name.starts_with("__") && name.ends_with("__") {
+            return true;
+        }
+        if e.file.ends_with(".go") && self.interface_methods.contains(name) {
+            return true;
+        }
+
+        let Some(owner) = self.owner_id(e) else {
+            return false;
+        };
+        let mut seen: HashSet<&str> = HashSet::from([owner]);
+        let mut stack: Vec<&str> = self.supertypes.get(owner).cloned().unwrap_or_default();
+        while let Some(ty) = stack.pop() {
+            if !seen.insert(ty) {
+                continue;
+            }
+            if self.members.get(ty).is_some_and(|m| m.contains(name)) {
+                return true;
+            }
+            stack.extend(self.supertypes.get(ty).into_iter().flatten());
+        }
+        false
+    }
+}
+
+/// Whether `e` is a root under `config`: a program is entered through it,
+/// so it is used even without incoming relations.
+fn is_root(e: &Entity, config: &DeadCodeConfig, trait_impls: &TraitImpls) -> bool {
+    config
+        .roots
+        .iter()
+        .any(|p| glob_match(p, &e.name) || glob_match(p, short_name(&e.name)) || glob_match(p, &e.id))
+        || (config.exported && e.visibility == Visibility::Public)
+        || (config.decorated && !e.decorators.is_empty())
+        || (config.trait_impls && trait_impls.contains(e))
+}
+
+/// Entities with no incoming Calls or References from outside test code and
+/// that are not roots, ordered by file and line. Inheritance counts as a use,
+/// so a base type that is only extended is not dead. Members of interfaces
+/// and traits are never reported: calls to them resolve to implementations.
+pub fn dead_code<'a>(graph: &'a EntityGraph, config: &DeadCodeConfig) -> Vec<&'a Entity> {
+    let by_id: HashMap<&str, &Entity> = graph.entities.iter().map(|e| (e.id.as_str(), e)).collect();
+    let used: HashSet<&str> = graph
+        .relations
+        .iter()
+        .filter(|r| r.from_id != r.to_id)
+        .filter(|r| match r.kind {
+            RelationKind::Calls | RelationKind::References => {
+                by_id.get(r.from_id.as_str()).is_some_and(|from| !from.is_test_code())
+            }
+            RelationKind::Extends | RelationKind::Implements | RelationKind::Embeds => true,
+            _ => false,
+        })
+        .map(|r| r.to_id.as_str())
+        .collect();
+
+    let abstract_types: HashSet<&str> = graph
+        .entities
+        .iter()
+        .filter(|e| matches!(e.kind, EntityKind::Interface | EntityKind::Trait))
+        .map(|e| e.name.as_str())
+        .collect();
+    let trait_impls = TraitImpls::new(graph);
+
+    let mut dead: Vec<&Entity> = graph
+        .entities
+        .iter()
+        .filter(|e| is_candidate(&e.kind) && !e.is_test_code())
+        .filter(|e| !used.contains(e.id.as_str()))
+        .filter(|e| !e.owner.as_deref().is_some_and(|o| abstract_types.contains(o)))
+        .filter(|e| !config.ignore.iter().any(|p| glob_match(p, &e.file)))
+        .filter(|e| !is_root(e, config, &trait_impls))
+        .collect();
+    dead.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+    dead
+}
+
+/// Plain-text report grouped by file.
+pub fn render_report(dead: &[&Entity]) -> String {
+    let mut out = format!("{} unused entities\n", dead.len());
+    let mut file = "";
+    for e in dead {
+        if e.file != file {
+            file = &e.file;
+            out.push_str(&format!("\n{}\n", file));
+        }
+        out.push_str(&format!("  {}:{}  {} {}\n", e.line, e.end_line, e.kind.label(), e.name));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{self, DetectedLanguage, Relation};
+
+    const SERVER_GO: &str = "package server
+
+type Handler interface {
+	Serve()
+}
+
+type api struct{}
+
+func (a api) Serve() {}
+
+func (a api) helper() {}
+
+func main() { start() }
+
+func init() {}
+
+func start() {}
+
+func unused() {}
+
+func onlyTested() {}
+
+func Exported() {}
+
+func recursive() { recursive() }
+";
+
+    const SERVER_TEST_GO: &str = "package server
+
+import \"testing\"
+
+func TestOnly(t *testing.T) { onlyTested() }
+";
+
+    fn go_graph() -> EntityGraph {
+        let mut entities = Vec::new();
+        for (file, source) in [("server/server.go", SERVER_GO), ("server/server_test.go", SERVER_TEST_GO)] {
+            let tree = parser::parse_file(source, DetectedLanguage::Go.tree_sitter_language()).unwrap();
+            entities.extend(parser::extract_entities_go(source, &tree, file, "server"));
+        }
+        let id = |name: &str| entities.iter().find(|e| e.name == name).unwrap().id.clone();
+        let calls = |from: &str, to: &str| Relation { from_id: id(from), to_id: id(to), kind: RelationKind::Calls };
+        let relations = vec![
+            calls("main", "start"),
+            calls("TestOnly", "onlyTested"),
+            calls("recursive", "recursive"),
+        ];
+        EntityGraph { entities, relations, external_deps: Default::default() }
+    }
+
+    fn names(dead: Vec<&Entity>) -> Vec<&str> {
+        dead.into_iter().map(|e| e.name.as_str()).collect()
+    }
+
+    #[test]
+    fn reports_unused_entities_outside_roots() {
+        let graph = go_graph();
+        let dead = dead_code(&graph, &DeadCodeConfig::default());
+        assert_eq!(
+            names(dead),
+            vec!["api", "api.helper", "unused", "onlyTested", "recursive"]
+        );
+
+        let config = DeadCodeConfig {
+            roots: vec!["main".into(), "init".into(), "only*".into()],
+            exported: false,
+            ignore: vec!["*/none.go".into()],
+            ..DeadCodeConfig::default()
+        };
+        let dead = names(dead_code(&graph, &config));
+        assert!(dead.contains(&"Exported") && dead.contains(&"Handler"));
+        assert!(!dead.contains(&"onlyTested") && !dead.contains(&"api.Serve"));
+    }
+}
@@ -0,0 +1,272 @@
+//! Package dependency graph: which package directories use which, the cycles
+//! between them, and the layering rules from `.fode.toml`.
+
+use crate::config::{glob_match, DependencyConfig, LayerRule};
+use crate::parser::{file_dir, Entity, EntityGraph, RelationKind};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Entity edges kept per package dependency, to point at the code behind it.
+const MAX_EXAMPLES: usize = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityEdge {
+    pub from: String,
+    pub to: String,
+}
+
+/// `from` uses `to` through `count` entity-level calls, references or
+/// inheritance edges.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageDep {
+    pub from: String,
+    pub to: String,
+    pub count: usize,
+    pub examples: Vec<EntityEdge>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayerViolation {
+    pub rule: LayerRule,
+    pub dependency: PackageDep,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DependencyReport {
+    /// Package directories, sorted.
+    pub packages: Vec<String>,
+    pub dependencies: Vec<PackageDep>,
+    /// Strongly connected components of more than one package, each sorted.
+    pub cycles: Vec<Vec<String>>,
+    pub violations: Vec<LayerViolation>,
+    pub allow_cycles: bool,
+}
+
+impl DependencyReport {
+    pub fn passed(&self) -> bool {
+        self.violations.is_empty() && (self.allow_cycles || self.cycles.is_empty())
+    }
+}
+
+/// Package directories and the dependencies between them. Test code is left
+/// out: tests may reach across layers to set up fixtures.
+pub fn package_graph(graph: &EntityGraph) -> (Vec<String>, Vec<PackageDep>) {
+    let by_id: HashMap<&str, &Entity> = graph.entities.iter().map(|e| (e.id.as_str(), e)).collect();
+    let packages: BTreeSet<String> = graph
+        .entities
+        .iter()
+        .filter(|e| !e.is_test_code())
+        .map(|e| file_dir(&e.file))
+        .collect();
+
+    let mut deps: BTreeMap<(String, String), PackageDep> = BTreeMap::new();
+    for r in &graph.relations {
+        if !matches!(
+            r.kind,
+            RelationKind::Calls | RelationKind::References | RelationKind::Extends | RelationKind::Implements | RelationKind::Embeds
+        ) {
+            continue;
+        }
+        let (Some(from), Some(to)) = (by_id.get(r.from_id.as_str()), by_id.get(r.to_id.as_str())) else {
+            continue;
+        };
+        if from.is_test_code() || to.is_test_code() {
+            continue;
+        }
+        let (from_dir, to_dir) = (file_dir(&from.file), file_dir(&to.file));
+        if from_dir == to_dir {
+            continue;
+        }
+        let dep = deps.entry((from_dir.clone(), to_dir.clone())).or_insert_with(|| PackageDep {
+            from: from_dir,
+            to: to_dir,
+            count: 0,
+            examples: Vec::new(),
+        });
+        dep.count += 1;
+        if dep.examples.len() < MAX_EXAMPLES {
+            dep.examples.push(EntityEdge { from: r.from_id.clone(), to: r.to_id.clone() });
+        }
+    }
+    (packages.into_iter().collect(), deps.into_values().collect())
+}
+
+/// Tarjan's algorithm over the package graph; components of a single
+/// package are dropped since a package may use itself.
+fn cycles(packages: &[String], deps: &[PackageDep]) -> Vec<Vec<String>> {
+    struct Tarjan<'a> {
+        edges: HashMap<&'a str, Vec<&'a str>>,
+        index: HashMap<&'a str, usize>,
+        low: HashMap<&'a str, usize>,
+        stack: Vec<&'a str>,
+        on_stack: BTreeSet<&'a str>,
+        components: Vec<Vec<String>>,
+    }
+
+    impl<'a> Tarjan<'a> {
+        fn visit(&mut self, v: &'a str) {
+            let i = self.index.len();
+            self.index.insert(v, i);
+            self.low.insert(v, i);
+            self.stack.push(v);
+            self.on_stack.insert(v);
+
+            for w in self.edges.get(v).cloned().unwrap_or_default() {
+                if !self.index.contains_key(w) {
+                    self.visit(w);
+                    let low = self.low[v].min(self.low[w]);
+                    self.low.insert(v, low);
+                } else if self.on_stack.contains(w) {
+                    let low = self.low[v].min(self.index[w]);
+                    self.low.insert(v, low);
+                }
+            }
+
+            if self.low[v] == self.index[v] {
+                let mut component = Vec::new();
+                while let Some(w) = self.stack.pop() {
+                    self.on_stack.remove(w);
+                    component.push(w.to_string());
+                    if w == v {
+                        break;
+                    }
+                }
+                if component.len() > 1 {
+                    component.sort();
+                    self.components.push(component);
+                }
+            }
+        }
+    }
+
+    let mut edges: HashMap<&str, Vec<&str>> = HashMap::new();
+    for d in deps {
+        edges.entry(d.from.as_str()).or_default().push(d.to.as_str());
+    }
+    let mut tarjan = Tarjan {
+        edges,
+        index: HashMap::new(),
+        low: HashMap::new(),
+        stack: Vec::new(),
+        on_stack: BTreeSet::new(),
+        components: Vec::new(),
+    };
+    for p in packages {
+        if !tarjan.index.contains_key(p.as_str()) {
+            tarjan.visit(p);
+        }
+    }
+    let mut components = tarjan.components;
+    components.sort();
+    components
+}
+
+/// Build the package graph and check it against `config`.
+pub fn check(graph: &EntityGraph, config: &DependencyConfig) -> DependencyReport {
+    let (packages, dependencies) = package_graph(graph);
+    let cycles = cycles(&packages, &dependencies);
+    let violations = dependencies
+        .iter()
+        .flat_map(|dep| {
+            config
+                .forbid
+                .iter()
+                .filter(|rule| glob_match(&rule.from, &dep.from) && glob_match(&rule.to, &dep.to))
+                .map(|rule| LayerViolation { rule: rule.clone(), dependency: dep.clone() })
+        })
+        .collect();
+    DependencyReport { packages, dependencies, cycles, violations, allow_cycles: config.allow_cycles }
+}
+
+/// Plain-text report: cycles with the dependencies that form them, then
+/// rule violations, each with example entity edges.
+pub fn render_report(report: &DependencyReport) -> String {
+    let dep_line = |d: &PackageDep| {
+        let mut line = format!("    {} -> {}  ({} uses)\n", d.from, d.to, d.count);
+        for e in &d.examples {
+            line.push_str(&format!("      {} -> {}\n", e.from, e.to));
+        }
+        line
+    };
+
+    let mut out = format!(
+        "{} packages, {} dependencies, {} cycles{}, {} violations\n",
+        report.packages.len(),
+        report.dependencies.len(),
+        report.cycles.len(),
+        if report.allow_cycles { " (allowed)" } else { "" },
+        report.violations.len()
+    );
+    if !report.cycles.is_empty() {
+        out.push_str("\ncycles\n");
+        for cycle in &report.cycles {
+            out.push_str(&format!("  {}\n", cycle.join(", ")));
+            for d in &report.dependencies {
+                if cycle.contains(&d.from) && cycle.contains(&d.to) {
+                    out.push_str(&dep_line(d));
+                }
+            }
+        }
+    }
+    if !report.violations.is_empty() {
+        out.push_str("\nviolations\n");
+        for v in &report.violations {
+            let reason = v.rule.reason.as_deref().map(|r| format!(": {}", r)).unwrap_or_default();
+            out.push_str(&format!("  {} may not depend on {}{}\n", v.rule.from, v.rule.to, reason));
+            out.push_str(&dep_line(&v.dependency));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{EntityKind, Relation};
+
+    fn entity(file: &str, name: &str) -> Entity {
+        Entity::stub(file, name, EntityKind::Function, 1, 1)
+    }
+
+    #[test]
+    fn finds_cycles_and_forbidden_dependencies() {
+        let entities = vec![
+            entity("api/api.go", "Handle"),
+            entity("service/service.go", "Do"),
+            entity("service/service.go", "Notify"),
+            entity("storage/storage.go", "Get"),
+            entity("cmd/main.go", "main"),
+            entity("api/api_test.go", "helper"),
+        ];
+        let calls = |from: usize, to: usize| Relation {
+            from_id: entities[from].id.clone(),
+            to_id: entities[to].id.clone(),
+            kind: RelationKind::Calls,
+        };
+        let relations = vec![
+            calls(0, 1),
+            calls(2, 0),
+            calls(1, 3),
+            calls(0, 3),
+            calls(4, 0),
+            calls(1, 2),
+            calls(5, 4),
+        ];
+        let graph = EntityGraph { entities, relations, external_deps: Default::default() };
+
+        let config = DependencyConfig {
+            allow_cycles: false,
+            forbid: vec![LayerRule { from: "api".into(), to: "stor*".into(), reason: None }],
+        };
+        let report = check(&graph, &config);
+        assert_eq!(report.packages, vec!["api", "cmd", "service", "storage"]);
+        assert_eq!(report.dependencies.len(), 5);
+        assert_eq!(report.cycles, vec![vec!["api".to_string(), "service".to_string()]]);
+        assert_eq!(report.violations.len(), 1);
+        assert_eq!(report.violations[0].dependency.from, "api");
+        assert!(!report.passed());
+
+        let lenient = check(&graph, &DependencyConfig { allow_cycles: true, forbid: Vec::new() });
+        assert!(lenient.passed());
+    }
+}
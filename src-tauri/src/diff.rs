@@ -0,0 +1,232 @@
+//! Structural diff between two parses of a repo: which entities were added,
+//! deleted, moved or modified, with a unified diff of each one's source.
+
+use crate::parser::{Entity, EntityGraph, EntityKind};
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum EntityChangeKind {
+    Added,
+    Deleted,
+    /// Same entity in a different file. Carries a diff when the body changed too.
+    Moved,
+    Modified,
+}
+
+/// Where an entity lives in one revision.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EntityLocation {
+    pub id: String,
+    pub file: String,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityChange {
+    pub change: EntityChangeKind,
+    pub name: String,
+    pub kind: EntityKind,
+    pub package: String,
+    pub old: Option<EntityLocation>,
+    pub new: Option<EntityLocation>,
+    /// Unified diff of `Entity.source`; empty for moves with an unchanged body.
+    pub diff: String,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityDiff {
+    pub base: String,
+    pub head: String,
+    /// Ordered by file and line in the newer revision (older for deletions).
+    pub changes: Vec<EntityChange>,
+}
+
+fn location(entity: &Entity) -> EntityLocation {
+    EntityLocation { id: entity.id.clone(), file: entity.file.clone(), line: entity.line }
+}
+
+fn change(kind: EntityChangeKind, old: Option<&Entity>, new: Option<&Entity>) -> EntityChange {
+    let entity = new.or(old).expect("change needs an entity");
+    let old_source = old.map(|e| e.source.as_str()).unwrap_or("");
+    let new_source = new.map(|e| e.source.as_str()).unwrap_or("");
+
+    let (mut diff, mut lines_added, mut lines_removed) = (String::new(), 0, 0);
+    if old_source != new_source {
+        let text_diff = TextDiff::from_lines(old_source, new_source);
+        for op in text_diff.ops() {
+            for c in text_diff.iter_changes(op) {
+                match c.tag() {
+                    similar::ChangeTag::Insert => lines_added += 1,
+                    similar::ChangeTag::Delete => lines_removed += 1,
+                    similar::ChangeTag::Equal => {}
+                }
+            }
+        }
+        let old_header = old.map(|e| format!("a/{}:{}", e.file, e.line)).unwrap_or_else(|| "/dev/null".into());
+        let new_header = new.map(|e| format!("b/{}:{}", e.file, e.line)).unwrap_or_else(|| "/dev/null".into());
+        diff = text_diff
+            .unified_diff()
+            .context_radius(3)
+            .header(&old_header, &new_header)
+            .to_string();
+    }
+
+    EntityChange {
+        change: kind,
+        name: entity.name.clone(),
+        kind: entity.kind.clone(),
+        package: entity.package.clone(),
+        old: old.map(location),
+        new: new.map(location),
+        diff,
+        lines_added,
+        lines_removed,
+    }
+}
+
+/// Compare two parses. Entities pair up by ID first (same file, kind and
+/// name); the rest pair up by identical source, then by kind and name when
+/// that is unique on both sides, and count as moved. Anything left over was
+/// added or deleted.
+pub fn diff_graphs(base: &str, head: &str, old: &EntityGraph, new: &EntityGraph) -> EntityDiff {
+    let new_by_id: HashMap<&str, &Entity> = new.entities.iter().map(|e| (e.id.as_str(), e)).collect();
+    let old_ids: HashSet<&str> = old.entities.iter().map(|e| e.id.as_str()).collect();
+
+    let mut changes = Vec::new();
+    let mut old_unmatched: Vec<&Entity> = Vec::new();
+    for before in &old.entities {
+        match new_by_id.get(before.id.as_str()) {
+            Some(after) if after.source != before.source => {
+                changes.push(change(EntityChangeKind::Modified, Some(before), Some(after)));
+            }
+            Some(_) => {}
+            None => old_unmatched.push(before),
+        }
+    }
+    let mut new_unmatched: Vec<&Entity> = new
+        .entities
+        .iter()
+        .filter(|e| !old_ids.contains(e.id.as_str()))
+        .collect();
+
+    // Identical bodies in another file
+    let mut by_source: HashMap<(&EntityKind, &str), Vec<usize>> = HashMap::new();
+    for (i, e) in new_unmatched.iter().enumerate() {
+        by_source.entry((&e.kind, e.source.as_str())).or_default().push(i);
+    }
+    let mut taken = vec![false; new_unmatched.len()];
+    old_unmatched.retain(|before| {
+        let candidates = by_source.get(&(&before.kind, before.source.as_str()));
+        match candidates.and_then(|c| c.iter().find(|&&i| !taken[i])) {
+            Some(&i) => {
+                taken[i] = true;
+                changes.push(change(EntityChangeKind::Moved, Some(before), Some(new_unmatched[i])));
+                false
+            }
+            None => true,
+        }
+    });
+    new_unmatched = new_unmatched
+        .into_iter()
+        .zip(taken)
+        .filter(|(_, taken)| !taken)
+        .map(|(e, _)| e)
+        .collect();
+
+    // Same kind and name in another file, body edited along the way
+    let by_name = |entities: &[&'_ Entity]| {
+        let mut map: HashMap<(EntityKind, String), Vec<usize>> = HashMap::new();
+        for (i, e) in entities.iter().enumerate() {
+            map.entry((e.kind.clone(), e.name.clone())).or_default().push(i);
+        }
+        map
+    };
+    let (old_names, new_names) = (by_name(&old_unmatched), by_name(&new_unmatched));
+    let mut moved_to: HashSet<&str> = HashSet::new();
+    for before in &old_unmatched {
+        let key = (before.kind.clone(), before.name.clone());
+        match (old_names.get(&key).map(Vec::len), new_names.get(&key).map(Vec::as_slice)) {
+            (Some(1), Some(&[i])) => {
+                let after = new_unmatched[i];
+                moved_to.insert(after.id.as_str());
+                changes.push(change(EntityChangeKind::Moved, Some(before), Some(after)));
+            }
+            _ => changes.push(change(EntityChangeKind::Deleted, Some(before), None)),
+        }
+    }
+    for after in new_unmatched.iter().filter(|e| !moved_to.contains(e.id.as_str())) {
+        changes.push(change(EntityChangeKind::Added, None, Some(after)));
+    }
+
+    changes.sort_by(|a, b| {
+        let key = |c: &EntityChange| {
+            let loc = c.new.as_ref().or(c.old.as_ref()).expect("change has a location");
+            (loc.file.clone(), loc.line)
+        };
+        key(a).cmp(&key(b))
+    });
+    EntityDiff { base: base.to_string(), head: head.to_string(), changes }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(file: &str, name: &str, line: usize, source: &str) -> Entity {
+        Entity {
+            source: source.to_string(),
+            signature: source.lines().next().unwrap_or("").to_string(),
+            package: "pkg".to_string(),
+            ..Entity::stub(file, name, EntityKind::Function, line, line + source.lines().count() - 1)
+        }
+    }
+
+    fn graph(entities: Vec<Entity>) -> EntityGraph {
+        EntityGraph { entities, relations: Vec::new(), external_deps: Default::default() }
+    }
+
+    #[test]
+    fn classifies_added_deleted_moved_and_modified() {
+        let old = graph(vec![
+            entity("a.go", "Same", 1, "func Same() {}\n"),
+            entity("a.go", "Edit", 3, "func Edit() {\n\tone()\n}\n"),
+            entity("a.go", "Gone", 7, "func Gone() {}\n"),
+            entity("a.go", "Move", 9, "func Move() {}\n"),
+            entity("a.go", "MoveEdit", 11, "func MoveEdit() {\n\tone()\n}\n"),
+        ]);
+        let new = graph(vec![
+            entity("a.go", "Same", 1, "func Same() {}\n"),
+            entity("a.go", "Edit", 3, "func Edit() {\n\ttwo()\n}\n"),
+            entity("b.go", "Move", 1, "func Move() {}\n"),
+            entity("b.go", "MoveEdit", 3, "func MoveEdit() {\n\ttwo()\n}\n"),
+            entity("b.go", "Fresh", 7, "func Fresh() {}\n"),
+        ]);
+
+        let diff = diff_graphs("main", "topic", &old, &new);
+        let summary: Vec<(&str, EntityChangeKind, usize, usize)> = diff
+            .changes
+            .iter()
+            .map(|c| (c.name.as_str(), c.change, c.lines_added, c.lines_removed))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Edit", EntityChangeKind::Modified, 1, 1),
+                ("Gone", EntityChangeKind::Deleted, 0, 1),
+                ("Move", EntityChangeKind::Moved, 0, 0),
+                ("MoveEdit", EntityChangeKind::Moved, 1, 1),
+                ("Fresh", EntityChangeKind::Added, 1, 0),
+            ]
+        );
+
+        let edit = &diff.changes[0];
+        assert!(edit.diff.starts_with("--- a/a.go:3\n+++ b/a.go:3\n"));
+        assert!(edit.diff.contains("-\tone()\n+\ttwo()\n"));
+        assert!(diff.changes[2].diff.is_empty());
+        assert_eq!(diff.changes[2].old.as_ref().unwrap().file, "a.go");
+    }
+}
@@ -0,0 +1,367 @@
+//! Duplicate code: functions whose bodies have the same shape once
+//! identifiers and literals are normalized away, as copy-pasted helpers do.
+//! A body's shape is the multiset of hashed k-grams of its tokens; two
+//! bodies are as similar as the Jaccard index of their shapes.
+
+use crate::parser::{Entity, EntityGraph, EntityKind};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use tree_sitter::Node;
+
+/// Similarity used when none is given.
+pub const DEFAULT_SIMILARITY: f64 = 0.8;
+
+/// Tokens per k-gram.
+const SHINGLE: usize = 5;
+
+/// Bodies shorter than this are too small to count as duplicated.
+const MIN_TOKENS: usize = 20;
+
+/// Leaf-like kinds standing for one literal, across the supported grammars.
+const LITERAL_KINDS: &[&str] = &[
+    "string",
+    "number",
+    "integer",
+    "float",
+    "true",
+    "false",
+    "none",
+    "null",
+    "undefined",
+    "nil",
+    "template_string",
+    "concatenated_string",
+];
+
+fn is_literal(kind: &str) -> bool {
+    kind.ends_with("literal") || LITERAL_KINDS.contains(&kind)
+}
+
+/// Tokens of `node` with every identifier as `$id` and every literal as
+/// `$lit`; comments are dropped.
+fn normalized_tokens(node: Node, tokens: &mut Vec<&'static str>) {
+    let kind = node.kind();
+    if kind.contains("comment") {
+        return;
+    }
+    if is_literal(kind) {
+        tokens.push("$lit");
+        return;
+    }
+    if node.child_count() == 0 {
+        tokens.push(if kind.contains("identifier") || kind == "primitive_type" { "$id" } else { kind });
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        normalized_tokens(child, tokens);
+    }
+}
+
+/// Shape of a function's body: its normalized token k-grams, each hashed
+/// with how many times it occurred so far, sorted. Empty for bodies under
+/// `MIN_TOKENS` tokens.
+pub fn shape(node: Node) -> Vec<u64> {
+    let body = crate::metrics::find_in_definition(node, |n| n.child_by_field_name("body")).unwrap_or(node);
+    let mut tokens = Vec::new();
+    normalized_tokens(body, &mut tokens);
+    if tokens.len() < MIN_TOKENS {
+        return Vec::new();
+    }
+    let mut counts: HashMap<&[&str], usize> = HashMap::new();
+    let mut shingles: Vec<u64> = tokens
+        .windows(SHINGLE)
+        .map(|w| {
+            // Repeats stay distinct so that a long match or switch does not
+            // collapse into the shape of a short one
+            let count = counts.entry(w).or_default();
+            *count += 1;
+            let mut hasher = DefaultHasher::new();
+            (w, *count).hash(&mut hasher);
+            hasher.finish()
+        })
+        .collect();
+    shingles.sort_unstable();
+    shingles.dedup();
+    shingles
+}
+
+/// Jaccard index of two shapes.
+pub fn similarity(a: &[u64], b: &[u64]) -> f64 {
+    let (mut i, mut j, mut shared) = (0, 0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            Ordering::Less => i += 1,
+            Ordering::Greater => j += 1,
+            Ordering::Equal => {
+                shared += 1;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    jaccard(shared, a.len(), b.len())
+}
+
+fn jaccard(shared: usize, a: usize, b: usize) -> f64 {
+    if a + b == 0 {
+        return 0.0;
+    }
+    shared as f64 / (a + b - shared) as f64
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimilarEntity {
+    pub id: String,
+    pub name: String,
+    pub kind: EntityKind,
+    pub package: String,
+    pub file: String,
+    pub line: usize,
+    pub loc: usize,
+    /// To the entity compared against; within a cluster, to its most
+    /// similar member.
+    pub similarity: f64,
+}
+
+impl SimilarEntity {
+    fn new(e: &Entity, similarity: f64) -> Self {
+        SimilarEntity {
+            id: e.id.clone(),
+            name: e.name.clone(),
+            kind: e.kind.clone(),
+            package: e.package.clone(),
+            file: e.file.clone(),
+            line: e.line,
+            loc: e.metrics.loc,
+            similarity,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateCluster {
+    /// Lowest similarity among the pairs linking the cluster together.
+    pub similarity: f64,
+    /// Ordered by file and line.
+    pub entities: Vec<SimilarEntity>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateReport {
+    pub min_similarity: f64,
+    /// Most duplicated lines first.
+    pub clusters: Vec<DuplicateCluster>,
+}
+
+/// Non-test functions and methods with a shape.
+fn candidates(graph: &EntityGraph) -> impl Iterator<Item = &Entity> {
+    graph.entities.iter().filter(|e| !e.shape.is_empty() && !e.is_test_code())
+}
+
+/// Whether one of two entities is nested in the other, as a closure or
+/// inner function shares its tokens with the enclosing function.
+fn overlaps(a: &Entity, b: &Entity) -> bool {
+    a.file == b.file && a.line <= b.end_line && b.line <= a.end_line
+}
+
+/// Entities at least `min_similarity` similar to `entity`, most similar
+/// first.
+pub fn similar_to(graph: &EntityGraph, entity: &Entity, min_similarity: f64) -> Vec<SimilarEntity> {
+    if entity.shape.is_empty() {
+        return Vec::new();
+    }
+    let mut similar: Vec<SimilarEntity> = candidates(graph)
+        .filter(|e| e.id != entity.id && !overlaps(e, entity))
+        .map(|e| (e, similarity(&entity.shape, &e.shape)))
+        .filter(|&(_, s)| s >= min_similarity)
+        .map(|(e, s)| SimilarEntity::new(e, s))
+        .collect();
+    similar.sort_by(|a, b| b.similarity.total_cmp(&a.similarity).then_with(|| (&a.file, a.line).cmp(&(&b.file, b.line))));
+    similar
+}
+
+/// Cluster functions and methods linked by pairs at least `min_similarity`
+/// similar. Pairs are found through an index from k-gram to entities, so
+/// only functions sharing k-grams are compared.
+pub fn duplicates(graph: &EntityGraph, min_similarity: f64) -> DuplicateReport {
+    let entities: Vec<&Entity> = candidates(graph).collect();
+    let mut postings: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (i, e) in entities.iter().enumerate() {
+        for &shingle in &e.shape {
+            postings.entry(shingle).or_default().push(i);
+        }
+    }
+
+    let mut parent: Vec<usize> = (0..entities.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    let mut links: Vec<(usize, usize, f64)> = Vec::new();
+    for (i, e) in entities.iter().enumerate() {
+        let mut shared: HashMap<usize, usize> = HashMap::new();
+        for shingle in &e.shape {
+            for &j in postings[shingle].iter().filter(|&&j| j > i) {
+                *shared.entry(j).or_default() += 1;
+            }
+        }
+        for (j, shared) in shared {
+            let s = jaccard(shared, e.shape.len(), entities[j].shape.len());
+            if s >= min_similarity && !overlaps(e, entities[j]) {
+                links.push((i, j, s));
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                parent[a] = b;
+            }
+        }
+    }
+
+    // Root -> (members with their best similarity, weakest link)
+    let mut groups: BTreeMap<usize, (BTreeMap<usize, f64>, f64)> = BTreeMap::new();
+    for &(i, j, s) in &links {
+        let (members, weakest) = groups.entry(root(&mut parent, i)).or_insert_with(|| (BTreeMap::new(), 1.0));
+        for member in [i, j] {
+            let best = members.entry(member).or_insert(0.0);
+            *best = best.max(s);
+        }
+        *weakest = weakest.min(s);
+    }
+
+    let mut clusters: Vec<DuplicateCluster> = groups
+        .into_values()
+        .map(|(members, weakest)| {
+            let mut members: Vec<SimilarEntity> =
+                members.into_iter().map(|(i, s)| SimilarEntity::new(entities[i], s)).collect();
+            members.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+            DuplicateCluster { similarity: weakest, entities: members }
+        })
+        .collect();
+    // Lines that would go away if each cluster kept one copy
+    let duplicated = |c: &DuplicateCluster| {
+        let total: usize = c.entities.iter().map(|e| e.loc).sum();
+        total - c.entities.iter().map(|e| e.loc).max().unwrap_or(0)
+    };
+    clusters.sort_by(|a, b| duplicated(b).cmp(&duplicated(a)).then_with(|| a.entities[0].id.cmp(&b.entities[0].id)));
+    DuplicateReport { min_similarity, clusters }
+}
+
+/// Plain-text list of the clusters.
+pub fn render_report(report: &DuplicateReport) -> String {
+    let mut out = format!(
+        "{} clusters of similar functions (similarity >= {:.0}%)\n",
+        report.clusters.len(),
+        report.min_similarity * 100.0
+    );
+    for c in &report.clusters {
+        out.push_str(&format!("\n  {} entities, at least {:.0}% similar\n", c.entities.len(), c.similarity * 100.0));
+        for e in &c.entities {
+            out.push_str(&format!("    {} {}  {}:{}  ({} lines)\n", e.kind.label(), e.name, e.file, e.line, e.loc));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{self, DetectedLanguage};
+
+    const UTIL_GO: &str = "package util
+
+func SumPositive(values []int) int {
+	total := 0
+	for _, v := range values {
+		if v > 0 {
+			total += v
+		}
+	}
+	return total
+}
+
+// Same body, renamed and with other literals
+func addAbove(xs []int) int {
+	acc := 10
+	for _, x := range xs {
+		if x > 5 {
+			acc += x
+		}
+	}
+	return acc
+}
+
+// One extra statement
+func SumPositiveLogged(values []int) int {
+	total := 0
+	for _, v := range values {
+		if v > 0 {
+			total += v
+		}
+	}
+	println(total)
+	return total
+}
+
+func Describe(name string, age int) string {
+	switch {
+	case age < 13:
+		return name + \" is a child\"
+	case age < 20:
+		return name + \" is a teenager\"
+	}
+	return name + \" is an adult\"
+}
+
+func tiny() int { return 1 }
+";
+
+    fn go_graph() -> EntityGraph {
+        let tree = parser::parse_file(UTIL_GO, DetectedLanguage::Go.tree_sitter_language()).unwrap();
+        let mut entities = parser::extract_entities_go(UTIL_GO, &tree, "util/util.go", "util");
+        for e in &mut entities {
+            if let Some(node) = parser::entity_node(tree.root_node(), UTIL_GO.as_bytes(), e) {
+                e.shape = shape(node);
+            }
+        }
+        EntityGraph { entities, relations: Vec::new(), external_deps: Default::default() }
+    }
+
+    fn entity<'a>(graph: &'a EntityGraph, name: &str) -> &'a Entity {
+        graph.entities.iter().find(|e| e.name == name).unwrap()
+    }
+
+    #[test]
+    fn shapes_ignore_identifiers_and_literals() {
+        let graph = go_graph();
+        let (sum, add) = (entity(&graph, "SumPositive"), entity(&graph, "addAbove"));
+        assert!(!sum.shape.is_empty());
+        assert_eq!(sum.shape, add.shape);
+        assert!(entity(&graph, "tiny").shape.is_empty());
+
+        let logged = similarity(&sum.shape, &entity(&graph, "SumPositiveLogged").shape);
+        assert!(logged > 0.5 && logged < 1.0, "{}", logged);
+        assert!(similarity(&sum.shape, &entity(&graph, "Describe").shape) < 0.1);
+    }
+
+    #[test]
+    fn clusters_near_duplicates() {
+        let graph = go_graph();
+        let report = duplicates(&graph, 0.5);
+        let names: Vec<Vec<&str>> =
+            report.clusters.iter().map(|c| c.entities.iter().map(|e| e.name.as_str()).collect()).collect();
+        assert_eq!(names, vec![vec!["SumPositive", "addAbove", "SumPositiveLogged"]]);
+        assert!(report.clusters[0].similarity < 1.0);
+        assert_eq!(report.clusters[0].entities[1].similarity, 1.0);
+
+        assert_eq!(duplicates(&graph, 1.0).clusters[0].entities.len(), 2);
+        let similar = similar_to(&graph, entity(&graph, "SumPositive"), 0.5);
+        let similar: Vec<&str> = similar.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(similar, vec!["addAbove", "SumPositiveLogged"]);
+    }
+}
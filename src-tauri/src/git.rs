@@ -0,0 +1,798 @@
+//! Reading other revisions and the history of the open repo through the
+//! `git` CLI.
+
+use crate::parser::{self, Entity, EntityGraph, RepoAttribute, RepoInfo, SourceTree};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::Mutex;
+
+/// Run `git` in `repo` and return its stdout.
+pub fn run(repo: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// `rev` as a git argument. Revisions come from the CLI and the UI, and one
+/// starting with `-` (e.g. `--output=<file>`) would be taken for an option.
+pub fn rev_arg(rev: &str) -> Result<&str, String> {
+    if rev.starts_with('-') {
+        return Err(format!("Invalid revision: {}", rev));
+    }
+    Ok(rev)
+}
+
+/// Full commit hash of `rev`.
+pub fn resolve(repo: &Path, rev: &str) -> Result<String, String> {
+    let commit = format!("{}^{{commit}}", rev);
+    run(repo, &["rev-parse", "--verify", "--quiet", &commit])
+        .map(|out| out.trim().to_string())
+        .map_err(|_| format!("Unknown revision: {}", rev))
+}
+
+/// One revision of the repo, read straight from the object database without
+/// touching the checkout. Blobs are streamed through one `git cat-file
+/// --batch` process.
+pub struct RevisionTree {
+    root: PathBuf,
+    commit: String,
+    paths: Vec<PathBuf>,
+    cat_file: Mutex<CatFile>,
+}
+
+struct CatFile {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl RevisionTree {
+    /// Files under `repo` (which may be below the top of the checkout) as of `rev`.
+    pub fn open(repo: &Path, rev: &str) -> Result<RevisionTree, String> {
+        let commit = resolve(repo, rev)?;
+        let listing = run(repo, &["ls-tree", "-r", "-z", "--name-only", &commit, "--", "."])?;
+        let paths = listing
+            .split('\0')
+            .filter(|p| !p.is_empty())
+            .map(PathBuf::from)
+            .collect();
+
+        let mut child = Command::new("git")
+            .arg("-C")
+            .arg(repo)
+            .args(["cat-file", "--batch"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Failed to run git: {}", e))?;
+        let stdin = child.stdin.take().ok_or("git cat-file has no stdin")?;
+        let stdout = BufReader::new(child.stdout.take().ok_or("git cat-file has no stdout")?);
+
+        Ok(RevisionTree {
+            root: repo.to_path_buf(),
+            commit,
+            paths,
+            cat_file: Mutex::new(CatFile { child, stdin, stdout }),
+        })
+    }
+
+    pub fn commit(&self) -> &str {
+        &self.commit
+    }
+}
+
+impl SourceTree for RevisionTree {
+    fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn paths(&self) -> Vec<PathBuf> {
+        self.paths.clone()
+    }
+
+    fn read(&self, rel_path: &Path) -> std::io::Result<String> {
+        let mut cat_file = self.cat_file.lock().unwrap();
+        // `<rev>:./<path>` is relative to the directory cat-file runs in
+        writeln!(cat_file.stdin, "{}:./{}", self.commit, rel_path.to_string_lossy())?;
+        cat_file.stdin.flush()?;
+
+        // "<oid> blob <size>" then the contents and a newline, or "<name> missing"
+        let mut header = String::new();
+        cat_file.stdout.read_line(&mut header)?;
+        let fields: Vec<&str> = header.split_whitespace().collect();
+        let size = match fields.as_slice() {
+            [_, "blob", size] => size
+                .parse::<usize>()
+                .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e))?,
+            [_, kind, size] => {
+                // Consume whatever was printed so the stream stays in step
+                let size = size.parse::<usize>().unwrap_or(0);
+                let mut skip = vec![0; size + 1];
+                cat_file.stdout.read_exact(&mut skip)?;
+                return Err(std::io::Error::new(ErrorKind::InvalidData, format!("not a blob: {}", kind)));
+            }
+            _ => return Err(std::io::Error::new(ErrorKind::NotFound, header.trim().to_string())),
+        };
+        let mut contents = vec![0; size + 1];
+        cat_file.stdout.read_exact(&mut contents)?;
+        contents.pop();
+        String::from_utf8(contents).map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e))
+    }
+}
+
+impl Drop for RevisionTree {
+    fn drop(&mut self) {
+        let cat_file = self.cat_file.get_mut().unwrap();
+        // The batch would otherwise wait for more requests on stdin
+        let _ = cat_file.child.kill();
+        let _ = cat_file.child.wait();
+    }
+}
+
+/// Parse the repo as of `rev` without checking it out. Entity files are
+/// relative to the same directory as for the working tree, so IDs line up
+/// across revisions.
+pub fn parse_revision(repo: &Path, rev: &str) -> Result<(RepoInfo, EntityGraph), String> {
+    let tree = RevisionTree::open(repo, rev)?;
+    let (mut info, graph) = parser::parse_tree(&tree, None)
+        .ok_or_else(|| format!("No supported language files found at {}", rev))?;
+    info.name = format!("{} @ {}", info.name, rev);
+    info.attributes.insert(
+        0,
+        RepoAttribute {
+            label: "Revision".to_string(),
+            value: tree.commit()[..12.min(tree.commit().len())].to_string(),
+            link: None,
+        },
+    );
+    Ok((info, graph))
+}
+
+/// Changed line ranges per repo-relative file, on the new side of a diff.
+pub type ChangedLines = HashMap<String, Vec<(usize, usize)>>;
+
+/// Line ranges touched by `git diff -U0` output. A pure deletion marks the
+/// lines on either side of it.
+fn parse_changed_lines(diff: &str) -> ChangedLines {
+    let mut changed: ChangedLines = HashMap::new();
+    let mut file: Option<String> = None;
+    for line in diff.lines() {
+        if let Some(path) = line.strip_prefix("+++ ") {
+            file = path.strip_prefix("b/").map(str::to_string);
+        } else if let (Some(hunk), Some(file)) = (line.strip_prefix("@@ "), &file) {
+            // "@@ -a,b +c,d @@", counts default to 1
+            let Some(new_side) = hunk.split_whitespace().find_map(|f| f.strip_prefix('+')) else {
+                continue;
+            };
+            let (start, count) = match new_side.split_once(',') {
+                Some((start, count)) => (start.parse().unwrap_or(0), count.parse().unwrap_or(0)),
+                None => (new_side.parse().unwrap_or(0), 1),
+            };
+            let range = if count == 0 {
+                (start.max(1), start + 1)
+            } else {
+                (start, start + count - 1)
+            };
+            changed.entry(file.clone()).or_default().push(range);
+        }
+    }
+    changed
+}
+
+/// Lines changed by `range`: `A..B` between two revisions, a single
+/// revision against the working tree, or when `None` the uncommitted
+/// changes (including untracked files, which count as wholly changed).
+/// Paths are relative to `repo`.
+pub fn changed_lines(repo: &Path, range: Option<&str>) -> Result<ChangedLines, String> {
+    // Fixed prefixes: `diff.noprefix` and `diff.mnemonicPrefix` change the defaults
+    let mut args = vec![
+        "diff", "-U0", "--no-color", "--no-ext-diff", "--relative", "--src-prefix=a/", "--dst-prefix=b/",
+    ];
+    args.push(rev_arg(range.unwrap_or("HEAD"))?);
+    let mut changed = parse_changed_lines(&run(repo, &args)?);
+
+    if range.is_none() {
+        let untracked = run(repo, &["ls-files", "--others", "--exclude-standard", "-z"])?;
+        for path in untracked.split('\0').filter(|p| !p.is_empty()) {
+            changed.insert(path.to_string(), vec![(1, usize::MAX)]);
+        }
+    }
+    Ok(changed)
+}
+
+/// Most commits `entity_history` walks back through.
+const MAX_HISTORY_COMMITS: usize = 50;
+
+/// A commit that touched an entity's lines, with the hunks it changed there.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityCommit {
+    pub hash: String,
+    pub author: String,
+    pub email: String,
+    /// Author time, seconds since the epoch.
+    pub time: i64,
+    pub summary: String,
+    pub snippet: String,
+}
+
+/// How many of an entity's current lines one author last touched.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlameAuthor {
+    pub author: String,
+    pub email: String,
+    pub lines: usize,
+    pub last_time: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityHistory {
+    pub entity_id: String,
+    /// Newest first.
+    pub commits: Vec<EntityCommit>,
+    /// Most lines first.
+    pub blame: Vec<BlameAuthor>,
+    pub uncommitted_lines: usize,
+}
+
+/// Commits up to `rev` that changed `start..=end` of `file`, newest first.
+/// `git log -L` re-maps the range through each commit, so the entity is
+/// followed as it moves within the file.
+fn line_history(repo: &Path, rev: &str, file: &str, start: usize, end: usize) -> Result<Vec<EntityCommit>, String> {
+    let range = format!("-L{},{}:{}", start, end, file);
+    let max_count = format!("--max-count={}", MAX_HISTORY_COMMITS);
+    let out = run(
+        repo,
+        &["log", rev_arg(rev)?, &range, &max_count, "--no-color", "--format=%x1e%H%x1f%an%x1f%ae%x1f%at%x1f%s"],
+    )?;
+
+    Ok(out
+        .split('\x1e')
+        .filter(|chunk| !chunk.trim().is_empty())
+        .filter_map(|chunk| {
+            let (header, patch) = chunk.split_once('\n').unwrap_or((chunk, ""));
+            let fields: Vec<&str> = header.split('\x1f').collect();
+            let [hash, author, email, time, summary] = fields.as_slice() else {
+                return None;
+            };
+            // Keep the hunks; the diff --git/index/---/+++ preamble adds nothing
+            let snippet = patch.find("@@").map(|i| patch[i..].trim_end()).unwrap_or("");
+            Some(EntityCommit {
+                hash: hash.to_string(),
+                author: author.to_string(),
+                email: email.to_string(),
+                time: time.parse().unwrap_or(0),
+                summary: summary.to_string(),
+                snippet: snippet.to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Map `start..=end` of the working-tree `file` onto its lines at HEAD, through
+/// the hunks of its uncommitted changes. Lines added since HEAD are dropped;
+/// `None` when the whole range is new.
+fn range_at_head(repo: &Path, file: &str, start: usize, end: usize) -> Result<Option<(usize, usize)>, String> {
+    let out = run(repo, &["diff", "-U0", "--no-color", "--no-ext-diff", "HEAD", "--", file])?;
+    // (old start, old length, new start, new length)
+    let hunks: Vec<(usize, usize, usize, usize)> = out
+        .lines()
+        .filter_map(|line| {
+            let ranges = line.strip_prefix("@@ -")?.split(" @@").next()?;
+            let (old, new) = ranges.split_once(" +")?;
+            let parse = |r: &str| -> Option<(usize, usize)> {
+                match r.split_once(',') {
+                    Some((s, n)) => Some((s.parse().ok()?, n.parse().ok()?)),
+                    None => Some((r.parse().ok()?, 1)),
+                }
+            };
+            let ((old_start, old_len), (new_start, new_len)) = (parse(old)?, parse(new)?);
+            Some((old_start, old_len, new_start, new_len))
+        })
+        .collect();
+
+    let map = |line: usize, is_start: bool| -> usize {
+        let mut shift = 0isize;
+        for &(old_start, old_len, new_start, new_len) in &hunks {
+            // A pure deletion sits after `new_start`
+            let before = if new_len == 0 { line <= new_start } else { line < new_start };
+            if before {
+                break;
+            }
+            if line < new_start + new_len {
+                // Inside changed lines: clamp to the old lines they replaced
+                return match (old_len, is_start) {
+                    (0, true) => old_start + 1,
+                    (0, false) => old_start,
+                    (_, true) => old_start,
+                    (_, false) => old_start + old_len - 1,
+                };
+            }
+            shift += new_len as isize - old_len as isize;
+        }
+        (line as isize - shift) as usize
+    };
+    let (start, end) = (map(start, true), map(end, false));
+    Ok((start <= end).then_some((start, end)))
+}
+
+/// Author of one line as `git blame` reports it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlameLine {
+    pub author: String,
+    pub email: String,
+    pub time: i64,
+}
+
+/// Blame of each line of `file` at `rev` (the working tree when `None`),
+/// limited to `start..=end` when given. Lines not committed yet are `None`.
+pub fn blame_lines(
+    repo: &Path,
+    rev: Option<&str>,
+    file: &str,
+    range: Option<(usize, usize)>,
+) -> Result<Vec<Option<BlameLine>>, String> {
+    let range = range.map(|(start, end)| format!("-L{},{}", start, end));
+    let mut args = vec!["blame", "--line-porcelain"];
+    args.extend(range.as_deref());
+    args.extend(rev);
+    args.extend(["--", file]);
+    let out = run(repo, &args)?;
+
+    let mut lines = Vec::new();
+    let (mut hash, mut author, mut email, mut time) = (String::new(), String::new(), String::new(), 0i64);
+    for line in out.lines() {
+        if line.starts_with('\t') {
+            // Content line: closes this line's header block
+            let committed = !hash.bytes().all(|b| b == b'0');
+            lines.push(committed.then(|| BlameLine { author: author.clone(), email: email.clone(), time }));
+        } else if let Some(v) = line.strip_prefix("author-mail ") {
+            email = v.trim_matches(|c| c == '<' || c == '>').to_string();
+        } else if let Some(v) = line.strip_prefix("author-time ") {
+            time = v.parse().unwrap_or(0);
+        } else if let Some(v) = line.strip_prefix("author ") {
+            author = v.to_string();
+        } else if line.len() >= 40 && line.as_bytes()[..40].iter().all(u8::is_ascii_hexdigit) {
+            hash = line[..40].to_string();
+        }
+    }
+    Ok(lines)
+}
+
+/// Per-author line counts for `start..=end` of `file` at `rev` (the working
+/// tree when `None`), plus the number of lines not committed yet.
+fn blame_summary(
+    repo: &Path,
+    rev: Option<&str>,
+    file: &str,
+    start: usize,
+    end: usize,
+) -> Result<(Vec<BlameAuthor>, usize), String> {
+    let mut authors: HashMap<(String, String), BlameAuthor> = HashMap::new();
+    let mut uncommitted = 0;
+    for line in blame_lines(repo, rev, file, Some((start, end)))? {
+        let Some(line) = line else {
+            uncommitted += 1;
+            continue;
+        };
+        let entry = authors
+            .entry((line.author.clone(), line.email.clone()))
+            .or_insert_with(|| BlameAuthor {
+                author: line.author,
+                email: line.email,
+                lines: 0,
+                last_time: 0,
+            });
+        entry.lines += 1;
+        entry.last_time = entry.last_time.max(line.time);
+    }
+
+    let mut blame: Vec<BlameAuthor> = authors.into_values().collect();
+    blame.sort_by(|a, b| b.lines.cmp(&a.lines).then(b.last_time.cmp(&a.last_time)));
+    Ok((blame, uncommitted))
+}
+
+/// Git history of `entity`'s line range and a blame summary of its body,
+/// for an entity parsed at `rev` or, when `None`, from the working tree.
+/// Working-tree history starts at HEAD, with the range mapped past any
+/// uncommitted edits to the file.
+pub fn entity_history(repo: &Path, entity: &Entity, rev: Option<&str>) -> Result<EntityHistory, String> {
+    let rev = rev.map(rev_arg).transpose()?;
+    let (start, end) = (entity.line, entity.end_line.max(entity.line));
+    let (blame, uncommitted_lines) = blame_summary(repo, rev, &entity.file, start, end)?;
+    // A file that was never committed has no history to walk
+    let log_range = if blame.is_empty() {
+        None
+    } else {
+        match rev {
+            Some(_) => Some((start, end)),
+            None => range_at_head(repo, &entity.file, start, end)?,
+        }
+    };
+    let commits = match log_range {
+        Some((start, end)) => line_history(repo, rev.unwrap_or("HEAD"), &entity.file, start, end)?,
+        None => Vec::new(),
+    };
+    Ok(EntityHistory { entity_id: entity.id.clone(), commits, blame, uncommitted_lines })
+}
+
+/// A `-a,b +c,d` hunk header: `b` lines at `a` replaced by `d` lines at `c`.
+/// A zero count places the start before the first line of the other side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+}
+
+/// One file in a commit; a path is `None` when the file was added or deleted.
+#[derive(Debug, Clone, Default)]
+pub struct FileChange {
+    pub old_path: Option<String>,
+    pub new_path: Option<String>,
+    pub hunks: Vec<Hunk>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CommitChanges {
+    pub email: String,
+    pub time: i64,
+    pub files: Vec<FileChange>,
+}
+
+fn parse_hunk_side(side: &str) -> (usize, usize) {
+    match side.split_once(',') {
+        Some((start, count)) => (start.parse().unwrap_or(0), count.parse().unwrap_or(0)),
+        None => (side.parse().unwrap_or(0), 1),
+    }
+}
+
+/// Parse `git log -U0 -M` output with `%x1e%ae%x1f%at` headers.
+fn parse_log_changes(log: &str) -> Vec<CommitChanges> {
+    log.split('\x1e')
+        .filter(|chunk| !chunk.trim().is_empty())
+        .filter_map(|chunk| {
+            let (header, patch) = chunk.split_once('\n').unwrap_or((chunk, ""));
+            let fields: Vec<&str> = header.split('\x1f').collect();
+            let [email, time] = fields.as_slice() else {
+                return None;
+            };
+
+            let mut files: Vec<FileChange> = Vec::new();
+            for line in patch.lines() {
+                if let Some(paths) = line.strip_prefix("diff --git ") {
+                    // Paths are confirmed by ---/+++ or rename lines below
+                    let (a, b) = paths.split_once(" b/").unwrap_or((paths, ""));
+                    let a = a.strip_prefix("a/").unwrap_or(a);
+                    files.push(FileChange {
+                        old_path: Some(a.to_string()),
+                        new_path: Some(b.to_string()),
+                        hunks: Vec::new(),
+                    });
+                    continue;
+                }
+                let Some(file) = files.last_mut() else { continue };
+                if let Some(path) = line.strip_prefix("rename from ") {
+                    file.old_path = Some(path.to_string());
+                } else if let Some(path) = line.strip_prefix("rename to ") {
+                    file.new_path = Some(path.to_string());
+                } else if let Some(path) = line.strip_prefix("--- ") {
+                    file.old_path = path.strip_prefix("a/").map(str::to_string);
+                } else if let Some(path) = line.strip_prefix("+++ ") {
+                    file.new_path = path.strip_prefix("b/").map(str::to_string);
+                } else if let Some(hunk) = line.strip_prefix("@@ ") {
+                    let mut sides = hunk.split_whitespace();
+                    let (Some(old), Some(new)) = (sides.next(), sides.next()) else { continue };
+                    let (old_start, old_lines) = parse_hunk_side(old.trim_start_matches('-'));
+                    let (new_start, new_lines) = parse_hunk_side(new.trim_start_matches('+'));
+                    file.hunks.push(Hunk { old_start, old_lines, new_start, new_lines });
+                }
+            }
+            Some(CommitChanges {
+                email: email.to_string(),
+                time: time.parse().unwrap_or(0),
+                files,
+            })
+        })
+        .collect()
+}
+
+/// Non-merge commits up to `rev` (HEAD when `None`) since `since` (any
+/// `git log --since` date, e.g. `6 months ago`), newest first, with their
+/// zero-context hunks. Renames are detected; paths are relative to `repo`.
+pub fn log_changes(repo: &Path, rev: Option<&str>, since: &str) -> Result<Vec<CommitChanges>, String> {
+    let since = format!("--since={}", since);
+    let out = run(
+        repo,
+        &[
+            "log",
+            rev_arg(rev.unwrap_or("HEAD"))?,
+            &since,
+            "--no-merges",
+            "-M",
+            "-U0",
+            "--no-color",
+            "--no-ext-diff",
+            "--relative",
+            // Fixed prefixes: `diff.noprefix` and `diff.mnemonicPrefix` change the defaults
+            "--src-prefix=a/",
+            "--dst-prefix=b/",
+            "--format=%x1e%ae%x1f%at",
+            "--",
+            ".",
+        ],
+    )?;
+    Ok(parse_log_changes(&out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FodeConfig;
+
+    /// Fresh repo in a temp dir; returns its path.
+    fn temp_repo(name: &str) -> PathBuf {
+        let repo = std::env::temp_dir().join(format!("fode-git-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&repo);
+        std::fs::create_dir_all(repo.join("pkg")).unwrap();
+        run(&repo, &["init", "--quiet"]).unwrap();
+        run(&repo, &["config", "user.email", "dev@example.com"]).unwrap();
+        run(&repo, &["config", "user.name", "Dev"]).unwrap();
+        repo
+    }
+
+    #[test]
+    fn revisions_are_read_without_a_checkout() {
+        let repo = temp_repo("revision");
+        let git = |args: &[&str]| run(&repo, args).unwrap();
+
+        std::fs::write(repo.join("pkg/a.go"), "package pkg\n\nfunc Old() {}\n").unwrap();
+        std::fs::write(repo.join("pkg/b.go"), "package pkg\n\nfunc Other() {}\n").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "--quiet", "-m", "first"]);
+        std::fs::write(repo.join("pkg/a.go"), "package pkg\n\nfunc New() {}\n").unwrap();
+        git(&["commit", "--quiet", "-am", "second"]);
+
+        let (info, graph) = parse_revision(&repo.join("pkg"), "HEAD~1").unwrap();
+        let mut names: Vec<(&str, &str)> = graph.entities.iter().map(|e| (e.file.as_str(), e.name.as_str())).collect();
+        names.sort();
+        assert_eq!(names, vec![("a.go", "Old"), ("b.go", "Other")]);
+        assert!(info.name.ends_with("@ HEAD~1"));
+
+        let tree = RevisionTree::open(&repo.join("pkg"), "HEAD").unwrap();
+        assert!(tree.read(Path::new("missing.go")).is_err());
+        assert!(tree.read(Path::new("a.go")).unwrap().contains("New"));
+
+        // Nothing was checked out
+        assert_eq!(git(&["worktree", "list"]).lines().count(), 1);
+        assert_eq!(git(&["status", "--porcelain"]), "");
+        assert!(resolve(&repo, "no-such-ref").is_err());
+
+        std::fs::remove_dir_all(&repo).unwrap();
+    }
+
+    #[test]
+    fn repo_settings_are_read_at_the_revision() {
+        let repo = temp_repo("config");
+        let git = |args: &[&str]| run(&repo, args).unwrap();
+
+        std::fs::write(repo.join(".fode.toml"), "[dead_code]\nroots = [\"Old\"]\n").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "--quiet", "-m", "first"]);
+        std::fs::write(repo.join(".fode.toml"), "[dead_code]\nroots = [\"New\"]\n").unwrap();
+
+        let config = FodeConfig::load(&RevisionTree::open(&repo, "HEAD").unwrap());
+        assert_eq!(config.dead_code.roots, vec!["Old"]);
+        let config = FodeConfig::load(&parser::WorkingTree(&repo));
+        assert_eq!(config.dead_code.roots, vec!["New"]);
+
+        std::fs::remove_dir_all(&repo).unwrap();
+    }
+
+    #[test]
+    fn option_like_ranges_are_refused() {
+        let repo = temp_repo("range");
+        let output = repo.join("out");
+        assert!(changed_lines(&repo, Some(&format!("--output={}", output.display()))).is_err());
+        assert!(!output.exists());
+        std::fs::remove_dir_all(&repo).unwrap();
+    }
+
+    #[test]
+    fn changed_lines_ignore_diff_prefix_settings() {
+        let repo = temp_repo("prefix");
+        let git = |args: &[&str]| run(&repo, args).unwrap();
+
+        std::fs::write(repo.join("pkg/a.go"), "package pkg\n\nfunc A() {}\n").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "--quiet", "-m", "first"]);
+        std::fs::write(repo.join("pkg/a.go"), "package pkg\n\nfunc A() {}\n\nfunc B() {}\n").unwrap();
+
+        for setting in ["diff.noprefix", "diff.mnemonicPrefix"] {
+            git(&["config", setting, "true"]);
+            let changed = changed_lines(&repo, None).unwrap();
+            assert_eq!(changed.get("pkg/a.go"), Some(&vec![(4, 5)]), "{}", setting);
+            git(&["config", "--unset", setting]);
+        }
+
+        std::fs::remove_dir_all(&repo).unwrap();
+    }
+
+    #[test]
+    fn diff_hunks_become_new_side_line_ranges() {
+        let diff = "\
+diff --git a/pkg/a.go b/pkg/a.go
+--- a/pkg/a.go
++++ b/pkg/a.go
+@@ -3 +3 @@ func A() {
+@@ -10,2 +10,0 @@ func B() {
+@@ -20,0 +19,3 @@
+diff --git a/old.go b/old.go
+--- a/old.go
++++ /dev/null
+@@ -1,5 +0,0 @@
+";
+        let changed = parse_changed_lines(diff);
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed["pkg/a.go"], vec![(3, 3), (10, 11), (19, 21)]);
+    }
+
+    #[test]
+    fn log_hunks_keep_both_sides_and_renames() {
+        let log = "\x1eann@example.com\x1f1700000000
+diff --git a/pkg/a.go b/pkg/a.go
+index 1..2 100644
+--- a/pkg/a.go
++++ b/pkg/a.go
+@@ -3 +3,2 @@ func A() {
+@@ -10,2 +11,0 @@ func B() {
+diff --git a/old.go b/new.go
+similarity index 100%
+rename from old.go
+rename to new.go
+diff --git a/gone.go b/gone.go
+deleted file mode 100644
+--- a/gone.go
++++ /dev/null
+@@ -1,5 +0,0 @@
+\x1ebo@example.com\x1f1690000000
+";
+        let commits = parse_log_changes(log);
+        assert_eq!(commits.len(), 2);
+        let files = &commits[0].files;
+        assert_eq!(
+            files[0].hunks,
+            vec![
+                Hunk { old_start: 3, old_lines: 1, new_start: 3, new_lines: 2 },
+                Hunk { old_start: 10, old_lines: 2, new_start: 11, new_lines: 0 },
+            ]
+        );
+        assert_eq!((files[1].old_path.as_deref(), files[1].new_path.as_deref()), (Some("old.go"), Some("new.go")));
+        assert_eq!(files[2].new_path, None);
+        assert_eq!((commits[1].email.as_str(), commits[1].time), ("bo@example.com", 1690000000));
+    }
+
+    #[test]
+    fn log_paths_ignore_diff_prefix_settings() {
+        let repo = temp_repo("log-prefix");
+        let git = |args: &[&str]| run(&repo, args).unwrap();
+
+        std::fs::write(repo.join("pkg/a.go"), "package pkg\n").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "--quiet", "-m", "first"]);
+        git(&["mv", "pkg/a.go", "pkg/b.go"]);
+        git(&["commit", "--quiet", "-m", "rename"]);
+
+        for setting in ["diff.noprefix", "diff.mnemonicPrefix"] {
+            git(&["config", setting, "true"]);
+            let commits = log_changes(&repo, None, "2000-01-01").unwrap();
+            let paths: Vec<(Option<&str>, Option<&str>)> = commits
+                .iter()
+                .flat_map(|c| &c.files)
+                .map(|f| (f.old_path.as_deref(), f.new_path.as_deref()))
+                .collect();
+            assert_eq!(paths, vec![(Some("pkg/a.go"), Some("pkg/b.go")), (None, Some("pkg/a.go"))], "{}", setting);
+            git(&["config", "--unset", setting]);
+        }
+
+        std::fs::remove_dir_all(&repo).unwrap();
+    }
+
+    #[test]
+    fn history_follows_the_entity_within_its_file() {
+        let repo = temp_repo("history");
+        let git = |args: &[&str]| run(&repo, args).unwrap();
+        let commit = |author: &str, message: &str| {
+            git(&["-c", &format!("user.name={}", author), "commit", "--quiet", "-am", message]);
+        };
+
+        std::fs::write(repo.join("pkg/a.go"), "package pkg\n\nfunc Run() {\n\tone()\n}\n").unwrap();
+        git(&["add", "."]);
+        commit("Ann", "add Run");
+        // Push Run down the file, then edit its body
+        std::fs::write(
+            repo.join("pkg/a.go"),
+            "package pkg\n\nfunc Other() {}\n\nfunc Run() {\n\tone()\n}\n",
+        )
+        .unwrap();
+        commit("Bob", "add Other");
+        std::fs::write(
+            repo.join("pkg/a.go"),
+            "package pkg\n\nfunc Other() {}\n\nfunc Run() {\n\tone()\n\ttwo()\n}\n",
+        )
+        .unwrap();
+        commit("Bob", "call two");
+        std::fs::write(
+            repo.join("pkg/a.go"),
+            "package pkg\n\nfunc Other() {}\n\nfunc Run() {\n\tone()\n\ttwo()\n\tthree()\n}\n",
+        )
+        .unwrap();
+
+        let (_, graph) = parser::parse_repo(&repo.join("pkg"), None).unwrap();
+        let run_fn = graph.entities.iter().find(|e| e.name == "Run").unwrap();
+        let history = entity_history(&repo.join("pkg"), run_fn, None).unwrap();
+
+        let summaries: Vec<&str> = history.commits.iter().map(|c| c.summary.as_str()).collect();
+        assert_eq!(summaries, vec!["call two", "add Run"]);
+        assert!(history.commits[0].snippet.contains("+\ttwo()"));
+
+        let blame: Vec<(&str, usize)> = history.blame.iter().map(|b| (b.author.as_str(), b.lines)).collect();
+        assert_eq!(blame, vec![("Ann", 3), ("Bob", 1)]);
+        assert_eq!(history.uncommitted_lines, 1);
+        // Not passed on to git as an option
+        let output = repo.join("out");
+        let rev = format!("--output={}", output.display());
+        assert!(entity_history(&repo.join("pkg"), run_fn, Some(&rev)).is_err());
+        assert!(!output.exists());
+
+        std::fs::remove_dir_all(&repo).unwrap();
+    }
+
+    #[test]
+    fn history_maps_uncommitted_edits_onto_head() {
+        let repo = temp_repo("dirty");
+        let git = |args: &[&str]| run(&repo, args).unwrap();
+
+        std::fs::write(repo.join("pkg/a.go"), "package pkg\n\nfunc Run() {\n\tone()\n}\n").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "--quiet", "-m", "add Run"]);
+        std::fs::write(repo.join("pkg/a.go"), "package pkg\n\nfunc Run() {\n\tone()\n\ttwo()\n}\n").unwrap();
+        git(&["commit", "--quiet", "-am", "call two"]);
+        // Uncommitted: a new function above Run and a new line inside it
+        std::fs::write(
+            repo.join("pkg/a.go"),
+            "package pkg\n\nfunc Extra() {}\n\nfunc Run() {\n\tzero()\n\tone()\n\ttwo()\n}\n",
+        )
+        .unwrap();
+
+        let (_, graph) = parser::parse_repo(&repo.join("pkg"), None).unwrap();
+        let entity = |name: &str| graph.entities.iter().find(|e| e.name == name).unwrap();
+        assert_eq!(range_at_head(&repo.join("pkg"), "a.go", 5, 9).unwrap(), Some((3, 6)));
+
+        let history = entity_history(&repo.join("pkg"), entity("Run"), None).unwrap();
+        let summaries: Vec<&str> = history.commits.iter().map(|c| c.summary.as_str()).collect();
+        assert_eq!(summaries, vec!["call two", "add Run"]);
+        assert_eq!(history.uncommitted_lines, 1);
+
+        // Nothing of Extra is at HEAD yet
+        let history = entity_history(&repo.join("pkg"), entity("Extra"), None).unwrap();
+        assert!(history.commits.is_empty());
+
+        std::fs::remove_dir_all(&repo).unwrap();
+    }
+}
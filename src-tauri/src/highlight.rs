@@ -0,0 +1,188 @@
+//! Syntax highlighting with the highlight queries the grammar crates ship,
+//! so entity source is colored the same way for every supported language
+//! without a highlighter in the UI.
+
+use crate::parser::{self, DetectedLanguage, Entity};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::OnceLock;
+use streaming_iterator::StreamingIterator;
+use tree_sitter::{Query, QueryCursor, Tree};
+
+/// A run of source text with one highlight.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HighlightSpan {
+    /// Byte offsets into the highlighted source.
+    pub start: usize,
+    pub end: usize,
+    /// Capture name from the query, e.g. `keyword` or `function.method`.
+    pub capture: String,
+}
+
+/// The grammar's highlight query, compiled once per language.
+fn query(lang: &DetectedLanguage) -> Option<&'static Query> {
+    static GO: OnceLock<Option<Query>> = OnceLock::new();
+    static RUST: OnceLock<Option<Query>> = OnceLock::new();
+    static PYTHON: OnceLock<Option<Query>> = OnceLock::new();
+    static JAVASCRIPT: OnceLock<Option<Query>> = OnceLock::new();
+
+    let (cell, source) = match lang {
+        DetectedLanguage::Go => (&GO, tree_sitter_go::HIGHLIGHTS_QUERY),
+        DetectedLanguage::Rust => (&RUST, tree_sitter_rust::HIGHLIGHTS_QUERY),
+        DetectedLanguage::Python => (&PYTHON, tree_sitter_python::HIGHLIGHTS_QUERY),
+        DetectedLanguage::JavaScript => (&JAVASCRIPT, tree_sitter_javascript::HIGHLIGHT_QUERY),
+    };
+    cell.get_or_init(|| match Query::new(&lang.tree_sitter_language(), source) {
+        Ok(query) => Some(query),
+        Err(e) => {
+            eprintln!("[fode] {} highlight query failed: {}", lang.name(), e);
+            None
+        }
+    })
+    .as_ref()
+}
+
+/// Precedence of a capture on a node already captured: later patterns win,
+/// as in tree-sitter's highlighter, except that the catch-all `variable` and
+/// `property` only apply when nothing else does. Most grammars list those
+/// first, but Go lists them last.
+fn precedence(pattern_index: usize, name: &str) -> (bool, usize) {
+    (!matches!(name, "variable" | "property"), pattern_index)
+}
+
+/// Highlight `range` of `source`, parsed as `tree`, with offsets relative
+/// to the start of `range`. Spans come out ordered and disjoint: a nested
+/// capture, such as an escape in a string, splits the outer one.
+pub fn highlight(source: &str, tree: &Tree, lang: &DetectedLanguage, range: Range<usize>) -> Vec<HighlightSpan> {
+    let Some(query) = query(lang) else {
+        return Vec::new();
+    };
+    let names = query.capture_names();
+
+    // (start, end) -> (pattern, capture name)
+    let mut by_node: HashMap<(usize, usize), (usize, &str)> = HashMap::new();
+    let mut cursor = QueryCursor::new();
+    cursor.set_byte_range(range.clone());
+    let mut captures = cursor.captures(query, tree.root_node(), source.as_bytes());
+    while let Some((m, i)) = captures.next() {
+        let capture = m.captures[*i];
+        let name = names[capture.index as usize];
+        let (start, end) = (capture.node.start_byte().max(range.start), capture.node.end_byte().min(range.end));
+        // Names starting with `_` only serve predicates
+        if start >= end || name.starts_with('_') {
+            continue;
+        }
+        let entry = by_node.entry((start, end)).or_insert((m.pattern_index, name));
+        if precedence(m.pattern_index, name) > precedence(entry.0, entry.1) {
+            *entry = (m.pattern_index, name);
+        }
+    }
+
+    let mut nested: Vec<(usize, usize, &str)> = by_node
+        .into_iter()
+        .map(|((start, end), (_, name))| (start - range.start, end - range.start, name))
+        .collect();
+    // Outer spans first, so each is on the stack before the spans inside it
+    nested.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+
+    let mut spans: Vec<HighlightSpan> = Vec::new();
+    let mut push = |start: usize, end: usize, capture: &str| match spans.last_mut() {
+        Some(last) if last.end == start && last.capture == capture => last.end = end,
+        _ => spans.push(HighlightSpan { start, end, capture: capture.to_string() }),
+    };
+    let mut stack: Vec<(usize, usize, &str)> = Vec::new();
+    let mut pos = 0;
+    for span in nested.into_iter().chain([(usize::MAX, usize::MAX, "")]) {
+        // Emit what the open spans cover up to where this one starts
+        while let Some(&(_, end, capture)) = stack.last() {
+            let stop = end.min(span.0);
+            if stop > pos {
+                push(pos, stop, capture);
+                pos = stop;
+            }
+            if end > span.0 {
+                break;
+            }
+            stack.pop();
+        }
+        pos = pos.max(span.0);
+        stack.push(span);
+    }
+    spans
+}
+
+/// Highlights for `entity`'s source. `file` is the text of its file in the
+/// loaded revision, so the source is highlighted in context; when it is
+/// missing or no longer holds the entity at its line, the source is parsed
+/// on its own, which a method cut out of its class may not survive intact.
+pub fn highlight_entity(entity: &Entity, file: Option<&str>) -> Vec<HighlightSpan> {
+    let Some(lang) = DetectedLanguage::of_file(&entity.file) else {
+        return Vec::new();
+    };
+    let start = file.and_then(|file| {
+        let line_start: usize = file.split_inclusive('\n').take(entity.line.saturating_sub(1)).map(str::len).sum();
+        let offset = file.get(line_start..)?.find(&entity.source)?;
+        // Only a match on the entity's own first line
+        (!file[line_start..line_start + offset].contains('\n')).then_some(line_start + offset)
+    });
+    let (text, range) = match (file, start) {
+        (Some(file), Some(start)) => (file, start..start + entity.source.len()),
+        _ => (entity.source.as_str(), 0..entity.source.len()),
+    };
+    match parser::parse_file(text, lang.tree_sitter_language()) {
+        Some(tree) => highlight(text, &tree, &lang, range),
+        None => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::EntityKind;
+
+    fn captured<'a>(source: &'a str, spans: &[HighlightSpan]) -> Vec<(&'a str, String)> {
+        spans.iter().map(|s| (&source[s.start..s.end], s.capture.clone())).collect()
+    }
+
+    #[test]
+    fn spans_are_disjoint_and_nested_captures_split_outer_ones() {
+        let source = "package p\n\n// Greet says hi.\nfunc Greet(name string) string {\n\treturn \"hi\\n\" + name\n}\n";
+        let tree = parser::parse_file(source, DetectedLanguage::Go.tree_sitter_language()).unwrap();
+        let spans = highlight(source, &tree, &DetectedLanguage::Go, 0..source.len());
+
+        assert!(spans.windows(2).all(|w| w[0].end <= w[1].start));
+        let spans = captured(source, &spans);
+        for expected in [
+            ("// Greet says hi.", "comment"),
+            ("func", "keyword"),
+            ("Greet", "function"),
+            ("string", "type"),
+            ("return", "keyword"),
+            ("\"hi", "string"),
+            ("\\n", "escape"),
+            ("\"", "string"),
+        ] {
+            assert!(spans.contains(&(expected.0, expected.1.to_string())), "{:?} in {:?}", expected, spans);
+        }
+    }
+
+    #[test]
+    fn entities_are_highlighted_in_their_file() {
+        let file = "class Greeter:\n    def greet(self, name):\n        return f\"hi {name}\"\n";
+        let source = "def greet(self, name):\n        return f\"hi {name}\"";
+        let entity = Entity {
+            source: source.to_string(),
+            owner: Some("Greeter".to_string()),
+            ..Entity::stub("greeter.py", "Greeter.greet", EntityKind::Method, 2, 3)
+        };
+
+        let in_file = highlight_entity(&entity, Some(file));
+        assert_eq!(in_file.first().map(|s| (s.start, s.end)), Some((0, 3)));
+        assert!(in_file.iter().all(|s| s.end <= source.len()));
+        let spans = captured(source, &in_file);
+        assert!(spans.contains(&("greet", "function".to_string())), "{:?}", spans);
+        // A file edited since parsing falls back to the source alone
+        assert!(!highlight_entity(&entity, Some("x = 1\n")).is_empty());
+    }
+}
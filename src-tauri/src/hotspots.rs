@@ -0,0 +1,252 @@
+//! Hotspots: functions that change often and are complex. Each commit in a
+//! time window is mapped onto entities by following their line ranges back
+//! through the hunks of newer commits, as `git log -L` does for one range.
+
+use crate::git::{CommitChanges, Hunk};
+use crate::parser::{Entity, EntityGraph, EntityKind};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Window used when none is given.
+pub const DEFAULT_SINCE: &str = "12 months ago";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hotspot {
+    pub id: String,
+    pub name: String,
+    pub kind: EntityKind,
+    pub package: String,
+    pub file: String,
+    pub line: usize,
+    /// Commits in the window that changed the entity's lines.
+    pub commits: usize,
+    /// Distinct author emails among those commits.
+    pub authors: usize,
+    pub complexity: usize,
+    pub loc: usize,
+    /// `commits * complexity`.
+    pub score: usize,
+    /// Author time of the newest of those commits, seconds since the epoch.
+    pub last_time: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HotspotReport {
+    pub since: String,
+    /// Commits scanned in the window.
+    pub commits: usize,
+    /// Highest score first.
+    pub hotspots: Vec<Hotspot>,
+}
+
+/// (candidate index, first line, last line) in one revision.
+type TrackedRange = (usize, usize, usize);
+
+/// Map `line` from the new side of `hunks` to the old side. A line inside a
+/// hunk maps to its old lines: the first of them for a range start, the last
+/// for a range end.
+fn map_line(hunks: &[Hunk], line: usize, is_start: bool) -> usize {
+    let mut delta: isize = 0;
+    for h in hunks {
+        if h.new_lines > 0 && h.new_start <= line && line < h.new_start + h.new_lines {
+            return match (is_start, h.old_lines) {
+                (true, 0) => h.old_start + 1,
+                (true, _) => h.old_start,
+                (false, 0) => h.old_start,
+                (false, n) => h.old_start + n - 1,
+            };
+        }
+        let above = if h.new_lines > 0 { h.new_start + h.new_lines <= line } else { h.new_start < line };
+        if !above {
+            break;
+        }
+        delta += h.old_lines as isize - h.new_lines as isize;
+    }
+    (line as isize + delta).max(0) as usize
+}
+
+/// Whether a hunk changed lines `start..=end` of its new side; a deletion
+/// touches a range it falls strictly inside.
+fn touches(h: &Hunk, start: usize, end: usize) -> bool {
+    if h.new_lines > 0 {
+        h.new_start <= end && h.new_start + h.new_lines > start
+    } else {
+        start <= h.new_start && h.new_start < end
+    }
+}
+
+/// Rank non-test functions and methods of `graph` by churn in `commits`
+/// (newest first, as from `git::log_changes`) times complexity. Line ranges
+/// are the graph's, so uncommitted edits that shift lines blur the mapping.
+pub fn hotspots(graph: &EntityGraph, commits: &[CommitChanges], since: &str, limit: Option<usize>) -> HotspotReport {
+    let candidates: Vec<&Entity> = graph
+        .entities
+        .iter()
+        .filter(|e| matches!(e.kind, EntityKind::Function | EntityKind::Method) && !e.is_test_code())
+        .collect();
+
+    // Path as of the commit being visited -> ranges in that revision
+    let mut tracked: HashMap<String, Vec<TrackedRange>> = HashMap::new();
+    for (i, e) in candidates.iter().enumerate() {
+        tracked.entry(e.file.clone()).or_default().push((i, e.line, e.end_line.max(e.line)));
+    }
+
+    let mut counts = vec![0usize; candidates.len()];
+    let mut authors: Vec<HashSet<&str>> = vec![HashSet::new(); candidates.len()];
+    let mut last_time = vec![0i64; candidates.len()];
+    for commit in commits {
+        let mut touched: HashSet<usize> = HashSet::new();
+        let mut older: Vec<(String, Vec<TrackedRange>)> = Vec::new();
+        for file in &commit.files {
+            let Some(ranges) = file.new_path.as_ref().and_then(|p| tracked.remove(p)) else {
+                continue;
+            };
+            let mut mapped = Vec::new();
+            for (i, start, end) in ranges {
+                if file.hunks.iter().any(|h| touches(h, start, end)) {
+                    touched.insert(i);
+                }
+                let (old_start, old_end) = (map_line(&file.hunks, start, true), map_line(&file.hunks, end, false));
+                // Entirely added by this commit: nothing older to follow
+                if old_start <= old_end {
+                    mapped.push((i, old_start, old_end));
+                }
+            }
+            if let Some(old_path) = &file.old_path {
+                older.push((old_path.clone(), mapped));
+            }
+        }
+        // Re-key after the whole commit so a rename does not shadow a file
+        // visited later in the same commit
+        for (path, ranges) in older {
+            tracked.entry(path).or_default().extend(ranges);
+        }
+        for i in touched {
+            counts[i] += 1;
+            authors[i].insert(commit.email.as_str());
+            last_time[i] = last_time[i].max(commit.time);
+        }
+    }
+
+    let mut hotspots: Vec<Hotspot> = candidates
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| counts[i] > 0)
+        .map(|(i, e)| Hotspot {
+            id: e.id.clone(),
+            name: e.name.clone(),
+            kind: e.kind.clone(),
+            package: e.package.clone(),
+            file: e.file.clone(),
+            line: e.line,
+            commits: counts[i],
+            authors: authors[i].len(),
+            complexity: e.metrics.complexity,
+            loc: e.metrics.loc,
+            score: counts[i] * e.metrics.complexity.max(1),
+            last_time: last_time[i],
+        })
+        .collect();
+    hotspots.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then(b.commits.cmp(&a.commits))
+            .then_with(|| (&a.file, a.line).cmp(&(&b.file, b.line)))
+    });
+    if let Some(limit) = limit {
+        hotspots.truncate(limit);
+    }
+    HotspotReport { since: since.to_string(), commits: commits.len(), hotspots }
+}
+
+/// Plain-text table of the hotspots.
+pub fn render_report(report: &HotspotReport) -> String {
+    let mut out = format!("Hotspots since {} ({} commits)\n\n", report.since, report.commits);
+    out.push_str("  score  commits  authors  complexity  entity\n");
+    for h in &report.hotspots {
+        out.push_str(&format!(
+            "  {:>5}  {:>7}  {:>7}  {:>10}  {} {}  {}:{}\n",
+            h.score,
+            h.commits,
+            h.authors,
+            h.complexity,
+            h.kind.label(),
+            h.name,
+            h.file,
+            h.line
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::FileChange;
+    use crate::metrics::SyntaxMetrics;
+
+    fn function(file: &str, name: &str, lines: (usize, usize), complexity: usize) -> Entity {
+        Entity {
+            package: "pkg".to_string(),
+            metrics: SyntaxMetrics { complexity, ..Default::default() },
+            ..Entity::stub(file, name, EntityKind::Function, lines.0, lines.1)
+        }
+    }
+
+    fn commit(email: &str, files: Vec<FileChange>) -> CommitChanges {
+        CommitChanges { email: email.to_string(), time: 1, files }
+    }
+
+    fn change(old: Option<&str>, new: Option<&str>, hunks: &[(usize, usize, usize, usize)]) -> FileChange {
+        FileChange {
+            old_path: old.map(str::to_string),
+            new_path: new.map(str::to_string),
+            hunks: hunks
+                .iter()
+                .map(|&(old_start, old_lines, new_start, new_lines)| Hunk { old_start, old_lines, new_start, new_lines })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn lines_map_back_through_hunks() {
+        // Two lines inserted after 4, one deleted after new line 10
+        let hunks = [
+            Hunk { old_start: 4, old_lines: 0, new_start: 5, new_lines: 2 },
+            Hunk { old_start: 9, old_lines: 1, new_start: 10, new_lines: 0 },
+        ];
+        assert_eq!(map_line(&hunks, 3, true), 3);
+        assert_eq!(map_line(&hunks, 5, true), 5);
+        assert_eq!(map_line(&hunks, 6, false), 4);
+        assert_eq!(map_line(&hunks, 8, true), 6);
+        assert_eq!(map_line(&hunks, 12, true), 11);
+    }
+
+    #[test]
+    fn churn_follows_entities_through_moves_and_renames() {
+        let graph = EntityGraph {
+            entities: vec![
+                function("pkg/new.go", "Busy", (10, 20), 4),
+                function("pkg/new.go", "Calm", (22, 25), 9),
+                function("pkg/new.go", "Fresh", (27, 30), 2),
+            ],
+            relations: Vec::new(),
+            external_deps: Default::default(),
+        };
+        let commits = vec![
+            // Newest: Fresh added at the end, Busy edited
+            commit("ann@x", vec![change(Some("pkg/new.go"), Some("pkg/new.go"), &[(12, 1, 12, 1), (26, 0, 27, 4)])]),
+            // Renamed old.go -> new.go, no content change
+            commit("bo@x", vec![change(Some("pkg/old.go"), Some("pkg/new.go"), &[])]),
+            // Five lines removed above Busy, then Busy edited at its old position
+            commit("bo@x", vec![change(Some("pkg/old.go"), Some("pkg/old.go"), &[(1, 5, 0, 0)])]),
+            commit("cy@x", vec![change(Some("pkg/old.go"), Some("pkg/old.go"), &[(16, 2, 16, 3)])]),
+        ];
+
+        let report = hotspots(&graph, &commits, "forever", None);
+        let summary: Vec<(&str, usize, usize, usize)> =
+            report.hotspots.iter().map(|h| (h.name.as_str(), h.commits, h.authors, h.score)).collect();
+        assert_eq!(summary, vec![("Busy", 2, 2, 8), ("Fresh", 1, 1, 2)]);
+        assert_eq!(hotspots(&graph, &commits, "forever", Some(1)).hotspots.len(), 1);
+    }
+}
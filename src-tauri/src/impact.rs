@@ -0,0 +1,236 @@
+//! Change-impact analysis: which entities a diff touches, and everything
+//! that calls or references them, directly or through other callers.
+
+use crate::git::ChangedLines;
+use crate::parser::{file_dir, Entity, EntityGraph, EntityKind, RelationKind};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+/// An entity reached by the impact walk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImpactedEntity {
+    pub id: String,
+    pub name: String,
+    pub kind: EntityKind,
+    pub package: String,
+    pub file: String,
+    pub line: usize,
+    /// Steps from a changed entity; 0 for the changed entities themselves.
+    pub depth: usize,
+    /// The entity one step closer to the change that this one calls or references.
+    pub via: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImpactReport {
+    /// The diff analysed, e.g. `main..topic` or `working tree`.
+    pub range: String,
+    pub changed_files: Vec<String>,
+    pub changed: Vec<ImpactedEntity>,
+    /// Entities depending on the change, nearest first.
+    pub affected: Vec<ImpactedEntity>,
+    /// Package directories of changed and affected entities.
+    pub packages: Vec<String>,
+    /// Changed or affected tests: the ones worth re-running.
+    pub tests: Vec<ImpactedEntity>,
+}
+
+/// Innermost entity spanning any changed line, per hunk, so an edit inside a
+/// method marks the method rather than its whole class.
+pub fn changed_entities<'a>(graph: &'a EntityGraph, changed: &ChangedLines) -> Vec<&'a Entity> {
+    let mut by_file: HashMap<&str, Vec<&Entity>> = HashMap::new();
+    for e in &graph.entities {
+        by_file.entry(e.file.as_str()).or_default().push(e);
+    }
+
+    let mut hit: Vec<&Entity> = Vec::new();
+    for (file, ranges) in changed {
+        let Some(entities) = by_file.get(file.as_str()) else {
+            continue;
+        };
+        for &(start, end) in ranges {
+            // Every line of the hunk could sit in a different entity
+            let mut innermost: HashMap<usize, &Entity> = HashMap::new();
+            for e in entities.iter().filter(|e| e.line <= end && e.end_line >= start) {
+                for line in e.line.max(start)..=e.end_line.min(end) {
+                    let span = |x: &Entity| x.end_line - x.line;
+                    let current = innermost.entry(line).or_insert(e);
+                    if span(e) < span(current) {
+                        *current = e;
+                    }
+                }
+            }
+            for e in innermost.into_values() {
+                if !hit.iter().any(|h| h.id == e.id) {
+                    hit.push(e);
+                }
+            }
+        }
+    }
+    hit.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+    hit
+}
+
+fn impacted(entity: &Entity, depth: usize, via: Option<String>) -> ImpactedEntity {
+    ImpactedEntity {
+        id: entity.id.clone(),
+        name: entity.name.clone(),
+        kind: entity.kind.clone(),
+        package: entity.package.clone(),
+        file: entity.file.clone(),
+        line: entity.line,
+        depth,
+        via,
+    }
+}
+
+/// Map changed `lines` onto `graph` and walk incoming Calls/References
+/// breadth-first, up to `max_depth` steps when given.
+pub fn analyze(graph: &EntityGraph, range: &str, lines: &ChangedLines, max_depth: Option<usize>) -> ImpactReport {
+    let by_id: HashMap<&str, &Entity> = graph.entities.iter().map(|e| (e.id.as_str(), e)).collect();
+    let mut callers: HashMap<&str, Vec<&str>> = HashMap::new();
+    for r in &graph.relations {
+        if matches!(r.kind, RelationKind::Calls | RelationKind::References) && r.from_id != r.to_id {
+            callers.entry(r.to_id.as_str()).or_default().push(r.from_id.as_str());
+        }
+    }
+
+    let changed_set = changed_entities(graph, lines);
+    let mut seen: HashSet<&str> = changed_set.iter().map(|e| e.id.as_str()).collect();
+    let mut queue: VecDeque<(&str, usize)> = changed_set.iter().map(|e| (e.id.as_str(), 0)).collect();
+
+    let mut affected = Vec::new();
+    while let Some((id, depth)) = queue.pop_front() {
+        if max_depth.is_some_and(|max| depth >= max) {
+            continue;
+        }
+        for &caller in callers.get(id).into_iter().flatten() {
+            let Some(entity) = by_id.get(caller) else {
+                continue;
+            };
+            if !seen.insert(caller) {
+                continue;
+            }
+            queue.push_back((caller, depth + 1));
+            affected.push(impacted(entity, depth + 1, Some(id.to_string())));
+        }
+    }
+
+    let changed: Vec<ImpactedEntity> = changed_set.iter().map(|e| impacted(e, 0, None)).collect();
+    let packages: BTreeSet<String> = changed
+        .iter()
+        .chain(&affected)
+        .map(|e| file_dir(&e.file))
+        .collect();
+    let tests = changed
+        .iter()
+        .chain(&affected)
+        .filter(|e| by_id.get(e.id.as_str()).is_some_and(|entity| entity.is_test))
+        .cloned()
+        .collect();
+    let mut changed_files: Vec<String> = lines.keys().cloned().collect();
+    changed_files.sort();
+
+    ImpactReport {
+        range: range.to_string(),
+        changed_files,
+        changed,
+        affected,
+        packages: packages.into_iter().collect(),
+        tests,
+    }
+}
+
+/// Plain-text report: changed entities, then affected ones with the
+/// entity they depend on, packages and tests.
+pub fn render_report(report: &ImpactReport) -> String {
+    let names: HashMap<&str, &str> = report
+        .changed
+        .iter()
+        .chain(&report.affected)
+        .map(|e| (e.id.as_str(), e.name.as_str()))
+        .collect();
+    let entry = |e: &ImpactedEntity| format!("{} {}  {}:{}", e.kind.label(), e.name, e.file, e.line);
+
+    let mut out = format!(
+        "Impact of {}: {} changed, {} affected, {} packages, {} tests\n",
+        report.range,
+        report.changed.len(),
+        report.affected.len(),
+        report.packages.len(),
+        report.tests.len()
+    );
+    out.push_str("\nchanged\n");
+    for e in &report.changed {
+        out.push_str(&format!("  {}\n", entry(e)));
+    }
+    if !report.affected.is_empty() {
+        out.push_str("\naffected\n");
+        for e in &report.affected {
+            let via = e.via.as_deref().and_then(|id| names.get(id)).unwrap_or(&"");
+            out.push_str(&format!("  {}  (depth {}, via {})\n", entry(e), e.depth, via));
+        }
+    }
+    if !report.packages.is_empty() {
+        out.push_str("\npackages\n");
+        for dir in &report.packages {
+            out.push_str(&format!("  {}\n", if dir.is_empty() { "." } else { dir }));
+        }
+    }
+    if !report.tests.is_empty() {
+        out.push_str("\ntests\n");
+        for e in &report.tests {
+            out.push_str(&format!("  {}\n", entry(e)));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Relation;
+
+    fn entity(file: &str, name: &str, kind: EntityKind, lines: (usize, usize)) -> Entity {
+        Entity { package: "pkg".to_string(), ..Entity::stub(file, name, kind, lines.0, lines.1) }
+    }
+
+    fn relation(from: &Entity, to: &Entity, kind: RelationKind) -> Relation {
+        Relation { from_id: from.id.clone(), to_id: to.id.clone(), kind }
+    }
+
+    #[test]
+    fn walks_callers_transitively_from_changed_lines() {
+        let store = entity("store/store.go", "Store", EntityKind::Struct, (3, 20));
+        let get = entity("store/store.go", "Store.Get", EntityKind::Method, (10, 14));
+        let handler = entity("api/api.go", "Handle", EntityKind::Function, (5, 9));
+        let server = entity("cmd/main.go", "main", EntityKind::Function, (3, 6));
+        let mut test = entity("api/api_test.go", "TestHandle", EntityKind::Function, (5, 8));
+        test.is_test = true;
+        let unrelated = entity("api/api.go", "Other", EntityKind::Function, (11, 12));
+        let graph = EntityGraph {
+            relations: vec![
+                relation(&handler, &get, RelationKind::Calls),
+                relation(&server, &handler, RelationKind::References),
+                relation(&test, &handler, RelationKind::Calls),
+                relation(&unrelated, &get, RelationKind::Returns),
+            ],
+            entities: vec![store, get, handler, server, test, unrelated],
+            external_deps: Default::default(),
+        };
+
+        let lines: ChangedLines = [("store/store.go".to_string(), vec![(12, 12)])].into();
+        let report = analyze(&graph, "working tree", &lines, None);
+
+        let changed: Vec<&str> = report.changed.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(changed, vec!["Store.Get"]);
+        let affected: Vec<(&str, usize)> = report.affected.iter().map(|e| (e.name.as_str(), e.depth)).collect();
+        assert_eq!(affected, vec![("Handle", 1), ("main", 2), ("TestHandle", 2)]);
+        assert_eq!(report.packages, vec!["api", "cmd", "store"]);
+        let tests: Vec<&str> = report.tests.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(tests, vec!["TestHandle"]);
+
+        let shallow = analyze(&graph, "working tree", &lines, Some(1));
+        assert_eq!(shallow.affected.len(), 1);
+    }
+}
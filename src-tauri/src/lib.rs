@@ -1,5 +1,21 @@
+mod api;
+pub mod cli;
+mod code_index;
 mod commands;
+mod config;
+mod dead;
+mod deps;
+mod diff;
+mod duplicates;
+mod git;
+mod highlight;
+mod hotspots;
+mod impact;
+mod lsp;
+mod metrics;
+mod ownership;
 mod parser;
+mod test_map;
 
 use commands::AppState;
 use std::sync::Mutex;
@@ -14,6 +30,7 @@ pub fn run() {
             repo_info: Mutex::new(None),
             entity_graph: Mutex::new(None),
             repo_path: Mutex::new(None),
+            repo_rev: Mutex::new(None),
         })
         .invoke_handler(tauri::generate_handler![
             commands::open_repo,
@@ -22,7 +39,19 @@ pub fn run() {
             commands::get_entity_focus,
             commands::get_all_entities,
             commands::get_entity_source,
+            commands::get_metrics,
+            commands::get_hotspots,
+            commands::get_duplicates,
             commands::get_graph_data,
+            commands::get_api_surface,
+            commands::get_api_diff,
+            commands::get_entity_diff,
+            commands::get_entity_history,
+            commands::get_entity_owners,
+            commands::get_ownership,
+            commands::get_change_impact,
+            commands::get_test_selection,
+            commands::get_test_map,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,613 @@
+//! Optional language-server backend for reference resolution.
+//!
+//! Spawns the configured server (gopls, rust-analyzer, pyright, ...) over
+//! stdio, opens every parsed file and asks, per entity:
+//! - functions and methods: `callHierarchy/outgoingCalls` -> `Calls`
+//! - everything else: `textDocument/references` -> `References`/`Calls`
+//!
+//! Answers replace the tree-sitter heuristics for the entities the server
+//! answered for; entities it could not resolve keep their heuristic edges.
+//!
+//! The server is an arbitrary program, so only the user starts one, with
+//! the CLI's `--lsp` or in their settings; a repo's `.fode.toml` may only
+//! tune it.
+
+use crate::code_index::{use_relation_kind, EntityLocator};
+use crate::config::{RepoLspConfig, UserConfig};
+use crate::parser::{DetectedLanguage, Entity, EntityGraph, EntityKind, Relation, RelationKind};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+/// Stop asking after this many requests in a row fail, e.g. when the
+/// server hangs and every request runs into the timeout.
+const MAX_CONSECUTIVE_FAILURES: usize = 5;
+
+/// Default server command and arguments for a language.
+fn default_server(lang: &DetectedLanguage) -> (&'static str, &'static [&'static str]) {
+    match lang {
+        DetectedLanguage::Go => ("gopls", &[]),
+        DetectedLanguage::Rust => ("rust-analyzer", &[]),
+        DetectedLanguage::Python => ("pyright-langserver", &["--stdio"]),
+        DetectedLanguage::JavaScript => ("typescript-language-server", &["--stdio"]),
+    }
+}
+
+/// How to start a language server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerCommand {
+    pub command: String,
+    pub args: Vec<String>,
+    /// Per-request timeout.
+    pub timeout: Duration,
+}
+
+impl ServerCommand {
+    /// The server to run for a repo in `lang`: `explicit`, a command line
+    /// split on whitespace such as the CLI's `--lsp`, else the one in the
+    /// user's `[lsp.<language>]`; none when neither is given. The repo's
+    /// `[lsp]` can only tune that server, not start one.
+    pub fn choose(
+        lang: &DetectedLanguage,
+        explicit: Option<&str>,
+        user: &UserConfig,
+        repo: &RepoLspConfig,
+    ) -> Option<ServerCommand> {
+        let user_server = user.lsp.get(&lang.name().to_lowercase());
+        let (command, args) = match explicit {
+            Some(explicit) => {
+                let mut words = explicit.split_whitespace().map(str::to_string);
+                (words.next()?, words.collect())
+            }
+            None => {
+                let user = user_server?;
+                let (default_command, default_args) = default_server(lang);
+                let command = user.command.as_deref().unwrap_or(default_command).to_string();
+                let args = if user.command.is_none() && user.args.is_empty() {
+                    default_args.iter().map(|a| a.to_string()).collect()
+                } else {
+                    user.args.clone()
+                };
+                (command, args)
+            }
+        };
+        let timeout_secs =
+            user_server.and_then(|u| u.timeout_secs).or(repo.timeout_secs).unwrap_or(DEFAULT_TIMEOUT_SECS);
+        Some(ServerCommand { command, args, timeout: Duration::from_secs(timeout_secs) })
+    }
+}
+
+fn language_id(file: &str) -> &'static str {
+    match Path::new(file).extension().and_then(|e| e.to_str()) {
+        Some("go") => "go",
+        Some("rs") => "rust",
+        Some("py") => "python",
+        Some("ts") => "typescript",
+        Some("tsx") => "typescriptreact",
+        Some("jsx") => "javascriptreact",
+        _ => "javascript",
+    }
+}
+
+/// Read one `Content-Length` framed JSON-RPC message. Returns `None` at EOF.
+fn read_message(reader: &mut impl BufRead) -> Option<Value> {
+    let mut content_length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            if content_length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some(len) = line.strip_prefix("Content-Length:") {
+            content_length = len.trim().parse::<usize>().ok();
+        }
+    }
+    let mut body = vec![0; content_length?];
+    reader.read_exact(&mut body).ok()?;
+    Some(serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+fn write_message(writer: &mut impl Write, msg: &Value) -> std::io::Result<()> {
+    let body = msg.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+/// Minimal synchronous JSON-RPC client for a language server.
+pub struct LspClient {
+    writer: Box<dyn Write + Send>,
+    incoming: Receiver<Value>,
+    next_id: i64,
+    timeout: Duration,
+    child: Option<Child>,
+}
+
+impl LspClient {
+    /// Spawn a server process and talk to it over stdin/stdout.
+    pub fn spawn(command: &str, args: &[String], root: &Path, timeout: Duration) -> Result<Self, String> {
+        let mut child = Command::new(command)
+            .args(args)
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Failed to start language server `{}`: {}", command, e))?;
+        let stdin = child.stdin.take().ok_or("Language server has no stdin")?;
+        let stdout = child.stdout.take().ok_or("Language server has no stdout")?;
+        let mut client = Self::over(stdout, stdin, timeout);
+        client.child = Some(child);
+        Ok(client)
+    }
+
+    /// Talk to a server over an arbitrary transport.
+    pub fn over(
+        reader: impl Read + Send + 'static,
+        writer: impl Write + Send + 'static,
+        timeout: Duration,
+    ) -> Self {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut reader = BufReader::new(reader);
+            while let Some(msg) = read_message(&mut reader) {
+                if tx.send(msg).is_err() {
+                    break;
+                }
+            }
+        });
+        LspClient {
+            writer: Box::new(writer),
+            incoming: rx,
+            next_id: 1,
+            timeout,
+            child: None,
+        }
+    }
+
+    fn send(&mut self, msg: &Value) -> Result<(), String> {
+        write_message(&mut self.writer, msg).map_err(|e| format!("Language server write failed: {}", e))
+    }
+
+    pub fn notify(&mut self, method: &str, params: Value) -> Result<(), String> {
+        self.send(&json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+    }
+
+    /// Send a request and wait for its response. A JSON-RPC error response
+    /// yields `Null`; `Err` is reserved for transport failures and timeouts.
+    pub fn request(&mut self, method: &str, params: Value) -> Result<Value, String> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(&json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))?;
+
+        loop {
+            let msg = self
+                .incoming
+                .recv_timeout(self.timeout)
+                .map_err(|_| format!("Language server did not answer {}", method))?;
+
+            // Server-to-client requests must be answered or some servers stall
+            if let Some(server_method) = msg.get("method").and_then(|m| m.as_str()) {
+                if let Some(req_id) = msg.get("id") {
+                    let result = match server_method {
+                        "workspace/configuration" => {
+                            let count = msg["params"]["items"].as_array().map_or(0, |a| a.len());
+                            Value::Array(vec![Value::Null; count])
+                        }
+                        _ => Value::Null,
+                    };
+                    self.send(&json!({ "jsonrpc": "2.0", "id": req_id, "result": result }))?;
+                }
+                continue;
+            }
+
+            if msg.get("id") != Some(&json!(id)) {
+                continue;
+            }
+            if msg.get("error").is_some() {
+                return Ok(Value::Null);
+            }
+            return Ok(msg.get("result").cloned().unwrap_or(Value::Null));
+        }
+    }
+
+    pub fn shutdown(&mut self) {
+        let _ = self.request("shutdown", Value::Null);
+        let _ = self.notify("exit", Value::Null);
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+impl Drop for LspClient {
+    fn drop(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Relations answered by the language server.
+#[derive(Debug, Default)]
+pub struct LspRelations {
+    pub relations: Vec<Relation>,
+    /// Callables whose outgoing calls came from the server.
+    pub resolved_callers: HashSet<String>,
+    /// Entities whose incoming references came from the server.
+    pub resolved_targets: HashSet<String>,
+}
+
+fn path_to_uri(path: &Path) -> String {
+    format!("file://{}", path.to_string_lossy())
+}
+
+fn uri_to_file(uri: &str, root_uri: &str) -> Option<String> {
+    uri.strip_prefix(root_uri)
+        .map(|rel| rel.trim_start_matches('/').replace("%20", " "))
+}
+
+/// LSP position (0-based line, UTF-16 column) of an entity's name on its
+/// declaration line. Callables prefer an occurrence followed by `(`, `<` or `[`.
+fn name_position(entity: &Entity, line_text: &str) -> Option<Value> {
+    let member = entity.name.rsplit('.').next().unwrap_or(&entity.name);
+    if member.is_empty() {
+        return None;
+    }
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let occurrences: Vec<usize> = line_text
+        .match_indices(member)
+        .map(|(i, _)| i)
+        .filter(|&i| {
+            let before = line_text[..i].chars().next_back();
+            let after = line_text[i + member.len()..].chars().next();
+            !before.is_some_and(is_ident) && !after.is_some_and(is_ident)
+        })
+        .collect();
+    let callable = matches!(entity.kind, EntityKind::Function | EntityKind::Method);
+    let byte_col = occurrences
+        .iter()
+        .find(|&&i| {
+            callable
+                && line_text[i + member.len()..]
+                    .trim_start()
+                    .starts_with(['(', '<', '['])
+        })
+        .or(occurrences.first())?;
+    let character = line_text[..*byte_col].encode_utf16().count();
+    Some(json!({ "line": entity.line - 1, "character": character }))
+}
+
+/// Query call hierarchy and references for every entity. An entity whose
+/// requests fail or come back empty keeps its heuristic edges.
+pub fn query_relations(
+    client: &mut LspClient,
+    repo_path: &Path,
+    entities: &[Entity],
+) -> Result<LspRelations, String> {
+    let root_uri = path_to_uri(repo_path);
+    client.request(
+        "initialize",
+        json!({
+            "processId": std::process::id(),
+            "rootUri": root_uri,
+            "workspaceFolders": [{ "uri": root_uri, "name": "repo" }],
+            "capabilities": {
+                "textDocument": {
+                    "callHierarchy": {},
+                    "references": {},
+                },
+            },
+        }),
+    )?;
+    client.notify("initialized", json!({}))?;
+
+    // Open every file that holds entities
+    let mut file_lines: HashMap<&str, Vec<String>> = HashMap::new();
+    for entity in entities {
+        if file_lines.contains_key(entity.file.as_str()) {
+            continue;
+        }
+        let Ok(text) = std::fs::read_to_string(repo_path.join(&entity.file)) else { continue };
+        client.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": {
+                    "uri": path_to_uri(&repo_path.join(&entity.file)),
+                    "languageId": language_id(&entity.file),
+                    "version": 1,
+                    "text": text,
+                },
+            }),
+        )?;
+        file_lines.insert(&entity.file, text.lines().map(str::to_string).collect());
+    }
+
+    let locator = EntityLocator::new(entities);
+    let mut out = LspRelations::default();
+    let mut seen: HashSet<(String, String)> = HashSet::new();
+    let mut push = |out: &mut LspRelations, from: &Entity, to: &Entity, kind: RelationKind| {
+        if from.id != to.id && seen.insert((from.id.clone(), to.id.clone())) {
+            out.relations.push(Relation {
+                from_id: from.id.clone(),
+                to_id: to.id.clone(),
+                kind,
+            });
+        }
+    };
+
+    let mut failures = 0;
+    for entity in entities {
+        if failures == MAX_CONSECUTIVE_FAILURES {
+            eprintln!("[fode] language server stopped answering; keeping heuristics for the rest");
+            break;
+        }
+        let line_text = file_lines
+            .get(entity.file.as_str())
+            .and_then(|lines| lines.get(entity.line.saturating_sub(1)));
+        let Some(position) = line_text.and_then(|t| name_position(entity, t)) else { continue };
+        let text_document = json!({ "uri": path_to_uri(&repo_path.join(&entity.file)) });
+        let mut request = |method: &str, params: Value| match client.request(method, params) {
+            Ok(result) => {
+                failures = 0;
+                Some(result)
+            }
+            Err(e) => {
+                eprintln!("[fode] skipping {}: {}", entity.id, e);
+                failures += 1;
+                None
+            }
+        };
+
+        if matches!(entity.kind, EntityKind::Function | EntityKind::Method) {
+            let Some(items) = request(
+                "textDocument/prepareCallHierarchy",
+                json!({ "textDocument": text_document, "position": position }),
+            ) else { continue };
+            let Some(item) = items.as_array().and_then(|a| a.first()).cloned() else { continue };
+            let Some(calls) = request("callHierarchy/outgoingCalls", json!({ "item": item })) else { continue };
+            // An empty answer is what a server still indexing gives too
+            let Some(calls) = calls.as_array().filter(|calls| !calls.is_empty()) else { continue };
+
+            out.resolved_callers.insert(entity.id.clone());
+            for call in calls {
+                let to = &call["to"];
+                let file = to["uri"].as_str().and_then(|u| uri_to_file(u, &root_uri));
+                let line = to["selectionRange"]["start"]["line"].as_u64();
+                let (Some(file), Some(line)) = (file, line) else { continue };
+                let name = to["name"].as_str().unwrap_or("");
+                if let Some(target) = locator.defined_at(&file, line as usize + 1, name) {
+                    push(&mut out, entity, target, RelationKind::Calls);
+                }
+            }
+        } else {
+            let Some(refs) = request(
+                "textDocument/references",
+                json!({
+                    "textDocument": text_document,
+                    "position": position,
+                    "context": { "includeDeclaration": false },
+                }),
+            ) else { continue };
+            let Some(refs) = refs.as_array().filter(|refs| !refs.is_empty()) else { continue };
+
+            out.resolved_targets.insert(entity.id.clone());
+            for location in refs {
+                let file = location["uri"].as_str().and_then(|u| uri_to_file(u, &root_uri));
+                let line = location["range"]["start"]["line"].as_u64();
+                let (Some(file), Some(line)) = (file, line) else { continue };
+                if let Some(source) = locator.enclosing(&file, line as usize + 1) {
+                    push(&mut out, source, entity, use_relation_kind(entity));
+                }
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+/// Replace heuristic edges with server answers for the entities it resolved.
+pub fn merge_into(graph: &mut EntityGraph, answers: LspRelations) {
+    graph.relations.retain(|r| match r.kind {
+        RelationKind::Calls => {
+            !answers.resolved_callers.contains(&r.from_id) && !answers.resolved_targets.contains(&r.to_id)
+        }
+        RelationKind::References => !answers.resolved_targets.contains(&r.to_id),
+        _ => true,
+    });
+    let mut seen: HashSet<(String, String)> = graph
+        .relations
+        .iter()
+        .map(|r| (r.from_id.clone(), r.to_id.clone()))
+        .collect();
+    for r in answers.relations {
+        if seen.insert((r.from_id.clone(), r.to_id.clone())) {
+            graph.relations.push(r);
+        }
+    }
+}
+
+/// Resolve references through a language server and merge them into `graph`.
+pub fn resolve(repo_path: &Path, server: &ServerCommand, graph: &mut EntityGraph) -> Result<(), String> {
+    let root: PathBuf = repo_path.canonicalize().unwrap_or_else(|_| repo_path.to_path_buf());
+    eprintln!("[fode] starting language server: {} {}", server.command, server.args.join(" "));
+    let mut client = LspClient::spawn(&server.command, &server.args, &root, server.timeout)?;
+    let answers = query_relations(&mut client, &root, &graph.entities);
+    client.shutdown();
+    let answers = answers?;
+
+    eprintln!(
+        "[fode] language server resolved {} callers, {} targets, {} relations",
+        answers.resolved_callers.len(),
+        answers.resolved_targets.len(),
+        answers.relations.len()
+    );
+    merge_into(graph, answers);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LspConfig;
+
+    /// Answers like a server that knows `Run` calls `Load` and `Config` is
+    /// used on line 2 of a.go, finds no references to anything else and
+    /// never answers about `helper`. Sends a configuration request first.
+    fn stub_server(reader: std::io::PipeReader, mut writer: std::io::PipeWriter) {
+        let mut reader = BufReader::new(reader);
+        let mut root = String::new();
+        while let Some(msg) = read_message(&mut reader) {
+            let Some(id) = msg.get("id").cloned() else {
+                if msg["method"] == "exit" {
+                    break;
+                }
+                continue;
+            };
+            if msg["params"]["position"]["line"] == 3 {
+                continue; // `helper`
+            }
+            let result = match msg["method"].as_str() {
+                Some("initialize") => {
+                    root = msg["params"]["rootUri"].as_str().unwrap_or("").to_string();
+                    let config_request = json!({
+                        "jsonrpc": "2.0", "id": "cfg", "method": "workspace/configuration",
+                        "params": { "items": [{}] },
+                    });
+                    write_message(&mut writer, &config_request).unwrap();
+                    json!({ "capabilities": {} })
+                }
+                Some("textDocument/prepareCallHierarchy") => {
+                    let params = &msg["params"];
+                    if params["textDocument"]["uri"] == format!("{}/a.go", root)
+                        && params["position"] == json!({ "line": 0, "character": 5 })
+                    {
+                        json!([{ "name": "Run", "uri": format!("{}/a.go", root) }])
+                    } else {
+                        Value::Null
+                    }
+                }
+                Some("callHierarchy/outgoingCalls") => json!([{
+                    "to": {
+                        "name": "Load",
+                        "uri": format!("{}/b.go", root),
+                        "selectionRange": { "start": { "line": 4, "character": 5 } },
+                    },
+                }]),
+                Some("textDocument/references") => {
+                    if msg["params"]["position"] == json!({ "line": 0, "character": 5 }) {
+                        json!([{
+                            "uri": format!("{}/a.go", root),
+                            "range": { "start": { "line": 1, "character": 6 } },
+                        }])
+                    } else {
+                        json!([])
+                    }
+                }
+                _ => Value::Null,
+            };
+            if id == "cfg" {
+                continue; // our configuration request being answered
+            }
+            write_message(&mut writer, &json!({ "jsonrpc": "2.0", "id": id, "result": result })).unwrap();
+        }
+    }
+
+    #[test]
+    fn stub_server_answers_replace_heuristics() {
+        let dir = std::env::temp_dir().join(format!("fode-lsp-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.go"), "func Run() {\n\tc := Config{}\n\tLoad(c)\n}\n").unwrap();
+        std::fs::write(dir.join("b.go"), "type Config struct {\n}\n\nfunc helper() {}\nfunc Load(c Config) {}\ntype Options struct{}\n").unwrap();
+
+        let entities = vec![
+            Entity::stub("a.go", "Run", EntityKind::Function, 1, 4),
+            Entity::stub("b.go", "helper", EntityKind::Function, 4, 4),
+            Entity::stub("b.go", "Config", EntityKind::Struct, 1, 2),
+            Entity::stub("b.go", "Load", EntityKind::Function, 5, 5),
+            Entity::stub("b.go", "Options", EntityKind::Struct, 6, 6),
+        ];
+        let [run, helper, config, load, options] = [0, 1, 2, 3, 4].map(|i| entities[i].id.clone());
+
+        let (client_reader, server_writer) = std::io::pipe().unwrap();
+        let (server_reader, client_writer) = std::io::pipe().unwrap();
+        let server = std::thread::spawn(move || stub_server(server_reader, server_writer));
+
+        let mut client = LspClient::over(client_reader, client_writer, Duration::from_secs(1));
+        let answers = query_relations(&mut client, &dir, &entities).unwrap();
+        client.shutdown();
+        drop(client);
+        server.join().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(answers.resolved_callers.contains(&run));
+        // `helper` timed out; `Config` after it was still asked about
+        assert!(!answers.resolved_callers.contains(&helper));
+        assert!(answers.resolved_targets.contains(&config));
+        // No references found for `Options` may just mean the server is still indexing
+        assert!(!answers.resolved_targets.contains(&options));
+
+        // Heuristics guessed a wrong callee; the server's answer wins
+        let mut graph = EntityGraph {
+            entities,
+            relations: vec![
+                Relation { from_id: run.clone(), to_id: helper.clone(), kind: RelationKind::Calls },
+                Relation { from_id: load.clone(), to_id: options.clone(), kind: RelationKind::References },
+            ],
+            external_deps: HashMap::new(),
+        };
+        merge_into(&mut graph, answers);
+
+        let edges: HashSet<(&str, &str, RelationKind)> = graph
+            .relations
+            .iter()
+            .map(|r| (r.from_id.as_str(), r.to_id.as_str(), r.kind.clone()))
+            .collect();
+        assert_eq!(
+            edges,
+            HashSet::from([
+                (run.as_str(), load.as_str(), RelationKind::Calls),
+                (run.as_str(), config.as_str(), RelationKind::References),
+                (load.as_str(), options.as_str(), RelationKind::References),
+            ])
+        );
+    }
+
+    #[test]
+    fn only_the_user_starts_a_server() {
+        let go = DetectedLanguage::Go;
+        let repo = RepoLspConfig { timeout_secs: Some(5) };
+        assert_eq!(ServerCommand::choose(&go, None, &UserConfig::default(), &repo), None);
+
+        let user = |config: LspConfig| UserConfig { lsp: HashMap::from([("go".to_string(), config)]) };
+        let default = ServerCommand::choose(&go, None, &user(LspConfig::default()), &repo).unwrap();
+        assert_eq!((default.command.as_str(), default.timeout), ("gopls", Duration::from_secs(5)));
+
+        let configured = user(LspConfig {
+            command: Some("/opt/gopls".to_string()),
+            args: vec!["serve".to_string()],
+            timeout_secs: Some(60),
+        });
+        let custom = ServerCommand::choose(&go, None, &configured, &RepoLspConfig::default()).unwrap();
+        assert_eq!(custom.command, "/opt/gopls");
+        assert_eq!(custom.args, vec!["serve"]);
+        assert_eq!(custom.timeout, Duration::from_secs(60));
+        assert_eq!(ServerCommand::choose(&DetectedLanguage::Rust, None, &configured, &repo), None);
+
+        let explicit = ServerCommand::choose(&go, Some("gopls -rpc.trace"), &UserConfig::default(), &repo).unwrap();
+        assert_eq!((explicit.command.as_str(), explicit.args.as_slice()), ("gopls", &["-rpc.trace".to_string()][..]));
+    }
+}
@@ -2,5 +2,11 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    // `fode <subcommand>` runs a command-line report instead of the app;
+    // only a bare `fode` opens the window
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = fode_lib::cli::run(&args) {
+        std::process::exit(code);
+    }
     fode_lib::run();
 }
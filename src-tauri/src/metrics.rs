@@ -0,0 +1,357 @@
+//! Code metrics: size and complexity of each entity, measured on its syntax
+//! tree while parsing, plus fan-in/fan-out and package coupling from the
+//! relations.
+
+use crate::deps;
+use crate::parser::{file_dir, Entity, EntityGraph, EntityKind, RelationKind};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tree_sitter::Node;
+
+/// Measured on an entity's syntax tree; zero for entities parsed without one.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SyntaxMetrics {
+    /// Lines holding code, not counting blank and comment-only lines.
+    pub loc: usize,
+    /// McCabe: one plus each branch, loop, case and short-circuit operator.
+    pub complexity: usize,
+    /// Deepest nesting of control-flow blocks; `else if` does not nest.
+    pub nesting: usize,
+    /// Declared parameters, not counting `self`.
+    pub params: usize,
+}
+
+/// Node kinds adding a path through the code, across the supported grammars.
+const DECISION_KINDS: &[&str] = &[
+    "if_statement",
+    "if_expression",
+    "elif_clause",
+    "for_statement",
+    "for_expression",
+    "for_in_statement",
+    "while_statement",
+    "while_expression",
+    "do_statement",
+    "expression_case",
+    "type_case",
+    "communication_case",
+    "case_clause",
+    "switch_case",
+    "catch_clause",
+    "except_clause",
+    "conditional_expression",
+    "ternary_expression",
+    "if_clause",
+    "boolean_operator",
+];
+
+/// Node kinds opening a nested control-flow block.
+const NESTING_KINDS: &[&str] = &[
+    "if_statement",
+    "if_expression",
+    "for_statement",
+    "for_expression",
+    "for_in_statement",
+    "while_statement",
+    "while_expression",
+    "loop_expression",
+    "do_statement",
+    "match_expression",
+    "match_statement",
+    "expression_switch_statement",
+    "type_switch_statement",
+    "select_statement",
+    "switch_statement",
+    "try_statement",
+    "with_statement",
+];
+
+fn is_comment(node: &Node) -> bool {
+    node.kind().contains("comment")
+}
+
+/// Measure the syntax tree of one entity.
+pub fn measure(node: Node, bytes: &[u8]) -> SyntaxMetrics {
+    let mut metrics = SyntaxMetrics { complexity: 1, params: parameter_count(node, bytes), ..Default::default() };
+    let mut rows = HashSet::new();
+    walk(node, bytes, 0, &mut metrics, &mut rows);
+    metrics.loc = rows.len();
+    metrics
+}
+
+fn walk(node: Node, bytes: &[u8], depth: usize, metrics: &mut SyntaxMetrics, rows: &mut HashSet<usize>) {
+    if is_comment(&node) {
+        return;
+    }
+    if node.child_count() == 0 {
+        rows.extend(node.start_position().row..=node.end_position().row);
+        return;
+    }
+
+    let kind = node.kind();
+    if DECISION_KINDS.contains(&kind) {
+        metrics.complexity += 1;
+    }
+    // A Rust match adds a path per arm beyond the first
+    if kind == "match_block" {
+        let mut cursor = node.walk();
+        let arms = node.named_children(&mut cursor).filter(|c| c.kind() == "match_arm").count();
+        metrics.complexity += arms.saturating_sub(1);
+    }
+    if kind == "binary_expression" {
+        let operator = node.child_by_field_name("operator").and_then(|o| o.utf8_text(bytes).ok());
+        if matches!(operator, Some("&&" | "||" | "??")) {
+            metrics.complexity += 1;
+        }
+    }
+
+    let else_if = matches!(kind, "if_statement" | "if_expression")
+        && node
+            .parent()
+            .is_some_and(|p| matches!(p.kind(), "if_statement" | "if_expression" | "else_clause"));
+    let depth = if NESTING_KINDS.contains(&kind) && !else_if { depth + 1 } else { depth };
+    metrics.nesting = metrics.nesting.max(depth);
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        walk(child, bytes, depth, metrics, rows);
+    }
+}
+
+/// The first node `f` accepts in a definition, looking through wrappers
+/// such as decorators, `export` and `const f = (a, b) => ...` bindings.
+pub(crate) fn find_in_definition<'t, T>(node: Node<'t>, f: impl Fn(Node<'t>) -> Option<T>) -> Option<T> {
+    let mut current = node;
+    for _ in 0..4 {
+        if let Some(found) = f(current) {
+            return Some(found);
+        }
+        current = ["definition", "declaration", "value"]
+            .iter()
+            .find_map(|field| current.child_by_field_name(field))
+            .or_else(|| {
+                matches!(current.kind(), "lexical_declaration" | "variable_declaration")
+                    .then(|| current.named_child(0))
+                    .flatten()
+            })?;
+    }
+    None
+}
+
+/// Parameters of a function; see `find_in_definition`.
+fn parameter_count(node: Node, bytes: &[u8]) -> usize {
+    find_in_definition(node, |current| {
+        if let Some(params) = current.child_by_field_name("parameters") {
+            let mut cursor = params.walk();
+            let count = params
+                .named_children(&mut cursor)
+                .filter(|p| !is_comment(p))
+                .enumerate()
+                .map(|(i, p)| match p.kind() {
+                    "self_parameter" | "positional_separator" | "keyword_separator" => 0,
+                    // Go `a, b int`
+                    "parameter_declaration" => {
+                        let mut names = p.walk();
+                        p.children_by_field_name("name", &mut names).count().max(1)
+                    }
+                    "identifier" if i == 0 && matches!(p.utf8_text(bytes), Ok("self" | "cls")) => 0,
+                    _ => 1,
+                })
+                .sum();
+            return Some(count);
+        }
+        // JavaScript `x => ...`
+        current.child_by_field_name("parameter").map(|_| 1)
+    })
+    .unwrap_or(0)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityMetrics {
+    pub id: String,
+    pub name: String,
+    pub kind: EntityKind,
+    pub package: String,
+    pub file: String,
+    pub line: usize,
+    #[serde(flatten)]
+    pub syntax: SyntaxMetrics,
+    /// Distinct entities calling or referencing this one.
+    pub fan_in: usize,
+    /// Distinct entities this one calls or references.
+    pub fan_out: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageMetrics {
+    /// Package directory.
+    pub package: String,
+    pub entities: usize,
+    pub loc: usize,
+    pub max_complexity: usize,
+    /// Afferent coupling: packages depending on this one.
+    pub afferent: usize,
+    /// Efferent coupling: packages this one depends on.
+    pub efferent: usize,
+    /// efferent / (afferent + efferent): 0 is maximally stable, 1 unstable.
+    pub instability: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricsReport {
+    pub entities: Vec<EntityMetrics>,
+    pub packages: Vec<PackageMetrics>,
+}
+
+/// Per-entity metrics for `entities`, and package aggregates over the whole
+/// graph. Package coupling follows `deps::package_graph`, so test code is
+/// not counted there.
+pub fn metrics<'a>(graph: &'a EntityGraph, entities: impl IntoIterator<Item = &'a Entity>) -> MetricsReport {
+    let mut incoming: HashMap<&str, HashSet<&str>> = HashMap::new();
+    let mut outgoing: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for r in &graph.relations {
+        if matches!(r.kind, RelationKind::Calls | RelationKind::References) && r.from_id != r.to_id {
+            incoming.entry(r.to_id.as_str()).or_default().insert(r.from_id.as_str());
+            outgoing.entry(r.from_id.as_str()).or_default().insert(r.to_id.as_str());
+        }
+    }
+    let count = |map: &HashMap<&str, HashSet<&str>>, id: &str| map.get(id).map_or(0, HashSet::len);
+
+    let entities = entities
+        .into_iter()
+        .map(|e| EntityMetrics {
+            id: e.id.clone(),
+            name: e.name.clone(),
+            kind: e.kind.clone(),
+            package: e.package.clone(),
+            file: e.file.clone(),
+            line: e.line,
+            syntax: e.metrics,
+            fan_in: count(&incoming, &e.id),
+            fan_out: count(&outgoing, &e.id),
+        })
+        .collect();
+
+    let (packages, dependencies) = deps::package_graph(graph);
+    let mut package_metrics: BTreeMap<String, PackageMetrics> = packages
+        .into_iter()
+        .map(|dir| {
+            let metrics = PackageMetrics {
+                package: dir.clone(),
+                entities: 0,
+                loc: 0,
+                max_complexity: 0,
+                afferent: 0,
+                efferent: 0,
+                instability: 0.0,
+            };
+            (dir, metrics)
+        })
+        .collect();
+    for e in graph.entities.iter().filter(|e| !e.is_test_code()) {
+        if let Some(p) = package_metrics.get_mut(&file_dir(&e.file)) {
+            p.entities += 1;
+            p.max_complexity = p.max_complexity.max(e.metrics.complexity);
+            // Members are counted within their owner
+            if e.owner.is_none() {
+                p.loc += e.metrics.loc;
+            }
+        }
+    }
+    for d in &dependencies {
+        if let Some(p) = package_metrics.get_mut(&d.from) {
+            p.efferent += 1;
+        }
+        if let Some(p) = package_metrics.get_mut(&d.to) {
+            p.afferent += 1;
+        }
+    }
+    for p in package_metrics.values_mut() {
+        if p.afferent + p.efferent > 0 {
+            p.instability = p.efferent as f64 / (p.afferent + p.efferent) as f64;
+        }
+    }
+
+    MetricsReport { entities, packages: package_metrics.into_values().collect() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{self, DetectedLanguage};
+
+    fn measure_source(lang: DetectedLanguage, source: &str) -> SyntaxMetrics {
+        let tree = parser::parse_file(source, lang.tree_sitter_language()).unwrap();
+        let root = tree.root_node();
+        let node = root.named_child(root.named_child_count() - 1).unwrap();
+        measure(node, source.as_bytes())
+    }
+
+    #[test]
+    fn measures_go_functions() {
+        let source = "package p
+
+// Classify sorts n into a bucket.
+func Classify(n, limit int, verbose bool) string {
+	if n < 0 || n > limit {
+		return \"out\"
+	} else if n == 0 {
+		return \"zero\"
+	}
+
+	for i := 0; i < n; i++ {
+		switch {
+		case i%2 == 0:
+			if verbose {
+				println(i)
+			}
+		case i%3 == 0:
+		default:
+		}
+	}
+	return \"ok\"
+}
+";
+        let m = measure_source(DetectedLanguage::Go, source);
+        // if, ||, else if, for, two cases, nested if
+        assert_eq!(m.complexity, 8);
+        // for > switch > if
+        assert_eq!(m.nesting, 3);
+        assert_eq!(m.params, 3);
+        assert_eq!(m.loc, 18);
+    }
+
+    #[test]
+    fn measures_rust_and_python_functions() {
+        let rust = "impl S {
+    fn pick(&self, v: Option<u8>) -> u8 {
+        match v {
+            Some(0) => 1,
+            Some(n) if n > 9 => n,
+            _ => 0,
+        }
+    }
+}
+";
+        let tree = parser::parse_file(rust, DetectedLanguage::Rust.tree_sitter_language()).unwrap();
+        let mut cursor = tree.walk();
+        let method = loop {
+            let node = cursor.node();
+            if node.kind() == "function_item" {
+                break node;
+            }
+            assert!(cursor.goto_first_child() || cursor.goto_next_sibling());
+        };
+        let m = measure(method, rust.as_bytes());
+        assert_eq!((m.complexity, m.nesting, m.params), (3, 1, 1));
+
+        let python = "@cached
+def load(self, path, *rest, strict=False):
+    \"\"\"Read it.\"\"\"
+    with open(path) as f:
+        return [line for line in f if line and not strict]
+";
+        let m = measure_source(DetectedLanguage::Python, python);
+        assert_eq!((m.complexity, m.nesting, m.params, m.loc), (3, 1, 3, 5));
+    }
+}
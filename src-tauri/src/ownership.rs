@@ -0,0 +1,342 @@
+//! Ownership: who to ask about an entity or package. Combines the owners a
+//! CODEOWNERS file assigns with `git blame` over the code's current lines.
+
+use crate::git::{self, BlameLine};
+use crate::parser::{file_dir, Entity, EntityGraph, EntityKind};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Where GitHub and GitLab look for a CODEOWNERS file, in order.
+const CODEOWNERS_PATHS: &[&str] = &["CODEOWNERS", ".github/CODEOWNERS", ".gitlab/CODEOWNERS", "docs/CODEOWNERS"];
+
+/// Blame of each file, `None` for lines not committed yet.
+pub type FileBlame = HashMap<String, Vec<Option<BlameLine>>>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct OwnerRule {
+    pattern: String,
+    owners: Vec<String>,
+}
+
+/// Rules of a CODEOWNERS file; the last rule matching a path wins.
+#[derive(Debug, Clone, Default)]
+pub struct CodeOwners {
+    rules: Vec<OwnerRule>,
+}
+
+impl CodeOwners {
+    pub fn parse(text: &str) -> Self {
+        let rules = text
+            .lines()
+            .map(str::trim)
+            // GitLab `[Section]` headers carry no pattern
+            .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('['))
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace().take_while(|f| !f.starts_with('#'));
+                let pattern = fields.next()?.to_string();
+                Some(OwnerRule { pattern, owners: fields.map(str::to_string).collect() })
+            })
+            .collect();
+        CodeOwners { rules }
+    }
+
+    /// The repo's CODEOWNERS at `rev` (the working tree when `None`); empty
+    /// when there is none.
+    pub fn load(repo: &Path, rev: Option<&str>) -> Self {
+        CODEOWNERS_PATHS
+            .iter()
+            .find_map(|path| match rev {
+                Some(rev) => git::run(repo, &["show", &format!("{}:{}", rev, path)]).ok(),
+                None => std::fs::read_to_string(repo.join(path)).ok(),
+            })
+            .map(|text| CodeOwners::parse(&text))
+            .unwrap_or_default()
+    }
+
+    /// Owners of the repo-relative file `path`. A matching rule without
+    /// owners leaves the path unowned.
+    pub fn owners_of(&self, path: &str) -> &[String] {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| pattern_matches(&rule.pattern, path))
+            .map_or(&[], |rule| rule.owners.as_slice())
+    }
+}
+
+/// gitignore-style matching as CODEOWNERS uses it: a pattern with a leading
+/// or inner `/` is anchored at the root, otherwise it matches at any depth; a
+/// pattern also matches everything under a directory it matches, and one
+/// ending in `/` matches only directories.
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let dir_only = pattern.ends_with('/');
+    let pattern = pattern.trim_end_matches('/');
+    let anchored = pattern.starts_with('/') || pattern.contains('/');
+    let pattern = pattern.trim_start_matches('/');
+    if pattern.is_empty() {
+        return false;
+    }
+
+    // Parent directories, then the file itself
+    let mut candidates: Vec<&str> = path.match_indices('/').map(|(i, _)| &path[..i]).collect();
+    if !dir_only {
+        candidates.push(path);
+    }
+    candidates.into_iter().any(|candidate| {
+        if anchored {
+            return glob(pattern.as_bytes(), candidate.as_bytes());
+        }
+        let mut starts = std::iter::once(0).chain(candidate.match_indices('/').map(|(i, _)| i + 1));
+        starts.any(|i| glob(pattern.as_bytes(), &candidate.as_bytes()[i..]))
+    })
+}
+
+/// `*` and `?` stay within one path segment, `**` crosses segments.
+fn glob(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', b'/', rest @ ..] => (0..=text.len()).any(|i| (i == 0 || text[i - 1] == b'/') && glob(rest, &text[i..])),
+        [b'*', b'*', rest @ ..] => (0..=text.len()).any(|i| glob(rest, &text[i..])),
+        [b'*', rest @ ..] => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != b'/')
+            .any(|i| glob(rest, &text[i..])),
+        [b'?', rest @ ..] => text.first().is_some_and(|&c| c != b'/') && glob(rest, &text[1..]),
+        [c, rest @ ..] => text.first() == Some(c) && glob(rest, &text[1..]),
+    }
+}
+
+/// Lines one author last touched.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorShare {
+    pub author: String,
+    pub email: String,
+    pub lines: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Ownership {
+    /// Owners from CODEOWNERS.
+    pub code_owners: Vec<String>,
+    /// By blamed lines, most first.
+    pub authors: Vec<AuthorShare>,
+    /// Fewest authors who together wrote more than half of the committed
+    /// lines; 0 when nothing is committed.
+    pub bus_factor: usize,
+    /// The CODEOWNERS owners when there are any, else the authors behind the
+    /// bus factor.
+    pub primary_owners: Vec<String>,
+    pub uncommitted_lines: usize,
+}
+
+impl Ownership {
+    fn new<'a>(code_owners: Vec<String>, lines: impl IntoIterator<Item = &'a Option<BlameLine>>) -> Self {
+        let mut authors: Vec<AuthorShare> = Vec::new();
+        let mut index: HashMap<&str, usize> = HashMap::new();
+        let mut uncommitted_lines = 0;
+        for line in lines {
+            let Some(line) = line else {
+                uncommitted_lines += 1;
+                continue;
+            };
+            let i = *index.entry(line.email.as_str()).or_insert_with(|| {
+                authors.push(AuthorShare { author: line.author.clone(), email: line.email.clone(), lines: 0 });
+                authors.len() - 1
+            });
+            authors[i].lines += 1;
+        }
+        authors.sort_by(|a, b| b.lines.cmp(&a.lines).then_with(|| a.author.cmp(&b.author)));
+
+        let committed: usize = authors.iter().map(|a| a.lines).sum();
+        let mut covered = 0;
+        let bus_factor = authors
+            .iter()
+            .take_while(|a| {
+                let below_half = covered * 2 <= committed;
+                covered += a.lines;
+                below_half
+            })
+            .count();
+        let primary_owners = if code_owners.is_empty() {
+            authors[..bus_factor].iter().map(|a| a.author.clone()).collect()
+        } else {
+            code_owners.clone()
+        };
+        Ownership { code_owners, authors, bus_factor, primary_owners, uncommitted_lines }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityOwnership {
+    pub id: String,
+    pub name: String,
+    pub kind: EntityKind,
+    pub file: String,
+    pub line: usize,
+    #[serde(flatten)]
+    pub ownership: Ownership,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageOwnership {
+    /// Package directory.
+    pub package: String,
+    pub files: usize,
+    #[serde(flatten)]
+    pub ownership: Ownership,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OwnershipReport {
+    pub has_codeowners: bool,
+    pub packages: Vec<PackageOwnership>,
+    pub entities: Vec<EntityOwnership>,
+}
+
+/// Blame every file in `files` at `rev`. Files git cannot blame, such as
+/// untracked ones, are left out and count as having no authors.
+pub fn blame_files<'a>(repo: &Path, rev: Option<&str>, files: impl IntoIterator<Item = &'a str>) -> FileBlame {
+    let mut blame = FileBlame::new();
+    for file in files {
+        if !blame.contains_key(file) {
+            if let Ok(lines) = git::blame_lines(repo, rev, file, None) {
+                blame.insert(file.to_string(), lines);
+            }
+        }
+    }
+    blame
+}
+
+/// Ownership of `e`'s line range; `blame` must cover its file.
+pub fn entity_ownership(e: &Entity, blame: &FileBlame, code_owners: &CodeOwners) -> EntityOwnership {
+    let lines = blame.get(&e.file).map_or(&[][..], |lines| {
+        let end = e.end_line.max(e.line).min(lines.len());
+        &lines[(e.line.max(1) - 1).min(end)..end]
+    });
+    EntityOwnership {
+        id: e.id.clone(),
+        name: e.name.clone(),
+        kind: e.kind.clone(),
+        file: e.file.clone(),
+        line: e.line,
+        ownership: Ownership::new(code_owners.owners_of(&e.file).to_vec(), lines),
+    }
+}
+
+/// Ownership of the package directory `package` over whole files. Its code
+/// owners are those of any of its files, the most files first.
+pub fn package_ownership(package: &str, files: &[&str], blame: &FileBlame, code_owners: &CodeOwners) -> PackageOwnership {
+    let mut owner_files: Vec<(&String, usize)> = Vec::new();
+    for file in files {
+        for owner in code_owners.owners_of(file) {
+            match owner_files.iter_mut().find(|(o, _)| *o == owner) {
+                Some((_, n)) => *n += 1,
+                None => owner_files.push((owner, 1)),
+            }
+        }
+    }
+    owner_files.sort_by_key(|&(_, n)| std::cmp::Reverse(n));
+    let owners = owner_files.into_iter().map(|(o, _)| o.clone()).collect();
+
+    let lines = files.iter().filter_map(|f| blame.get(*f)).flatten();
+    PackageOwnership { package: package.to_string(), files: files.len(), ownership: Ownership::new(owners, lines) }
+}
+
+/// Source files of the package directory `package`, sorted.
+pub fn package_files<'a>(graph: &'a EntityGraph, package: &str) -> Vec<&'a str> {
+    let mut files: Vec<&str> = graph
+        .entities
+        .iter()
+        .filter(|e| file_dir(&e.file) == package)
+        .map(|e| e.file.as_str())
+        .collect();
+    files.sort_unstable();
+    files.dedup();
+    files
+}
+
+/// Ownership of every package and of every entity other than imports and
+/// package clauses; `blame` should cover every file of the graph.
+pub fn ownership(graph: &EntityGraph, blame: &FileBlame, code_owners: &CodeOwners) -> OwnershipReport {
+    let mut by_package: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for e in &graph.entities {
+        by_package.entry(file_dir(&e.file)).or_default().push(&e.file);
+    }
+    let packages = by_package
+        .into_iter()
+        .map(|(package, mut files)| {
+            files.sort_unstable();
+            files.dedup();
+            package_ownership(&package, &files, blame, code_owners)
+        })
+        .collect();
+    let entities = graph
+        .entities
+        .iter()
+        .filter(|e| !matches!(e.kind, EntityKind::Import | EntityKind::Package))
+        .map(|e| entity_ownership(e, blame, code_owners))
+        .collect();
+    OwnershipReport { has_codeowners: !code_owners.rules.is_empty(), packages, entities }
+}
+
+/// Plain-text table of package ownership; entities are in the JSON only.
+pub fn render_report(report: &OwnershipReport) -> String {
+    let mut out = format!(
+        "{} packages{}\n\n  bus factor  files  package  primary owners\n",
+        report.packages.len(),
+        if report.has_codeowners { "" } else { ", no CODEOWNERS" }
+    );
+    for p in &report.packages {
+        let owners = &p.ownership.primary_owners;
+        let owners = if owners.is_empty() { "-".to_string() } else { owners.join(", ") };
+        out.push_str(&format!("  {:>10}  {:>5}  {}  {}\n", p.ownership.bus_factor, p.files, p.package, owners));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codeowners_last_matching_rule_wins() {
+        let owners = CodeOwners::parse(
+            "# Default owners
+*                   @core
+*.go                @gophers # inline comment
+/api/               @api-team @core
+docs/**/*.md        @writers
+internal/gen
+[Storage]
+storage/            @storage
+",
+        );
+        let of = |path| owners.owners_of(path).join(" ");
+        assert_eq!(of("README.md"), "@core");
+        assert_eq!(of("cmd/main.go"), "@gophers");
+        assert_eq!(of("api/v1/handler.go"), "@api-team @core");
+        assert_eq!(of("pkg/api/handler.go"), "@gophers");
+        assert_eq!(of("docs/guide/setup/intro.md"), "@writers");
+        assert_eq!(of("internal/gen/types.go"), "");
+        assert_eq!(of("lib/storage/disk.go"), "@storage");
+        // `storage/` only matches directories
+        assert!(!pattern_matches("storage/", "storage"));
+    }
+
+    #[test]
+    fn bus_factor_counts_authors_behind_most_lines() {
+        let line = |who: &str| Some(BlameLine { author: who.to_string(), email: format!("{}@x", who), time: 0 });
+        let lines = [line("ann"), line("bo"), line("ann"), line("cy"), None, line("bo"), line("ann")];
+
+        let o = Ownership::new(Vec::new(), &lines);
+        let shares: Vec<(&str, usize)> = o.authors.iter().map(|a| (a.author.as_str(), a.lines)).collect();
+        assert_eq!(shares, vec![("ann", 3), ("bo", 2), ("cy", 1)]);
+        // ann alone wrote exactly half of the six committed lines
+        assert_eq!((o.bus_factor, o.uncommitted_lines), (2, 1));
+        assert_eq!(o.primary_owners, vec!["ann", "bo"]);
+
+        let o = Ownership::new(vec!["@team".to_string()], &lines[..3]);
+        assert_eq!((o.bus_factor, o.primary_owners), (1, vec!["@team".to_string()]));
+        assert_eq!(Ownership::new(Vec::new(), &[None]).bus_factor, 0);
+    }
+}
@@ -1,5 +1,9 @@
+use crate::code_index::CodeIndex;
+use crate::duplicates;
+use crate::metrics::{self, SyntaxMetrics};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 use tree_sitter::{Language, Parser, Tree};
 use walkdir::WalkDir;
@@ -19,6 +23,7 @@ pub enum EntityKind {
     Enum,
     Trait,
     Module,
+    Field,
 }
 
 impl EntityKind {
@@ -37,8 +42,22 @@ impl EntityKind {
             EntityKind::Enum => "enum",
             EntityKind::Trait => "trait",
             EntityKind::Module => "module",
+            EntityKind::Field => "field",
         }
     }
+
+    /// Whether this kind declares a type that can own fields, methods or constants.
+    pub fn is_type(&self) -> bool {
+        matches!(
+            self,
+            EntityKind::Struct
+                | EntityKind::Interface
+                | EntityKind::TypeAlias
+                | EntityKind::Class
+                | EntityKind::Enum
+                | EntityKind::Trait
+        )
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -53,6 +72,200 @@ pub struct Entity {
     pub signature: String,
     pub package: String,
     pub doc_comment: String,
+    /// Name of the owning type for fields, methods and typed constants.
+    /// Resolved into a `Contains` relation within the same package.
+    pub owner: Option<String>,
+    /// For Go methods: whether the receiver is a pointer or a value.
+    pub receiver_kind: Option<ReceiverKind>,
+    /// Python decorators without the `@`, e.g. `property`, `app.route("/")`.
+    #[serde(default)]
+    pub decorators: Vec<String>,
+    #[serde(default)]
+    pub visibility: Visibility,
+    /// Run by the language's test runner: Go `TestXxx` in `_test.go`, Rust
+    /// `#[test]`, pytest `test_*`.
+    #[serde(default)]
+    pub is_test: bool,
+    /// Size and complexity, filled in by `parse_tree`.
+    #[serde(default)]
+    pub metrics: SyntaxMetrics,
+    /// Fingerprint of a function or method body for finding duplicates,
+    /// filled in by `parse_tree`; see `duplicates::shape`.
+    #[serde(default, skip_serializing)]
+    pub shape: Vec<u64>,
+    /// Names in `source` resolved to other entities, filled in by
+    /// `extract_references`.
+    #[serde(default, skip_serializing)]
+    pub references: Vec<SourceReference>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ReceiverKind {
+    Pointer,
+    Value,
+}
+
+/// How far an entity is visible outside its declaring module.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum Visibility {
+    /// Go capitalized, Rust `pub`, Python public name, JS `export`.
+    #[default]
+    Public,
+    /// Rust `pub(crate)`, `pub(super)`, `pub(in path)`.
+    Internal,
+    /// Go lowercase, Rust without `pub`, Python `_name` or not in `__all__`,
+    /// JS not exported.
+    Private,
+}
+
+impl Visibility {
+    pub fn label(&self) -> &str {
+        match self {
+            Visibility::Public => "public",
+            Visibility::Internal => "internal",
+            Visibility::Private => "private",
+        }
+    }
+}
+
+/// Go: exported when the (member) name starts with an uppercase letter.
+fn go_visibility(name: &str) -> Visibility {
+    let member = name.rsplit('.').next().unwrap_or(name);
+    if member.chars().next().is_some_and(char::is_uppercase) {
+        Visibility::Public
+    } else {
+        Visibility::Private
+    }
+}
+
+/// Rust: from the item's `pub` modifier. Items of trait impls and trait
+/// definitions carry the trait's visibility, taken as public.
+fn rust_visibility(node: &tree_sitter::Node, bytes: &[u8]) -> Visibility {
+    let mut cursor = node.walk();
+    let modifier = node
+        .children(&mut cursor)
+        .find(|c| c.kind() == "visibility_modifier")
+        .map(|m| node_text(bytes, &m).replace(' ', ""));
+    match modifier.as_deref() {
+        Some("pub") => Visibility::Public,
+        Some(_) => Visibility::Internal,
+        None => {
+            let container = node.parent().and_then(|list| list.parent());
+            let in_trait = container.is_some_and(|c| {
+                c.kind() == "trait_item"
+                    || (c.kind() == "impl_item" && c.child_by_field_name("trait").is_some())
+            });
+            if in_trait { Visibility::Public } else { Visibility::Private }
+        }
+    }
+}
+
+/// Python: `_name` is private, `__dunder__` is public.
+fn python_visibility(name: &str) -> Visibility {
+    let member = name.rsplit('.').next().unwrap_or(name);
+    let dunder = member.len() > 4 && member.starts_with("__") && member.ends_with("__");
+    if member.starts_with('_') && !dunder {
+        Visibility::Private
+    } else {
+        Visibility::Public
+    }
+}
+
+impl Entity {
+    /// Tests plus everything else in test files, such as helpers and fixtures.
+    pub fn is_test_code(&self) -> bool {
+        self.is_test || is_test_file(&self.file)
+    }
+}
+
+#[cfg(test)]
+impl Entity {
+    /// A public entity with no source, for tests that build graphs by hand;
+    /// set anything else with struct update syntax.
+    pub(crate) fn stub(file: &str, name: &str, kind: EntityKind, line: usize, end_line: usize) -> Entity {
+        Entity {
+            id: make_entity_id(file, name, &kind),
+            name: name.to_string(),
+            kind,
+            file: file.to_string(),
+            line,
+            end_line,
+            source: String::new(),
+            signature: String::new(),
+            package: String::new(),
+            doc_comment: String::new(),
+            owner: None,
+            receiver_kind: None,
+            decorators: Vec::new(),
+            visibility: Visibility::Public,
+            is_test: false,
+            metrics: Default::default(),
+            shape: Vec::new(),
+            references: Vec::new(),
+        }
+    }
+}
+
+/// Test files by naming convention: Go `_test.go`, pytest `test_*.py` and
+/// `*_test.py`, JS `*.test.*`/`*.spec.*`, and anything under a `tests` dir.
+pub fn is_test_file(file: &str) -> bool {
+    let name = file.rsplit('/').next().unwrap_or(file);
+    file.ends_with("_test.go")
+        || (name.starts_with("test_") && name.ends_with(".py"))
+        || name.ends_with("_test.py")
+        || name.contains(".test.")
+        || name.contains(".spec.")
+        || file.split('/').any(|c| c == "tests" || c == "__tests__")
+}
+
+/// `go test` entry points: `TestXxx`, `BenchmarkXxx`, `FuzzXxx` and
+/// `ExampleXxx` functions in `_test.go` files.
+fn go_is_test(entity: &Entity) -> bool {
+    entity.kind == EntityKind::Function
+        && entity.file.ends_with("_test.go")
+        && ["Test", "Benchmark", "Fuzz", "Example"].iter().any(|prefix| {
+            entity
+                .name
+                .strip_prefix(prefix)
+                .is_some_and(|rest| !rest.starts_with(|c: char| c.is_lowercase()))
+        })
+}
+
+/// Whether a Rust function carries a test attribute: `#[test]`,
+/// `#[tokio::test]`, `#[rstest]` and the like.
+fn rust_is_test(node: &tree_sitter::Node, bytes: &[u8]) -> bool {
+    let mut sibling = node.prev_sibling();
+    while let Some(s) = sibling {
+        match s.kind() {
+            "attribute_item" => {
+                let text = node_text(bytes, &s);
+                let path = text.trim_start_matches("#[").split(['(', ']']).next().unwrap_or("").trim();
+                if path == "test" || path.ends_with("::test") || path == "rstest" || path == "test_case" {
+                    return true;
+                }
+            }
+            "line_comment" | "block_comment" => {}
+            _ => break,
+        }
+        sibling = s.prev_sibling();
+    }
+    false
+}
+
+/// pytest collection rules: `test*` functions in `test_*.py`/`*_test.py`
+/// files and `test*` methods of `Test*` classes.
+fn python_is_test(name: &str, owner: Option<&str>, file_path: &str) -> bool {
+    let short = name.rsplit('.').next().unwrap_or(name);
+    if !short.starts_with("test") {
+        return false;
+    }
+    match owner {
+        Some(class) => class.rsplit('.').next().unwrap_or(class).starts_with("Test"),
+        None => {
+            let file = file_path.rsplit('/').next().unwrap_or(file_path);
+            file.starts_with("test_") || file.ends_with("_test.py")
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -65,6 +278,10 @@ pub enum RelationKind {
     ContainedBy,
     Implements,
     ImplementedBy,
+    Extends,
+    ExtendedBy,
+    Embeds,
+    EmbeddedBy,
     Returns,
     Accepts,
 }
@@ -76,6 +293,16 @@ pub struct Relation {
     pub kind: RelationKind,
 }
 
+/// A name in an entity's source that resolves to another entity, e.g. the
+/// callee in a call expression.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SourceReference {
+    /// Byte offsets into the entity's `source`.
+    pub start: usize,
+    pub end: usize,
+    pub target_id: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RepoInfo {
     pub path: String,
@@ -126,10 +353,18 @@ impl DetectedLanguage {
             DetectedLanguage::Go => &["go"],
             DetectedLanguage::Rust => &["rs"],
             DetectedLanguage::Python => &["py"],
-            DetectedLanguage::JavaScript => &["js", "jsx", "ts", "tsx"],
+            DetectedLanguage::JavaScript => &["js", "jsx", "mjs", "cjs", "ts", "tsx"],
         }
     }
 
+    /// The language a file is parsed as, by its extension.
+    pub fn of_file(path: &str) -> Option<DetectedLanguage> {
+        let ext = Path::new(path).extension()?.to_str()?;
+        [DetectedLanguage::Go, DetectedLanguage::Rust, DetectedLanguage::Python, DetectedLanguage::JavaScript]
+            .into_iter()
+            .find(|lang| lang.extensions().contains(&ext))
+    }
+
     pub fn tree_sitter_language(&self) -> Language {
         match self {
             DetectedLanguage::Go => tree_sitter_go::LANGUAGE.into(),
@@ -140,23 +375,62 @@ impl DetectedLanguage {
     }
 }
 
+/// Files of a repository as the parser sees them: the working tree on disk,
+/// or a snapshot such as a git revision read from the object database.
+pub trait SourceTree {
+    /// Repo root on disk; names the repo and anchors repo-relative paths.
+    fn root(&self) -> &Path;
+    /// Repo-relative paths of every file.
+    fn paths(&self) -> Vec<PathBuf>;
+    fn read(&self, rel_path: &Path) -> std::io::Result<String>;
+}
+
+/// The checked-out files under a directory.
+pub struct WorkingTree<'a>(pub &'a Path);
+
+impl SourceTree for WorkingTree<'_> {
+    fn root(&self) -> &Path {
+        self.0
+    }
+
+    fn paths(&self) -> Vec<PathBuf> {
+        WalkDir::new(self.0)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter_map(|e| e.path().strip_prefix(self.0).ok().map(Path::to_path_buf))
+            .collect()
+    }
+
+    fn read(&self, rel_path: &Path) -> std::io::Result<String> {
+        std::fs::read_to_string(self.0.join(rel_path))
+    }
+}
+
 pub fn detect_language(repo_path: &Path) -> Option<DetectedLanguage> {
+    detect_language_in(
+        WalkDir::new(repo_path)
+            .max_depth(5)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .map(|e| e.into_path()),
+    )
+}
+
+/// The most common supported language among `paths`.
+fn detect_language_in<P: AsRef<Path>>(paths: impl IntoIterator<Item = P>) -> Option<DetectedLanguage> {
     let mut go_count = 0;
     let mut rs_count = 0;
     let mut py_count = 0;
     let mut js_count = 0;
 
-    for entry in WalkDir::new(repo_path)
-        .max_depth(5)
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
-        if let Some(ext) = entry.path().extension().and_then(|e| e.to_str()) {
+    for path in paths {
+        if let Some(ext) = path.as_ref().extension().and_then(|e| e.to_str()) {
             match ext {
                 "go" => go_count += 1,
                 "rs" => rs_count += 1,
                 "py" => py_count += 1,
-                "js" | "jsx" | "ts" | "tsx" => js_count += 1,
+                "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" => js_count += 1,
                 _ => {}
             }
         }
@@ -178,15 +452,13 @@ pub fn detect_language(repo_path: &Path) -> Option<DetectedLanguage> {
     }
 }
 
-pub fn collect_source_files(repo_path: &Path, lang: &DetectedLanguage) -> Vec<PathBuf> {
+/// Repo-relative paths of the source files to parse, from `paths`.
+pub fn collect_source_files(paths: &[PathBuf], lang: &DetectedLanguage) -> Vec<PathBuf> {
     let extensions = lang.extensions();
-    WalkDir::new(repo_path)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-        .filter(|e| {
+    paths
+        .iter()
+        .filter(|path| {
             // Skip hidden dirs, vendor, node_modules, target
-            let path = e.path();
             !path.components().any(|c| {
                 let s = c.as_os_str().to_str().unwrap_or("");
                 s.starts_with('.')
@@ -196,14 +468,14 @@ pub fn collect_source_files(repo_path: &Path, lang: &DetectedLanguage) -> Vec<Pa
                     || s == "testdata"
             })
         })
-        .filter(|e| {
-            if let Some(ext) = e.path().extension().and_then(|x| x.to_str()) {
+        .filter(|path| {
+            if let Some(ext) = path.extension().and_then(|x| x.to_str()) {
                 extensions.contains(&ext)
             } else {
                 false
             }
         })
-        .map(|e| e.into_path())
+        .cloned()
         .collect()
 }
 
@@ -223,6 +495,12 @@ fn get_doc_comment(source: &[u8], node: &tree_sitter::Node) -> String {
     let mut sibling = node.prev_sibling();
     while let Some(s) = sibling {
         if s.kind() == "comment" {
+            // A trailing comment on the previous item's line belongs to that item
+            if s.prev_sibling()
+                .is_some_and(|p| p.kind() != "comment" && p.end_position().row == s.start_position().row)
+            {
+                break;
+            }
             let text = std::str::from_utf8(&source[s.byte_range()]).unwrap_or("").to_string();
             comments.push(text);
             sibling = s.prev_sibling();
@@ -234,6 +512,184 @@ fn get_doc_comment(source: &[u8], node: &tree_sitter::Node) -> String {
     comments.join("\n")
 }
 
+/// Python docstring: a string literal as the first statement of a def/class
+/// body, with indentation removed like `inspect.cleandoc`.
+fn python_docstring(node: &tree_sitter::Node, bytes: &[u8]) -> Option<String> {
+    let body = node.child_by_field_name("body")?;
+    let mut cursor = body.walk();
+    let first = body.named_children(&mut cursor).find(|c| c.kind() != "comment")?;
+    if first.kind() != "expression_statement" {
+        return None;
+    }
+    let string = first.named_child(0).filter(|s| s.kind() == "string")?;
+    let mut cursor = string.walk();
+    let raw: String = string
+        .named_children(&mut cursor)
+        .filter(|c| c.kind() == "string_content")
+        .map(|c| node_text(bytes, &c))
+        .collect();
+
+    let mut lines = raw.lines();
+    let first_line = lines.next().unwrap_or("").trim().to_string();
+    let rest: Vec<&str> = lines.collect();
+    let indent = rest
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let mut cleaned = vec![first_line];
+    cleaned.extend(rest.iter().map(|l| l.get(indent..).unwrap_or("").trim_end().to_string()));
+    let doc = cleaned.join("\n").trim_matches('\n').to_string();
+    (!doc.is_empty()).then_some(doc)
+}
+
+/// Whether a Go declaration uses the parenthesized group form, e.g. `type ( ... )`.
+fn is_grouped_decl(decl: &tree_sitter::Node) -> bool {
+    let mut cursor = decl.walk();
+    let grouped = decl
+        .children(&mut cursor)
+        .any(|c| c.kind() == "(" || c.kind() == "var_spec_list");
+    grouped
+}
+
+fn node_text<'a>(bytes: &'a [u8], node: &tree_sitter::Node) -> &'a str {
+    std::str::from_utf8(&bytes[node.byte_range()]).unwrap_or("")
+}
+
+/// Strip pointers, package qualifiers and type arguments from a Go type,
+/// e.g. `*pkg.List[T]` -> `List`.
+fn go_base_type_name(node: tree_sitter::Node, bytes: &[u8]) -> String {
+    match node.kind() {
+        "pointer_type" => node
+            .named_child(0)
+            .map(|inner| go_base_type_name(inner, bytes))
+            .unwrap_or_default(),
+        "generic_type" => node
+            .child_by_field_name("type")
+            .map(|inner| go_base_type_name(inner, bytes))
+            .unwrap_or_default(),
+        "qualified_type" => node
+            .child_by_field_name("name")
+            .map(|n| node_text(bytes, &n).to_string())
+            .unwrap_or_default(),
+        _ => node_text(bytes, &node).to_string(),
+    }
+}
+
+/// Strip references, paths and type arguments from a Rust type,
+/// e.g. `&mut crate::store::Cache<K, V>` -> `Cache`.
+fn rust_base_type_name(node: tree_sitter::Node, bytes: &[u8]) -> String {
+    match node.kind() {
+        "reference_type" | "generic_type" | "generic_type_with_turbofish" => node
+            .child_by_field_name("type")
+            .map(|inner| rust_base_type_name(inner, bytes))
+            .unwrap_or_default(),
+        "scoped_type_identifier" | "scoped_identifier" => node
+            .child_by_field_name("name")
+            .map(|n| node_text(bytes, &n).to_string())
+            .unwrap_or_default(),
+        _ => node_text(bytes, &node).to_string(),
+    }
+}
+
+/// Parse a Go method receiver into (variable name, base type name, is pointer).
+/// The variable name is empty for receivers like `func (*T) M()`.
+fn go_receiver(method: tree_sitter::Node, bytes: &[u8]) -> Option<(String, String, bool)> {
+    let params = method.child_by_field_name("receiver")?;
+    let mut cursor = params.walk();
+    let param = params
+        .named_children(&mut cursor)
+        .find(|c| c.kind() == "parameter_declaration")?;
+    let type_node = param.child_by_field_name("type")?;
+    let var = param
+        .child_by_field_name("name")
+        .map(|n| node_text(bytes, &n).to_string())
+        .unwrap_or_default();
+    let type_name = go_base_type_name(type_node, bytes);
+    if type_name.is_empty() {
+        return None;
+    }
+    Some((var, type_name, type_node.kind() == "pointer_type"))
+}
+
+/// Extract struct fields and interface method specs as child entities named
+/// `Type.member`, owned by `type_name`.
+fn extract_go_type_members(
+    type_node: tree_sitter::Node,
+    bytes: &[u8],
+    file_path: &str,
+    package: &str,
+    type_name: &str,
+    entities: &mut Vec<Entity>,
+) {
+    let (list, member_kind) = match type_node.kind() {
+        "struct_type" => {
+            let mut cursor = type_node.walk();
+            let list = type_node
+                .children(&mut cursor)
+                .find(|c| c.kind() == "field_declaration_list");
+            (list, EntityKind::Field)
+        }
+        "interface_type" => (Some(type_node), EntityKind::Method),
+        _ => return,
+    };
+    let Some(list) = list else { return };
+
+    let mut cursor = list.walk();
+    for member in list.children(&mut cursor) {
+        let names: Vec<String> = match member.kind() {
+            "field_declaration" => {
+                let mut name_cursor = member.walk();
+                let names: Vec<String> = member
+                    .children_by_field_name("name", &mut name_cursor)
+                    .map(|n| node_text(bytes, &n).to_string())
+                    .collect();
+                if names.is_empty() {
+                    // Embedded field: named after its type
+                    member
+                        .child_by_field_name("type")
+                        .map(|t| vec![go_base_type_name(t, bytes)])
+                        .unwrap_or_default()
+                } else {
+                    names
+                }
+            }
+            "method_elem" => member
+                .child_by_field_name("name")
+                .map(|n| vec![node_text(bytes, &n).to_string()])
+                .unwrap_or_default(),
+            _ => continue,
+        };
+
+        let member_source = node_text(bytes, &member);
+        let doc = get_doc_comment(bytes, &member);
+        for member_name in names.into_iter().filter(|n| !n.is_empty()) {
+            let name = format!("{}.{}", type_name, member_name);
+            entities.push(Entity {
+                id: make_entity_id(file_path, &name, &member_kind),
+                name,
+                kind: member_kind.clone(),
+                file: file_path.to_string(),
+                line: member.start_position().row + 1,
+                end_line: member.end_position().row + 1,
+                source: member_source.to_string(),
+                signature: member_source.lines().next().unwrap_or("").trim().to_string(),
+                package: package.to_string(),
+                doc_comment: doc.clone(),
+                owner: Some(type_name.to_string()),
+                receiver_kind: None,
+                decorators: Vec::new(),
+                visibility: Visibility::Private,
+                is_test: false,
+                metrics: Default::default(),
+                shape: Vec::new(),
+                references: Vec::new(),
+            });
+        }
+    }
+}
+
 pub fn extract_entities_go(
     source: &str,
     tree: &Tree,
@@ -268,6 +724,14 @@ pub fn extract_entities_go(
                         signature,
                         package: package.to_string(),
                         doc_comment: doc,
+                        owner: None,
+                        receiver_kind: None,
+                        decorators: Vec::new(),
+                        visibility: Visibility::Private,
+                        is_test: false,
+                        metrics: Default::default(),
+                        shape: Vec::new(),
+                        references: Vec::new(),
                     });
                 }
             }
@@ -281,18 +745,19 @@ pub fn extract_entities_go(
                     let signature = full_source.lines().next().unwrap_or("").to_string();
                     let doc = get_doc_comment(bytes, &child);
 
-                    // Get receiver type
-                    let mut receiver = String::new();
-                    if let Some(params) = child.child_by_field_name("receiver") {
-                        receiver =
-                            std::str::from_utf8(&bytes[params.byte_range()]).unwrap_or("").to_string();
-                    }
-
-                    let display_name = if receiver.is_empty() {
-                        name.clone()
-                    } else {
-                        format!("{}.{}", receiver, name)
+                    // Name methods after their receiver's base type: `(s *Server)` -> `Server`
+                    let receiver = go_receiver(child, bytes);
+                    let display_name = match &receiver {
+                        Some((_, type_name, _)) => format!("{}.{}", type_name, name),
+                        None => name.clone(),
                     };
+                    let receiver_kind = receiver.as_ref().map(|(_, _, is_pointer)| {
+                        if *is_pointer {
+                            ReceiverKind::Pointer
+                        } else {
+                            ReceiverKind::Value
+                        }
+                    });
 
                     entities.push(Entity {
                         id: make_entity_id(file_path, &display_name, &EntityKind::Method),
@@ -305,11 +770,21 @@ pub fn extract_entities_go(
                         signature,
                         package: package.to_string(),
                         doc_comment: doc,
+                        owner: receiver.map(|(_, type_name, _)| type_name),
+                        receiver_kind,
+                        decorators: Vec::new(),
+                        visibility: Visibility::Private,
+                        is_test: false,
+                        metrics: Default::default(),
+                        shape: Vec::new(),
+                        references: Vec::new(),
                     });
                 }
             }
             "type_declaration" => {
-                // Type declarations can contain type_spec children
+                // Type declarations can contain type_spec children. In a grouped
+                // `type ( ... )` block each spec gets its own source and doc.
+                let grouped = is_grouped_decl(&child);
                 let mut spec_cursor = child.walk();
                 for spec in child.children(&mut spec_cursor) {
                     if spec.kind() == "type_spec" {
@@ -317,9 +792,10 @@ pub fn extract_entities_go(
                             let name = std::str::from_utf8(&bytes[name_node.byte_range()])
                                 .unwrap_or("")
                                 .to_string();
+                            let decl = if grouped { spec } else { child };
                             let full_source =
-                                std::str::from_utf8(&bytes[child.byte_range()]).unwrap_or("");
-                            let doc = get_doc_comment(bytes, &child);
+                                std::str::from_utf8(&bytes[decl.byte_range()]).unwrap_or("");
+                            let doc = get_doc_comment(bytes, &decl);
 
                             // Determine if struct or interface
                             let type_node = spec.child_by_field_name("type");
@@ -337,16 +813,30 @@ pub fn extract_entities_go(
 
                             entities.push(Entity {
                                 id: make_entity_id(file_path, &name, &kind),
-                                name,
+                                name: name.clone(),
                                 kind,
                                 file: file_path.to_string(),
-                                line: child.start_position().row + 1,
-                                end_line: child.end_position().row + 1,
+                                line: decl.start_position().row + 1,
+                                end_line: decl.end_position().row + 1,
                                 source: full_source.to_string(),
                                 signature,
                                 package: package.to_string(),
                                 doc_comment: doc,
+                                owner: None,
+                                receiver_kind: None,
+                                decorators: Vec::new(),
+                                visibility: Visibility::Private,
+                                is_test: false,
+                                metrics: Default::default(),
+                                shape: Vec::new(),
+                                references: Vec::new(),
                             });
+
+                            if let Some(tn) = type_node {
+                                extract_go_type_members(
+                                    tn, bytes, file_path, package, &name, &mut entities,
+                                );
+                            }
                         }
                     }
                 }
@@ -359,16 +849,57 @@ pub fn extract_entities_go(
                     EntityKind::Variable
                 };
 
+                // Typed constants in a group (`A Kind = iota; B; C`) belong to their
+                // type. A spec with neither type nor value repeats the previous one.
+                let mut group_type: Option<String> = None;
+
+                // Grouped specs carry their own doc comment; `var ( ... )` nests
+                // them in a var_spec_list.
+                let grouped = is_grouped_decl(&child);
                 let mut spec_cursor = child.walk();
-                for spec in child.children(&mut spec_cursor) {
+                let specs: Vec<tree_sitter::Node> = child
+                    .children(&mut spec_cursor)
+                    .flat_map(|c| {
+                        let mut list_cursor = c.walk();
+                        if c.kind() == "var_spec_list" {
+                            c.children(&mut list_cursor).collect()
+                        } else {
+                            vec![c]
+                        }
+                    })
+                    .collect();
+
+                for spec in specs {
                     if spec.kind() == "const_spec" || spec.kind() == "var_spec" {
-                        if let Some(name_node) = spec.child_by_field_name("name") {
+                        if is_const {
+                            match spec.child_by_field_name("type") {
+                                Some(t) if t.kind() == "type_identifier" => {
+                                    group_type = Some(node_text(bytes, &t).to_string());
+                                }
+                                Some(_) => group_type = None,
+                                None if spec.child_by_field_name("value").is_some() => {
+                                    group_type = None;
+                                }
+                                None => {}
+                            }
+                        }
+
+                        let full_source =
+                            std::str::from_utf8(&bytes[spec.byte_range()]).unwrap_or("");
+                        let doc = if grouped {
+                            get_doc_comment(bytes, &spec)
+                        } else {
+                            get_doc_comment(bytes, &child)
+                        };
+
+                        let mut name_cursor = spec.walk();
+                        let names = spec
+                            .children_by_field_name("name", &mut name_cursor)
+                            .filter(|n| n.is_named());
+                        for name_node in names {
                             let name = std::str::from_utf8(&bytes[name_node.byte_range()])
                                 .unwrap_or("")
                                 .to_string();
-                            let full_source =
-                                std::str::from_utf8(&bytes[spec.byte_range()]).unwrap_or("");
-                            let doc = get_doc_comment(bytes, &child);
 
                             entities.push(Entity {
                                 id: make_entity_id(file_path, &name, &kind),
@@ -380,7 +911,15 @@ pub fn extract_entities_go(
                                 source: full_source.to_string(),
                                 signature: full_source.lines().next().unwrap_or("").to_string(),
                                 package: package.to_string(),
-                                doc_comment: doc,
+                                doc_comment: doc.clone(),
+                                owner: group_type.clone(),
+                                receiver_kind: None,
+                                decorators: Vec::new(),
+                                visibility: Visibility::Private,
+                                is_test: false,
+                                metrics: Default::default(),
+                                shape: Vec::new(),
+                                references: Vec::new(),
                             });
                         }
                     }
@@ -390,6 +929,10 @@ pub fn extract_entities_go(
         }
     }
 
+    for e in &mut entities {
+        e.visibility = go_visibility(&e.name);
+        e.is_test = go_is_test(e);
+    }
     entities
 }
 
@@ -403,16 +946,123 @@ pub fn extract_entities_generic(
     let root = tree.root_node();
     let bytes = source.as_bytes();
 
+    /// Per-file state shared by the recursive walk.
+    struct WalkContext<'a> {
+        bytes: &'a [u8],
+        file_path: &'a str,
+        package: &'a str,
+        lang: &'a DetectedLanguage,
+    }
+
+    /// Push an entity for `node`. `span` is the node plus any wrapper that
+    /// belongs to it (Python decorators, JS `export`).
+    fn push_entity(
+        ctx: &WalkContext,
+        entities: &mut Vec<Entity>,
+        kind: EntityKind,
+        name: String,
+        owner: Option<String>,
+        node: tree_sitter::Node,
+        span: tree_sitter::Node,
+    ) {
+        let bytes = ctx.bytes;
+        let name_for_visibility = name.clone();
+        let decorators: Vec<String> = {
+            let mut cursor = span.walk();
+            span.children(&mut cursor)
+                .filter(|c| c.kind() == "decorator")
+                .map(|d| node_text(bytes, &d).trim_start_matches('@').trim().to_string())
+                .collect()
+        };
+        let full_source = node_text(bytes, &span);
+        let signature = node_text(bytes, &node).lines().next().unwrap_or("").to_string();
+        let doc = python_docstring(&node, bytes)
+            .filter(|_| matches!(ctx.lang, DetectedLanguage::Python))
+            .unwrap_or_else(|| get_doc_comment(bytes, &span));
+        let is_test = match ctx.lang {
+            DetectedLanguage::Rust => kind == EntityKind::Function && rust_is_test(&node, bytes),
+            DetectedLanguage::Python => {
+                matches!(kind, EntityKind::Function | EntityKind::Method)
+                    && python_is_test(&name, owner.as_deref(), ctx.file_path)
+            }
+            _ => false,
+        };
+
+        entities.push(Entity {
+            id: make_entity_id(ctx.file_path, &name, &kind),
+            name,
+            kind,
+            file: ctx.file_path.to_string(),
+            line: span.start_position().row + 1,
+            end_line: span.end_position().row + 1,
+            source: full_source.to_string(),
+            signature,
+            package: ctx.package.to_string(),
+            doc_comment: doc,
+            owner,
+            receiver_kind: None,
+            decorators,
+            visibility: match ctx.lang {
+                DetectedLanguage::Rust => rust_visibility(&node, bytes),
+                DetectedLanguage::Python => python_visibility(&name_for_visibility),
+                _ if span.kind() == "export_statement" => Visibility::Public,
+                _ => Visibility::Private,
+            },
+            is_test,
+            metrics: Default::default(),
+            shape: Vec::new(),
+            references: Vec::new(),
+        });
+    }
+
+    fn is_js_function(node: &tree_sitter::Node) -> bool {
+        matches!(
+            node.kind(),
+            "arrow_function" | "function_expression" | "function" | "generator_function"
+        )
+    }
+
+    /// JS module-level `const a = ..., b = ...`: one entity per declarator,
+    /// a Function when bound to an arrow function or function expression.
+    fn push_js_bindings(ctx: &WalkContext, entities: &mut Vec<Entity>, decl: tree_sitter::Node) {
+        let span = decl.parent().filter(|p| p.kind() == "export_statement").unwrap_or(decl);
+        let mut cursor = decl.walk();
+        let declarators: Vec<_> = decl
+            .named_children(&mut cursor)
+            .filter(|c| c.kind() == "variable_declarator")
+            .collect();
+        for declarator in &declarators {
+            // Destructuring patterns have no single name
+            let Some(name) = declarator.child_by_field_name("name").filter(|n| n.kind() == "identifier") else {
+                continue;
+            };
+            let is_function = declarator
+                .child_by_field_name("value")
+                .is_some_and(|v| is_js_function(&v));
+            let kind = if is_function { EntityKind::Function } else { EntityKind::Variable };
+            let name = node_text(ctx.bytes, &name).to_string();
+            if declarators.len() == 1 {
+                push_entity(ctx, entities, kind, name, None, decl, span);
+            } else {
+                push_entity(ctx, entities, kind, name, None, *declarator, *declarator);
+                if span.kind() == "export_statement" {
+                    entities.last_mut().unwrap().visibility = Visibility::Public;
+                }
+            }
+        }
+    }
+
     // Walk all top-level and nested definitions
     fn walk_node(
         node: tree_sitter::Node,
-        bytes: &[u8],
-        file_path: &str,
-        package: &str,
+        ctx: &WalkContext,
         entities: &mut Vec<Entity>,
-        lang: &DetectedLanguage,
+        owner: Option<&str>,
     ) {
+        let (bytes, lang) = (ctx.bytes, ctx.lang);
         let kind_opt = match (lang, node.kind()) {
+            // Rust functions inside impl/trait blocks are methods of that type
+            (DetectedLanguage::Rust, "function_item") if owner.is_some() => Some(EntityKind::Method),
             // Rust
             (DetectedLanguage::Rust, "function_item") => Some(EntityKind::Function),
             (DetectedLanguage::Rust, "struct_item") => Some(EntityKind::Struct),
@@ -423,47 +1073,83 @@ pub fn extract_entities_generic(
             (DetectedLanguage::Rust, "const_item") => Some(EntityKind::Constant),
             (DetectedLanguage::Rust, "static_item") => Some(EntityKind::Variable),
             (DetectedLanguage::Rust, "mod_item") => Some(EntityKind::Module),
+            // Python functions directly inside a class body are its methods
+            (DetectedLanguage::Python, "function_definition") if owner.is_some() => Some(EntityKind::Method),
             // Python
             (DetectedLanguage::Python, "function_definition") => Some(EntityKind::Function),
             (DetectedLanguage::Python, "class_definition") => Some(EntityKind::Class),
+            // JavaScript/TypeScript class members: methods, getters/setters and
+            // fields; fields holding a function behave as methods
+            (DetectedLanguage::JavaScript, "method_definition") if owner.is_some() => Some(EntityKind::Method),
+            (DetectedLanguage::JavaScript, "field_definition") if owner.is_some() => {
+                if node.child_by_field_name("value").is_some_and(|v| is_js_function(&v)) {
+                    Some(EntityKind::Method)
+                } else {
+                    Some(EntityKind::Field)
+                }
+            }
             // JavaScript/TypeScript
             (DetectedLanguage::JavaScript, "function_declaration") => Some(EntityKind::Function),
+            (DetectedLanguage::JavaScript, "generator_function_declaration") => Some(EntityKind::Function),
             (DetectedLanguage::JavaScript, "class_declaration") => Some(EntityKind::Class),
-            (DetectedLanguage::JavaScript, "lexical_declaration") => Some(EntityKind::Variable),
-            (DetectedLanguage::JavaScript, "variable_declaration") => Some(EntityKind::Variable),
             _ => None,
         };
 
+        // Module-level JS bindings; locals inside function bodies are skipped
+        if matches!(lang, DetectedLanguage::JavaScript)
+            && matches!(node.kind(), "lexical_declaration" | "variable_declaration")
+            && node.parent().is_some_and(|p| matches!(p.kind(), "program" | "export_statement"))
+        {
+            push_js_bindings(ctx, entities, node);
+        }
+
+        // Owner passed down to children: set by impl/trait blocks, cleared by
+        // any other entity so nested items are not treated as members.
+        let mut child_owner = owner.map(str::to_string);
+        if matches!(lang, DetectedLanguage::Rust) && node.kind() == "impl_item" {
+            child_owner = node
+                .child_by_field_name("type")
+                .map(|t| rust_base_type_name(t, bytes))
+                .filter(|n| !n.is_empty());
+        }
+
         if let Some(kind) = kind_opt {
             let name = node
                 .child_by_field_name("name")
-                .map(|n| std::str::from_utf8(&bytes[n.byte_range()]).unwrap_or("").to_string())
+                .or_else(|| node.child_by_field_name("property"))
+                .map(|n| node_text(bytes, &n).to_string())
                 .unwrap_or_else(|| "<anonymous>".to_string());
 
-            if name != "<anonymous>" {
-                let full_source = std::str::from_utf8(&bytes[node.byte_range()]).unwrap_or("");
-                let signature = full_source.lines().next().unwrap_or("").to_string();
-                let doc = get_doc_comment(bytes, &node);
-
-                entities.push(Entity {
-                    id: make_entity_id(file_path, &name, &kind),
-                    name,
-                    kind,
-                    file: file_path.to_string(),
-                    line: node.start_position().row + 1,
-                    end_line: node.end_position().row + 1,
-                    source: full_source.to_string(),
-                    signature,
-                    package: package.to_string(),
-                    doc_comment: doc,
-                });
+            let (name, entity_owner) = match (&kind, owner) {
+                (EntityKind::Method | EntityKind::Field, Some(o)) => {
+                    (format!("{}.{}", o, name), Some(o.to_string()))
+                }
+                // Nested Python classes are named after their enclosing class
+                (EntityKind::Class, Some(o)) if matches!(lang, DetectedLanguage::Python) => {
+                    (format!("{}.{}", o, name), Some(o.to_string()))
+                }
+                _ => (name, None),
+            };
+
+            child_owner = match kind {
+                EntityKind::Trait | EntityKind::Class => Some(name.clone()),
+                _ => None,
+            };
+
+            if !name.ends_with("<anonymous>") {
+                // Decorators and `export` belong to the entity: include them in its span
+                let span = node
+                    .parent()
+                    .filter(|p| matches!(p.kind(), "decorated_definition" | "export_statement"))
+                    .unwrap_or(node);
+                push_entity(ctx, entities, kind, name, entity_owner, node, span);
             }
         }
 
         // Recurse into children
         let mut cursor = node.walk();
         for child in node.children(&mut cursor) {
-            walk_node(child, bytes, file_path, package, entities, lang);
+            walk_node(child, ctx, entities, child_owner.as_deref());
         }
     }
 
@@ -474,10 +1160,127 @@ pub fn extract_entities_generic(
         .unwrap_or("")
         .to_string();
 
-    walk_node(root, bytes, file_path, &package, &mut entities, lang);
+    let ctx = WalkContext { bytes, file_path, package: &package, lang };
+    walk_node(root, &ctx, &mut entities, None);
+
+    match lang {
+        DetectedLanguage::JavaScript => mark_js_exports(&mut entities, root, bytes),
+        DetectedLanguage::Python => apply_python_all(&mut entities, root, bytes),
+        _ => {}
+    }
     entities
 }
 
+/// Make entities public when exported through `export { a, b as c }`, `export default a`,
+/// `module.exports = { a }` or `exports.a = ...`, and members of exported
+/// classes (except `#private` ones).
+fn mark_js_exports(entities: &mut [Entity], root: tree_sitter::Node, bytes: &[u8]) {
+    let mut names: HashSet<String> = HashSet::new();
+    let mut cursor = root.walk();
+    for stmt in root.named_children(&mut cursor) {
+        match stmt.kind() {
+            "export_statement" => {
+                let mut inner = stmt.walk();
+                for child in stmt.named_children(&mut inner) {
+                    match child.kind() {
+                        "export_clause" => {
+                            let mut specs = child.walk();
+                            for spec in child.named_children(&mut specs) {
+                                if let Some(name) = spec.child_by_field_name("name") {
+                                    names.insert(node_text(bytes, &name).to_string());
+                                }
+                            }
+                        }
+                        // `export default name;`
+                        "identifier" => {
+                            names.insert(node_text(bytes, &child).to_string());
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            "expression_statement" => {
+                let Some(assign) = stmt.named_child(0).filter(|a| a.kind() == "assignment_expression") else {
+                    continue;
+                };
+                let (Some(left), Some(right)) =
+                    (assign.child_by_field_name("left"), assign.child_by_field_name("right"))
+                else {
+                    continue;
+                };
+                let target = node_text(bytes, &left);
+                if target == "module.exports" {
+                    if right.kind() == "identifier" {
+                        names.insert(node_text(bytes, &right).to_string());
+                    } else if right.kind() == "object" {
+                        let mut props = right.walk();
+                        for prop in right.named_children(&mut props) {
+                            let key = match prop.kind() {
+                                "shorthand_property_identifier" => Some(prop),
+                                "pair" => prop.child_by_field_name("value").filter(|v| v.kind() == "identifier"),
+                                _ => None,
+                            };
+                            if let Some(key) = key {
+                                names.insert(node_text(bytes, &key).to_string());
+                            }
+                        }
+                    }
+                } else if let Some(name) = target.strip_prefix("exports.").or_else(|| target.strip_prefix("module.exports.")) {
+                    names.insert(name.to_string());
+                    if right.kind() == "identifier" {
+                        names.insert(node_text(bytes, &right).to_string());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for e in entities.iter_mut().filter(|e| e.owner.is_none() && names.contains(&e.name)) {
+        e.visibility = Visibility::Public;
+    }
+    let exported_types: HashSet<String> = entities
+        .iter()
+        .filter(|e| e.visibility == Visibility::Public && e.kind.is_type())
+        .map(|e| e.name.clone())
+        .collect();
+    for e in entities.iter_mut() {
+        if let Some(owner) = &e.owner {
+            let private = e.name.rsplit('.').next().is_some_and(|m| m.starts_with('#'));
+            e.visibility = if exported_types.contains(owner) && !private {
+                Visibility::Public
+            } else {
+                Visibility::Private
+            };
+        }
+    }
+}
+
+/// A module-level `__all__ = [...]` lists the public names; every other
+/// top-level entity becomes private. Members keep their own visibility.
+fn apply_python_all(entities: &mut [Entity], root: tree_sitter::Node, bytes: &[u8]) {
+    let mut cursor = root.walk();
+    let all = root.named_children(&mut cursor).find_map(|stmt| {
+        let assign = stmt.named_child(0).filter(|a| a.kind() == "assignment")?;
+        let left = assign.child_by_field_name("left")?;
+        if node_text(bytes, &left) != "__all__" {
+            return None;
+        }
+        let right = assign.child_by_field_name("right")?;
+        let mut items = right.walk();
+        let names: HashSet<String> = right
+            .named_children(&mut items)
+            .filter(|i| i.kind() == "string")
+            .map(|i| node_text(bytes, &i).trim_matches(['"', '\'']).to_string())
+            .collect();
+        Some(names)
+    });
+    let Some(all) = all else { return };
+    for e in entities.iter_mut().filter(|e| e.owner.is_none()) {
+        e.visibility = if all.contains(&e.name) { Visibility::Public } else { Visibility::Private };
+    }
+}
+
 /// Metadata about an entity used during reference resolution.
 pub(crate) struct EntityMeta {
     /// Repo-relative directory path (unique package identifier).
@@ -566,157 +1369,556 @@ fn parse_go_imports(source: &str, tree: &Tree) -> HashMap<String, String> {
 }
 
 /// Get the repo-relative directory for a file path.
-fn file_dir(file_path: &str) -> String {
+pub(crate) fn file_dir(file_path: &str) -> String {
     Path::new(file_path)
         .parent()
+        .filter(|p| !p.as_os_str().is_empty())
         .and_then(|p| p.to_str())
         .unwrap_or(".")
         .to_string()
 }
 
-/// Extract references from entities in a single file.
-///
-/// Resolution strategy (Go-specific, with fallback for other languages):
-/// - Qualified refs (pkg.Name): resolve qualifier through file imports + module
-///   path to get a repo-relative dir, then match entities in that dir.
-/// - Bare identifiers: match entities in the same directory (same package).
-pub fn extract_references(
-    source: &str,
-    tree: &Tree,
-    file_entities: &[Entity],
-    all_entity_names: &HashMap<String, Vec<String>>,
-    entity_meta: &HashMap<String, EntityMeta>,
-    file_import_dirs: &ImportMap,
-    caller_pkg_dir: &str,
-) -> Vec<Relation> {
-    let mut relations = Vec::new();
-    let root = tree.root_node();
-    let bytes = source.as_bytes();
-    let mut seen: HashSet<(String, String)> = HashSet::new();
+/// The inferred type of a local variable, parameter or receiver.
+#[derive(Debug, Clone)]
+struct LocalType {
+    /// Base type name, e.g. `Server` for `*pkg.Server`.
+    name: String,
+    /// Repo-relative directory declaring the type, when the reference is
+    /// package-qualified. `None` means "prefer the caller's package".
+    dir: Option<String>,
+}
 
-    fn try_add(
-        from_id: &str,
-        to_id: &str,
-        kind: RelationKind,
-        seen: &mut HashSet<(String, String)>,
-        relations: &mut Vec<Relation>,
-    ) -> bool {
-        if from_id == to_id {
-            return false;
-        }
-        let key = (from_id.to_string(), to_id.to_string());
-        if seen.insert(key) {
-            relations.push(Relation {
-                from_id: from_id.to_string(),
-                to_id: to_id.to_string(),
-                kind,
-            });
-            true
-        } else {
-            false
+/// Infer local variable types within an entity body. Lightweight and flow
+/// insensitive: parameters, receivers, `:=`/`var`/`let` bindings from struct
+/// literals, `&T{}`, `new(T)` and constructor calls (`NewT()`, `T::new()`).
+fn infer_local_types(
+    entity: &Entity,
+    node: tree_sitter::Node,
+    bytes: &[u8],
+    file_import_dirs: &ImportMap,
+) -> HashMap<String, LocalType> {
+    let mut locals = HashMap::new();
+
+    // Rust methods: `self`/`Self` are the impl type; Python `self`/`cls`; JS `this`
+    let self_names: &[&str] = match node.kind() {
+        "function_item" => &["self", "Self"],
+        "function_definition" | "decorated_definition" => &["self", "cls"],
+        "method_definition" | "field_definition" => &["this"],
+        _ => &[],
+    };
+    if let Some(owner) = entity.owner.as_ref().filter(|_| entity.kind == EntityKind::Method) {
+        for var in self_names {
+            locals.insert(var.to_string(), LocalType { name: owner.clone(), dir: None });
         }
     }
 
-    /// Match a name against entities, filtering by expected pkg_dir.
-    fn match_targets(
-        name: &str,
-        expected_dir: &str,
-        from_id: &str,
-        kind: &RelationKind,
-        name_to_ids: &HashMap<String, Vec<String>>,
-        entity_meta: &HashMap<String, EntityMeta>,
-        seen: &mut HashSet<(String, String)>,
-        relations: &mut Vec<Relation>,
-    ) {
-        if let Some(target_ids) = name_to_ids.get(name) {
-            for target_id in target_ids {
-                if let Some(meta) = entity_meta.get(target_id.as_str()) {
-                    if meta.pkg_dir == expected_dir {
-                        try_add(from_id, target_id, kind.clone(), seen, relations);
-                    }
-                }
+    fn type_of(node: tree_sitter::Node, bytes: &[u8], imports: &ImportMap) -> Option<LocalType> {
+        match node.kind() {
+            "pointer_type" => type_of(node.named_child(0)?, bytes, imports),
+            "reference_type" | "generic_type" | "generic_type_with_turbofish" => {
+                type_of(node.child_by_field_name("type")?, bytes, imports)
+            }
+            // Go `pkg.Type`: only packages inside this module can be resolved
+            "qualified_type" => {
+                let package = node_text(bytes, &node.child_by_field_name("package")?);
+                Some(LocalType {
+                    name: node_text(bytes, &node.child_by_field_name("name")?).to_string(),
+                    dir: Some(imports.get(package)?.clone()),
+                })
             }
+            "scoped_type_identifier" | "type_identifier" => Some(LocalType {
+                name: rust_base_type_name(node, bytes),
+                dir: None,
+            }),
+            _ => None,
         }
     }
 
-    fn find_references(
+    fn value_type_of(
         node: tree_sitter::Node,
         bytes: &[u8],
-        from_id: &str,
-        caller_pkg_dir: &str,
-        file_import_dirs: &ImportMap,
-        name_to_ids: &HashMap<String, Vec<String>>,
-        entity_meta: &HashMap<String, EntityMeta>,
-        relations: &mut Vec<Relation>,
+        imports: &ImportMap,
+        locals: &HashMap<String, LocalType>,
+    ) -> Option<LocalType> {
+        match node.kind() {
+            "composite_literal" => type_of(node.child_by_field_name("type")?, bytes, imports),
+            "struct_expression" => type_of(node.child_by_field_name("name")?, bytes, imports),
+            "unary_expression" => {
+                value_type_of(node.child_by_field_name("operand")?, bytes, imports, locals)
+            }
+            "reference_expression" => {
+                value_type_of(node.child_by_field_name("value")?, bytes, imports, locals)
+            }
+            "try_expression" | "parenthesized_expression" => {
+                value_type_of(node.named_child(0)?, bytes, imports, locals)
+            }
+            "call_expression" => {
+                let func = node.child_by_field_name("function")?;
+                match func.kind() {
+                    // Go `new(T)` and `NewT(...)`
+                    "identifier" => {
+                        let name = node_text(bytes, &func);
+                        if name == "new" {
+                            let args = node.child_by_field_name("arguments")?;
+                            type_of(args.named_child(0)?, bytes, imports)
+                        } else {
+                            let type_name = name.strip_prefix("New").filter(|t| !t.is_empty())?;
+                            Some(LocalType { name: type_name.to_string(), dir: None })
+                        }
+                    }
+                    // Go `pkg.NewT(...)`
+                    "selector_expression" => {
+                        let package = node_text(bytes, &func.child_by_field_name("operand")?);
+                        let field = node_text(bytes, &func.child_by_field_name("field")?);
+                        let type_name = field.strip_prefix("New").filter(|t| !t.is_empty())?;
+                        Some(LocalType {
+                            name: type_name.to_string(),
+                            dir: Some(imports.get(package)?.clone()),
+                        })
+                    }
+                    // Rust `Type::new()`, `Type::with_capacity(..)`, `Self::default()`
+                    "scoped_identifier" => {
+                        let name = node_text(bytes, &func.child_by_field_name("name")?);
+                        let is_constructor = name == "new"
+                            || name == "default"
+                            || name.starts_with("new_")
+                            || name.starts_with("with_")
+                            || name.starts_with("from");
+                        if !is_constructor {
+                            return None;
+                        }
+                        let path = func.child_by_field_name("path")?;
+                        if node_text(bytes, &path) == "Self" {
+                            return locals.get("Self").cloned();
+                        }
+                        Some(LocalType { name: rust_base_type_name(path, bytes), dir: None })
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Variable name bound by a simple pattern (`x`, `mut x`).
+    fn binding_name<'a>(pattern: tree_sitter::Node, bytes: &'a [u8]) -> Option<&'a str> {
+        match pattern.kind() {
+            "identifier" => Some(node_text(bytes, &pattern)),
+            "mut_pattern" => {
+                let mut cursor = pattern.walk();
+                let inner = pattern.named_children(&mut cursor).find(|c| c.kind() == "identifier");
+                inner.map(|n| node_text(bytes, &n))
+            }
+            _ => None,
+        }
+    }
+
+    /// Pair Go `a, b := x, y` bindings; a single call on the right types the first name.
+    fn bind_values(
+        names: &[&str],
+        values: Option<tree_sitter::Node>,
+        bytes: &[u8],
+        imports: &ImportMap,
+        locals: &mut HashMap<String, LocalType>,
+    ) {
+        let Some(values) = values else { return };
+        let mut cursor = values.walk();
+        let values: Vec<tree_sitter::Node> = if values.kind() == "expression_list" {
+            values.named_children(&mut cursor).collect()
+        } else {
+            vec![values]
+        };
+        for (i, name) in names.iter().enumerate() {
+            let value = if values.len() == names.len() {
+                values.get(i)
+            } else if i == 0 {
+                values.first()
+            } else {
+                None
+            };
+            if let Some(t) = value.and_then(|v| value_type_of(*v, bytes, imports, locals)) {
+                locals.insert(name.to_string(), t);
+            }
+        }
+    }
+
+    fn collect(
+        node: tree_sitter::Node,
+        bytes: &[u8],
+        imports: &ImportMap,
+        locals: &mut HashMap<String, LocalType>,
+    ) {
+        match node.kind() {
+            // Go parameters and receivers: `a, b T`
+            "parameter_declaration" => {
+                if let Some(t) = node.child_by_field_name("type").and_then(|t| type_of(t, bytes, imports)) {
+                    let mut cursor = node.walk();
+                    for name in node.children_by_field_name("name", &mut cursor) {
+                        locals.insert(node_text(bytes, &name).to_string(), t.clone());
+                    }
+                }
+            }
+            // Rust parameters: `name: T`
+            "parameter" => {
+                let name = node.child_by_field_name("pattern").and_then(|p| binding_name(p, bytes));
+                let t = node.child_by_field_name("type").and_then(|t| type_of(t, bytes, imports));
+                if let (Some(name), Some(t)) = (name, t) {
+                    locals.insert(name.to_string(), t);
+                }
+            }
+            "short_var_declaration" => {
+                if let Some(left) = node.child_by_field_name("left") {
+                    let mut cursor = left.walk();
+                    let names: Vec<&str> = left
+                        .named_children(&mut cursor)
+                        .map(|n| node_text(bytes, &n))
+                        .collect();
+                    bind_values(&names, node.child_by_field_name("right"), bytes, imports, locals);
+                }
+            }
+            "var_spec" => {
+                let mut cursor = node.walk();
+                let names: Vec<&str> = node
+                    .children_by_field_name("name", &mut cursor)
+                    .filter(|n| n.is_named())
+                    .map(|n| node_text(bytes, &n))
+                    .collect();
+                match node.child_by_field_name("type").and_then(|t| type_of(t, bytes, imports)) {
+                    Some(t) => {
+                        for name in names {
+                            locals.insert(name.to_string(), t.clone());
+                        }
+                    }
+                    None => bind_values(&names, node.child_by_field_name("value"), bytes, imports, locals),
+                }
+            }
+            "let_declaration" => {
+                if let Some(name) = node.child_by_field_name("pattern").and_then(|p| binding_name(p, bytes)) {
+                    let t = match node.child_by_field_name("type") {
+                        Some(t) => type_of(t, bytes, imports),
+                        None => node
+                            .child_by_field_name("value")
+                            .and_then(|v| value_type_of(v, bytes, imports, locals)),
+                    };
+                    if let Some(t) = t {
+                        locals.insert(name.to_string(), t);
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            collect(child, bytes, imports, locals);
+        }
+    }
+
+    collect(node, bytes, file_import_dirs, &mut locals);
+    locals
+}
+
+/// Node spanning the entity's lines; with `source`, only the node with
+/// exactly that text (several declarators can share one line).
+fn find_node_at<'t>(
+    node: tree_sitter::Node<'t>,
+    start_line: usize,
+    end_line: usize,
+    source: Option<(&[u8], &str)>,
+) -> Option<tree_sitter::Node<'t>> {
+    if node.start_position().row + 1 == start_line
+        && node.end_position().row + 1 == end_line
+        && source.is_none_or(|(bytes, text)| node_text(bytes, &node) == text)
+    {
+        return Some(node);
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        if let Some(found) = find_node_at(child, start_line, end_line, source) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+/// The syntax node an entity was extracted from.
+pub(crate) fn entity_node<'t>(root: tree_sitter::Node<'t>, bytes: &[u8], entity: &Entity) -> Option<tree_sitter::Node<'t>> {
+    find_node_at(root, entity.line, entity.end_line, Some((bytes, &entity.source)))
+        .or_else(|| find_node_at(root, entity.line, entity.end_line, None))
+}
+
+/// Extract references from entities in a single file.
+///
+/// Resolution strategy (Go-specific, with fallback for other languages):
+/// - Qualified refs (pkg.Name): resolve qualifier through file imports + module
+///   path to get a repo-relative dir, then match entities in that dir.
+/// - Selectors on typed locals (recv.Field, x.Method()): resolve to
+///   `Type.Member` in the directory declaring the local's type.
+/// - Other selectors: Go and Rust leave them unresolved; Python and JavaScript
+///   fall back to any same-directory entity with that name.
+/// - Bare identifiers: match entities in the same directory (same package).
+///
+/// Also returns, for each of `file_entities`, where in its source the names
+/// that resolved to another entity are (see `Entity::references`).
+#[allow(clippy::too_many_arguments)]
+pub fn extract_references(
+    source: &str,
+    tree: &Tree,
+    lang: &DetectedLanguage,
+    file_entities: &[Entity],
+    all_entity_names: &HashMap<String, Vec<String>>,
+    entity_meta: &HashMap<String, EntityMeta>,
+    file_import_dirs: &ImportMap,
+    caller_pkg_dir: &str,
+) -> (Vec<Relation>, Vec<Vec<SourceReference>>) {
+    let mut relations = Vec::new();
+    let mut references = Vec::new();
+    let root = tree.root_node();
+    let bytes = source.as_bytes();
+    let mut seen: HashSet<(String, String)> = HashSet::new();
+
+    /// Per-entity lookup state shared by the recursive walk.
+    struct RefContext<'a> {
+        bytes: &'a [u8],
+        from_id: &'a str,
+        /// Byte offset of the entity's source in the file.
+        source_start: usize,
+        caller_pkg_dir: &'a str,
+        file_import_dirs: &'a ImportMap,
+        name_to_ids: &'a HashMap<String, Vec<String>>,
+        entity_meta: &'a HashMap<String, EntityMeta>,
+        /// Local variable name -> inferred type, e.g. method receivers.
+        local_types: HashMap<String, LocalType>,
+        /// Resolve `x.Name` with untyped `x` to any same-package `Name`.
+        untyped_member_fallback: bool,
+    }
+
+    fn try_add(
+        from_id: &str,
+        to_id: &str,
+        kind: RelationKind,
+        seen: &mut HashSet<(String, String)>,
+        relations: &mut Vec<Relation>,
+    ) -> bool {
+        if from_id == to_id {
+            return false;
+        }
+        let key = (from_id.to_string(), to_id.to_string());
+        if seen.insert(key) {
+            relations.push(Relation {
+                from_id: from_id.to_string(),
+                to_id: to_id.to_string(),
+                kind,
+            });
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Match a name against entities, filtering by expected pkg_dir.
+    /// Returns the ids matched, including ones already related.
+    fn match_targets(
+        ctx: &RefContext,
+        name: &str,
+        expected_dir: &str,
+        kind: &RelationKind,
+        seen: &mut HashSet<(String, String)>,
+        relations: &mut Vec<Relation>,
+    ) -> Vec<String> {
+        let mut matched = Vec::new();
+        if let Some(target_ids) = ctx.name_to_ids.get(name) {
+            for target_id in target_ids {
+                if let Some(meta) = ctx.entity_meta.get(target_id.as_str()) {
+                    if meta.pkg_dir == expected_dir {
+                        try_add(ctx.from_id, target_id, kind.clone(), seen, relations);
+                        matched.push(target_id.clone());
+                    }
+                }
+            }
+        }
+        matched
+    }
+
+    /// Match `Type.member` for a local of known type. Unqualified types prefer
+    /// the caller's package, then fall back to a unique match anywhere.
+    fn match_member(
+        ctx: &RefContext,
+        local: &LocalType,
+        member: &str,
+        kind: &RelationKind,
+        seen: &mut HashSet<(String, String)>,
+        relations: &mut Vec<Relation>,
+    ) -> Vec<String> {
+        let name = format!("{}.{}", local.name, member);
+        if let Some(dir) = &local.dir {
+            return match_targets(ctx, &name, dir, kind, seen, relations);
+        }
+        let Some(target_ids) = ctx.name_to_ids.get(&name) else { return Vec::new() };
+        let in_caller_pkg: Vec<&String> = target_ids
+            .iter()
+            .filter(|id| {
+                ctx.entity_meta
+                    .get(id.as_str())
+                    .is_some_and(|m| m.pkg_dir == ctx.caller_pkg_dir)
+            })
+            .collect();
+        if !in_caller_pkg.is_empty() {
+            for target_id in &in_caller_pkg {
+                try_add(ctx.from_id, target_id, kind.clone(), seen, relations);
+            }
+            in_caller_pkg.into_iter().cloned().collect()
+        } else if let [only] = target_ids.as_slice() {
+            try_add(ctx.from_id, only, kind.clone(), seen, relations);
+            vec![only.clone()]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Note that the name at `range` of the file resolved to `targets`; an
+    /// ambiguous name links to the first of them.
+    fn record(ctx: &RefContext, range: Range<usize>, targets: Vec<String>, spans: &mut Vec<SourceReference>) {
+        let Some(target_id) = targets.into_iter().find(|id| id != ctx.from_id) else { return };
+        if range.start >= ctx.source_start {
+            spans.push(SourceReference {
+                start: range.start - ctx.source_start,
+                end: range.end - ctx.source_start,
+                target_id,
+            });
+        }
+    }
+
+    /// The member name of a selector: Go `field`, Python `attribute`, JS `property`.
+    fn member_node(node: tree_sitter::Node) -> Option<tree_sitter::Node> {
+        node.child_by_field_name("field")
+            .or_else(|| node.child_by_field_name("attribute"))
+            .or_else(|| node.child_by_field_name("property"))
+    }
+
+    fn find_references(
+        node: tree_sitter::Node,
+        ctx: &RefContext,
+        relations: &mut Vec<Relation>,
         seen: &mut HashSet<(String, String)>,
+        spans: &mut Vec<SourceReference>,
     ) {
+        let bytes = ctx.bytes;
+
         // Handle call expressions: pkg.Func() or Func()
         if node.kind() == "call_expression" || node.kind() == "call" {
             if let Some(func_node) = node.child_by_field_name("function") {
                 let func_text = std::str::from_utf8(&bytes[func_node.byte_range()])
                     .unwrap_or("");
 
-                if func_text.contains('.') {
-                    // Qualified call: qualifier.Name()
-                    let simple_name = func_text.rsplit('.').next().unwrap_or(func_text);
-                    if let Some(qualifier) = func_text.rsplitn(2, '.').nth(1) {
-                        // Resolve qualifier via imports to a dir path
-                        if let Some(target_dir) = file_import_dirs.get(qualifier) {
-                            match_targets(
-                                simple_name, target_dir, from_id, &RelationKind::Calls,
-                                name_to_ids, entity_meta, seen, relations,
-                            );
-                        }
-                        // Also try matching methods: qualifier might be a variable,
-                        // not a package. In that case the method receiver type is
-                        // in the same package.
-                        if !file_import_dirs.contains_key(qualifier) {
-                            match_targets(
-                                simple_name, caller_pkg_dir, from_id, &RelationKind::Calls,
-                                name_to_ids, entity_meta, seen, relations,
-                            );
-                        }
+                if func_node.kind() == "scoped_identifier" {
+                    // Rust path call: `Type::assoc()` or `Self::assoc()`
+                    let path = func_node.child_by_field_name("path");
+                    let name = func_node.child_by_field_name("name");
+                    if let (Some(path), Some(name)) = (path, name) {
+                        let path_text = node_text(bytes, &path);
+                        let owner = ctx.local_types.get(path_text).cloned().unwrap_or_else(|| {
+                            LocalType { name: rust_base_type_name(path, bytes), dir: None }
+                        });
+                        let targets = match_member(
+                            ctx, &owner, node_text(bytes, &name), &RelationKind::Calls,
+                            seen, relations,
+                        );
+                        record(ctx, name.byte_range(), targets, spans);
                     }
+                } else if let Some((qualifier, simple_name)) = func_text.rsplit_once('.') {
+                    // Qualified call: qualifier.Name()
+                    // Resolve qualifier via imports to a dir path
+                    let targets = if let Some(target_dir) = ctx.file_import_dirs.get(qualifier) {
+                        match_targets(
+                            ctx, simple_name, target_dir, &RelationKind::Calls, seen, relations,
+                        )
+                    } else if let Some(local) = ctx.local_types.get(qualifier) {
+                        // Method on a local of known type: `Type.Name`
+                        match_member(ctx, local, simple_name, &RelationKind::Calls, seen, relations)
+                    } else if ctx.untyped_member_fallback {
+                        // Qualifier might be a variable of unknown type; guess
+                        // the method lives in the same package.
+                        [simple_name.to_string(), format!(".{}", simple_name)]
+                            .iter()
+                            .flat_map(|name| {
+                                match_targets(
+                                    ctx, name, ctx.caller_pkg_dir, &RelationKind::Calls,
+                                    seen, relations,
+                                )
+                            })
+                            .collect()
+                    } else {
+                        Vec::new()
+                    };
+                    let range = member_node(func_node)
+                        .map(|n| n.byte_range())
+                        .unwrap_or(func_node.end_byte() - simple_name.len()..func_node.end_byte());
+                    record(ctx, range, targets, spans);
                 } else {
                     // Bare call: same package only
-                    match_targets(
-                        func_text, caller_pkg_dir, from_id, &RelationKind::Calls,
-                        name_to_ids, entity_meta, seen, relations,
+                    let targets = match_targets(
+                        ctx, func_text, ctx.caller_pkg_dir, &RelationKind::Calls, seen, relations,
                     );
+                    record(ctx, func_node.byte_range(), targets, spans);
                 }
             }
             // Recurse into arguments but skip the function child
-            let func_id = node.child_by_field_name("function").map(|n| n.id());
+            let func_node = node.child_by_field_name("function");
+            let func_id = func_node.map(|n| n.id());
             let mut cursor = node.walk();
             for child in node.children(&mut cursor) {
                 if Some(child.id()) != func_id && child.kind() != "selector_expression" {
-                    find_references(child, bytes, from_id, caller_pkg_dir, file_import_dirs, name_to_ids, entity_meta, relations, seen);
+                    find_references(child, ctx, relations, seen, spans);
                 }
             }
+            // Method chains: `a.iter().filter(f).count()` holds calls in its receiver
+            let receiver = func_node.and_then(|f| {
+                ["operand", "value", "object"].iter().find_map(|field| f.child_by_field_name(field))
+            });
+            if let Some(receiver) = receiver.filter(|r| !matches!(r.kind(), "self" | "this")) {
+                find_references(receiver, ctx, relations, seen, spans);
+            }
             return;
         }
 
-        // Handle selector expressions: pkg.Type (non-call context)
-        if node.kind() == "selector_expression" || node.kind() == "qualified_type" {
-            let qualifier_text = node.child(0)
+        // Handle selector expressions: pkg.Type, Python obj.attr, JS obj.prop (non-call context)
+        if matches!(node.kind(), "selector_expression" | "qualified_type" | "attribute" | "member_expression") {
+            let qualifier = node.child(0);
+            let qualifier_text = qualifier
                 .and_then(|n| std::str::from_utf8(&bytes[n.byte_range()]).ok());
-            let field_text = node.child_by_field_name("field")
+            let field_node = member_node(node);
+            let field_text = field_node
                 .and_then(|n| std::str::from_utf8(&bytes[n.byte_range()]).ok());
 
-            if let (Some(qual), Some(field)) = (qualifier_text, field_text) {
-                if let Some(target_dir) = file_import_dirs.get(qual) {
+            if let (Some(qual), Some(field_node), Some(field)) = (qualifier_text, field_node, field_text) {
+                let targets = if let Some(target_dir) = ctx.file_import_dirs.get(qual) {
                     match_targets(
-                        field, target_dir, from_id, &RelationKind::References,
-                        name_to_ids, entity_meta, seen, relations,
-                    );
-                }
-                // Method/field on local variable — same package
-                if !file_import_dirs.contains_key(qual) {
-                    match_targets(
-                        field, caller_pkg_dir, from_id, &RelationKind::References,
-                        name_to_ids, entity_meta, seen, relations,
-                    );
-                }
+                        ctx, field, target_dir, &RelationKind::References, seen, relations,
+                    )
+                } else if let Some(local) = ctx.local_types.get(qual) {
+                    // Field on a local of known type — resolve to the member entity
+                    match_member(ctx, local, field, &RelationKind::References, seen, relations)
+                } else if ctx.untyped_member_fallback {
+                    // Method/field on local variable — same package
+                    [field.to_string(), format!(".{}", field)]
+                        .iter()
+                        .flat_map(|name| {
+                            match_targets(
+                                ctx, name, ctx.caller_pkg_dir, &RelationKind::References,
+                                seen, relations,
+                            )
+                        })
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+                record(ctx, field_node.byte_range(), targets, spans);
+            }
+            // Python/JS `a.b.c` / `f().x`: the object may hold further references
+            let nested = matches!(node.kind(), "attribute" | "member_expression");
+            if let Some(object) = qualifier.filter(|q| nested && !matches!(q.kind(), "identifier" | "this")) {
+                find_references(object, ctx, relations, seen, spans);
             }
             return;
         }
@@ -724,54 +1926,274 @@ pub fn extract_references(
         // Bare identifiers: same directory only
         if node.kind() == "type_identifier" || node.kind() == "identifier" {
             let name = std::str::from_utf8(&bytes[node.byte_range()]).unwrap_or("");
-            match_targets(
-                name, caller_pkg_dir, from_id, &RelationKind::References,
-                name_to_ids, entity_meta, seen, relations,
+            let targets = match_targets(
+                ctx, name, ctx.caller_pkg_dir, &RelationKind::References, seen, relations,
             );
+            record(ctx, node.byte_range(), targets, spans);
         }
 
         let mut cursor = node.walk();
         for child in node.children(&mut cursor) {
-            find_references(child, bytes, from_id, caller_pkg_dir, file_import_dirs, name_to_ids, entity_meta, relations, seen);
+            find_references(child, ctx, relations, seen, spans);
         }
     }
 
     for entity in file_entities {
-        fn find_node_at(
-            node: tree_sitter::Node,
-            start_line: usize,
-            end_line: usize,
-        ) -> Option<tree_sitter::Node> {
-            if node.start_position().row + 1 == start_line
-                && node.end_position().row + 1 == end_line
-            {
-                return Some(node);
+        let mut spans = Vec::new();
+        if let Some(entity_node) = entity_node(root, bytes, entity) {
+            let ctx = RefContext {
+                bytes,
+                from_id: &entity.id,
+                source_start: entity_node.start_byte(),
+                caller_pkg_dir,
+                file_import_dirs,
+                name_to_ids: all_entity_names,
+                entity_meta,
+                local_types: infer_local_types(entity, entity_node, bytes, file_import_dirs),
+                untyped_member_fallback: matches!(
+                    lang,
+                    DetectedLanguage::Python | DetectedLanguage::JavaScript
+                ),
+            };
+            find_references(entity_node, &ctx, &mut relations, &mut seen, &mut spans);
+            // Offsets only line up with `source` when the node is its text
+            if node_text(bytes, &entity_node) != entity.source {
+                spans.clear();
             }
-            let mut cursor = node.walk();
-            for child in node.children(&mut cursor) {
-                if let Some(found) = find_node_at(child, start_line, end_line) {
-                    return Some(found);
+        }
+        spans.sort_by_key(|s| s.start);
+        spans.dedup_by_key(|s| s.start);
+        references.push(spans);
+    }
+
+    (relations, references)
+}
+
+/// Entity name -> ids. Members are also indexed as `.member` so an untyped
+/// `x.member` can find them without knowing the owner.
+fn build_name_index(entities: &[Entity]) -> HashMap<String, Vec<String>> {
+    let mut map: HashMap<String, Vec<String>> = HashMap::new();
+    for e in entities {
+        map.entry(e.name.clone()).or_default().push(e.id.clone());
+        if e.owner.is_some() {
+            if let Some((_, member)) = e.name.rsplit_once('.') {
+                map.entry(format!(".{}", member)).or_default().push(e.id.clone());
+            }
+        }
+    }
+    map
+}
+
+/// Link owned entities (fields, methods, typed constants) to their owning
+/// type in the same directory with `Contains` edges.
+fn link_owned_entities(entities: &[Entity]) -> Vec<Relation> {
+    let type_ids: HashMap<(String, &str), &str> = entities
+        .iter()
+        .filter(|e| e.kind.is_type())
+        .map(|e| ((file_dir(&e.file), e.name.as_str()), e.id.as_str()))
+        .collect();
+
+    entities
+        .iter()
+        .filter_map(|e| {
+            let owner = e.owner.as_deref()?;
+            let owner_id = type_ids.get(&(file_dir(&e.file), owner))?;
+            Some(Relation {
+                from_id: owner_id.to_string(),
+                to_id: e.id.clone(),
+                kind: RelationKind::Contains,
+            })
+        })
+        .collect()
+}
+
+/// A supertype named in a type declaration: `class Admin(User)`,
+/// `impl Display for T`, Go struct embedding, ...
+#[derive(Debug, Clone)]
+pub(crate) struct TypeBase {
+    /// Name of the declaring type, as used for its entity.
+    type_name: String,
+    base: String,
+    /// Package/module qualifier on the base, e.g. `store` in `store.Base`.
+    qualifier: Option<String>,
+    kind: RelationKind,
+}
+
+/// Split `pkg.Name` / `mod::Name` / `Name<T>` into (qualifier, name).
+fn split_base_name(text: &str) -> (Option<String>, String) {
+    let text = text.split(['<', '[', '(']).next().unwrap_or("").trim();
+    let text = text.trim_start_matches(['*', '&']);
+    match text.rsplit_once("::").or_else(|| text.rsplit_once('.')) {
+        Some((qualifier, name)) => (Some(qualifier.to_string()), name.to_string()),
+        None => (None, text.to_string()),
+    }
+}
+
+/// Collect the supertypes declared in a file.
+pub(crate) fn extract_type_bases(source: &str, tree: &Tree, lang: &DetectedLanguage) -> Vec<TypeBase> {
+    let bytes = source.as_bytes();
+    let mut bases = Vec::new();
+
+    fn push(bases: &mut Vec<TypeBase>, type_name: &str, text: &str, kind: RelationKind) {
+        let (qualifier, base) = split_base_name(text);
+        if !base.is_empty() && base != type_name {
+            bases.push(TypeBase { type_name: type_name.to_string(), base, qualifier, kind });
+        }
+    }
+
+    fn walk(node: tree_sitter::Node, bytes: &[u8], lang: &DetectedLanguage, scope: Option<&str>, bases: &mut Vec<TypeBase>) {
+        let mut scope = scope.map(str::to_string);
+        match (lang, node.kind()) {
+            // Go: embedded struct fields and embedded interfaces
+            (DetectedLanguage::Go, "type_spec") => {
+                let name = node.child_by_field_name("name").map(|n| node_text(bytes, &n));
+                if let (Some(name), Some(ty)) = (name, node.child_by_field_name("type")) {
+                    let mut cursor = ty.walk();
+                    for member in ty.named_children(&mut cursor) {
+                        let mut inner = member.walk();
+                        let embedded: Vec<_> = match member.kind() {
+                            "field_declaration_list" => member
+                                .named_children(&mut inner)
+                                .filter(|f| f.kind() == "field_declaration" && f.child_by_field_name("name").is_none())
+                                .filter_map(|f| f.child_by_field_name("type"))
+                                .collect(),
+                            // A single-type element; unions are constraints, not embedding
+                            "type_elem" if member.named_child_count() == 1 => {
+                                member.named_child(0).into_iter().collect()
+                            }
+                            _ => Vec::new(),
+                        };
+                        for ty in embedded {
+                            push(bases, name, node_text(bytes, &ty), RelationKind::Embeds);
+                        }
+                    }
+                }
+                return;
+            }
+            (DetectedLanguage::Go, "function_declaration" | "method_declaration") => return,
+            // Rust: `impl Trait for Type` and supertraits `trait A: B`
+            (DetectedLanguage::Rust, "impl_item") => {
+                let ty = node.child_by_field_name("type").map(|t| rust_base_type_name(t, bytes));
+                if let (Some(ty), Some(tr)) = (ty, node.child_by_field_name("trait")) {
+                    push(bases, &ty, node_text(bytes, &tr), RelationKind::Implements);
+                }
+                return;
+            }
+            (DetectedLanguage::Rust, "trait_item") => {
+                let name = node.child_by_field_name("name").map(|n| node_text(bytes, &n));
+                if let (Some(name), Some(bounds)) = (name, node.child_by_field_name("bounds")) {
+                    let mut cursor = bounds.walk();
+                    for bound in bounds.named_children(&mut cursor) {
+                        if bound.kind() != "lifetime" {
+                            push(bases, name, node_text(bytes, &bound), RelationKind::Extends);
+                        }
+                    }
+                }
+                return;
+            }
+            (DetectedLanguage::Rust, "function_item") => return,
+            // Python: `class Admin(User, mixins.Audit)`; nested classes are `Outer.Inner`
+            (DetectedLanguage::Python, "class_definition") => {
+                let Some(name) = node.child_by_field_name("name").map(|n| node_text(bytes, &n)) else { return };
+                let name = match &scope {
+                    Some(outer) => format!("{}.{}", outer, name),
+                    None => name.to_string(),
+                };
+                if let Some(args) = node.child_by_field_name("superclasses") {
+                    let mut cursor = args.walk();
+                    for arg in args.named_children(&mut cursor) {
+                        if matches!(arg.kind(), "identifier" | "attribute" | "subscript") {
+                            push(bases, &name, node_text(bytes, &arg), RelationKind::Extends);
+                        }
+                    }
                 }
+                scope = Some(name);
             }
-            None
+            (DetectedLanguage::Python, "function_definition") => scope = None,
+            // JS/TS: `class A extends B implements C, D`. Read from the header
+            // text so TypeScript clauses survive a JavaScript parse.
+            (DetectedLanguage::JavaScript, "class_declaration") => {
+                let name = node.child_by_field_name("name").map(|n| node_text(bytes, &n));
+                let header_end = node.child_by_field_name("body").map_or(node.end_byte(), |b| b.start_byte());
+                let header = std::str::from_utf8(&bytes[node.start_byte()..header_end]).unwrap_or("");
+                if let Some(name) = name {
+                    let mut kind = None;
+                    for token in header.split(|c: char| c.is_whitespace() || c == ',') {
+                        match token {
+                            "extends" => kind = Some(RelationKind::Extends),
+                            "implements" => kind = Some(RelationKind::Implements),
+                            "" => {}
+                            _ => {
+                                if let Some(k) = &kind {
+                                    push(bases, name, token, k.clone());
+                                    if *k == RelationKind::Extends {
+                                        kind = None; // single superclass
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {}
         }
 
-        if let Some(entity_node) = find_node_at(root, entity.line, entity.end_line) {
-            find_references(
-                entity_node,
-                bytes,
-                &entity.id,
-                caller_pkg_dir,
-                file_import_dirs,
-                all_entity_names,
-                entity_meta,
-                &mut relations,
-                &mut seen,
-            );
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            walk(child, bytes, lang, scope.as_deref(), bases);
         }
     }
 
-    relations
+    walk(tree.root_node(), bytes, lang, None, &mut bases);
+    bases
+}
+
+/// Type entity name -> (directory, id), for resolving supertypes.
+fn build_type_index(entities: &[Entity]) -> HashMap<&str, Vec<(String, &str)>> {
+    let mut map: HashMap<&str, Vec<(String, &str)>> = HashMap::new();
+    for e in entities.iter().filter(|e| e.kind.is_type()) {
+        map.entry(e.name.as_str()).or_default().push((file_dir(&e.file), e.id.as_str()));
+    }
+    map
+}
+
+/// Resolve supertypes to `Extends`/`Implements`/`Embeds` relations. The
+/// declaring type is looked up in `dir`; the base through its import
+/// qualifier, then the same directory, then a unique match anywhere.
+fn link_type_bases(
+    bases: &[TypeBase],
+    lang: &DetectedLanguage,
+    dir: &str,
+    file_import_dirs: &ImportMap,
+    type_ids: &HashMap<&str, Vec<(String, &str)>>,
+) -> Vec<Relation> {
+    let lookup = |name: &str, dir: &str| {
+        type_ids
+            .get(name)
+            .and_then(|ids| ids.iter().find(|(d, _)| d == dir).map(|(_, id)| *id))
+    };
+
+    bases
+        .iter()
+        .filter_map(|b| {
+            let from = lookup(&b.type_name, dir)?;
+            let qualified_dir = b.qualifier.as_ref().and_then(|q| file_import_dirs.get(q));
+            let to = match qualified_dir {
+                Some(target_dir) => lookup(&b.base, target_dir),
+                // Go qualifiers are imports: an unresolved one is an external package
+                None if b.qualifier.is_some() && matches!(lang, DetectedLanguage::Go) => None,
+                None => lookup(&b.base, dir).or_else(|| match type_ids.get(b.base.as_str())?.as_slice() {
+                    [(_, only)] => Some(*only),
+                    _ => None,
+                }),
+            }?;
+            (from != to).then(|| Relation {
+                from_id: from.to_string(),
+                to_id: to.to_string(),
+                kind: b.kind.clone(),
+            })
+        })
+        .collect()
 }
 
 /// For each entity in a file, find external import paths referenced in its source.
@@ -825,30 +2247,34 @@ pub fn get_go_package(source: &str, tree: &Tree) -> String {
     "main".to_string()
 }
 
-pub fn get_go_module_name(repo_path: &Path) -> String {
-    let go_mod = repo_path.join("go.mod");
-    if let Ok(content) = std::fs::read_to_string(&go_mod) {
+pub fn get_go_module_name(tree: &dyn SourceTree) -> String {
+    if let Ok(content) = tree.read(Path::new("go.mod")) {
         for line in content.lines() {
             if line.starts_with("module ") {
                 return line.trim_start_matches("module ").trim().to_string();
             }
         }
     }
-    repo_path
+    tree.root()
         .file_name()
         .and_then(|n| n.to_str())
         .unwrap_or("unknown")
         .to_string()
 }
 
-pub fn build_repo_info(repo_path: &Path, lang: &DetectedLanguage, graph: &EntityGraph) -> RepoInfo {
+pub fn build_repo_info(
+    tree: &dyn SourceTree,
+    lang: &DetectedLanguage,
+    graph: &EntityGraph,
+    total_files: usize,
+) -> RepoInfo {
+    let repo_path = tree.root();
     let name = repo_path
         .file_name()
         .and_then(|n| n.to_str())
         .unwrap_or("unknown")
         .to_string();
 
-    let files = collect_source_files(repo_path, lang);
     let mut packages: Vec<String> = graph
         .entities
         .iter()
@@ -862,7 +2288,7 @@ pub fn build_repo_info(repo_path: &Path, lang: &DetectedLanguage, graph: &Entity
 
     match lang {
         DetectedLanguage::Go => {
-            let module_name = get_go_module_name(repo_path);
+            let module_name = get_go_module_name(tree);
             attributes.push(RepoAttribute {
                 label: "Module".to_string(),
                 value: module_name.clone(),
@@ -874,7 +2300,7 @@ pub fn build_repo_info(repo_path: &Path, lang: &DetectedLanguage, graph: &Entity
                 link: None,
             });
             let go_mod = repo_path.join("go.mod");
-            if go_mod.exists() {
+            if tree.read(Path::new("go.mod")).is_ok() {
                 attributes.push(RepoAttribute {
                     label: "go.mod".to_string(),
                     value: "go.mod".to_string(),
@@ -886,7 +2312,7 @@ pub fn build_repo_info(repo_path: &Path, lang: &DetectedLanguage, graph: &Entity
                 path: repo_path.to_string_lossy().to_string(),
                 name,
                 language: lang.name().to_string(),
-                total_files: files.len(),
+                total_files,
                 total_entities: graph.entities.len(),
                 packages,
                 module_name,
@@ -901,7 +2327,7 @@ pub fn build_repo_info(repo_path: &Path, lang: &DetectedLanguage, graph: &Entity
             });
             attributes.push(RepoAttribute {
                 label: "Files".to_string(),
-                value: files.len().to_string(),
+                value: total_files.to_string(),
                 link: None,
             });
             attributes.push(RepoAttribute {
@@ -915,7 +2341,7 @@ pub fn build_repo_info(repo_path: &Path, lang: &DetectedLanguage, graph: &Entity
                 path: repo_path.to_string_lossy().to_string(),
                 name,
                 language: lang.name().to_string(),
-                total_files: files.len(),
+                total_files,
                 total_entities: graph.entities.len(),
                 packages,
                 module_name,
@@ -925,26 +2351,39 @@ pub fn build_repo_info(repo_path: &Path, lang: &DetectedLanguage, graph: &Entity
     }
 }
 
-pub fn parse_repo(repo_path: &Path) -> Option<(RepoInfo, EntityGraph)> {
+/// Parse a repository into an entity graph. When a code intelligence `index`
+/// is given, its occurrences supply references for the files it covers and
+/// tree-sitter heuristics are used for the rest.
+pub fn parse_repo(repo_path: &Path, index: Option<&CodeIndex>) -> Option<(RepoInfo, EntityGraph)> {
+    parse_tree(&WorkingTree(repo_path), index)
+}
+
+/// `parse_repo` over any source tree.
+pub fn parse_tree(source_tree: &dyn SourceTree, index: Option<&CodeIndex>) -> Option<(RepoInfo, EntityGraph)> {
+    let repo_path = source_tree.root();
     eprintln!("[fode] parse_repo: {:?}", repo_path);
 
-    let lang = detect_language(repo_path)?;
+    let paths = source_tree.paths();
+    let lang = detect_language_in(paths.iter().filter(|p| p.components().count() <= 5))?;
     eprintln!("[fode] detected language: {}", lang.name());
 
     let ts_lang = lang.tree_sitter_language();
-    let files = collect_source_files(repo_path, &lang);
+    let files = collect_source_files(&paths, &lang);
     eprintln!("[fode] found {} source files", files.len());
 
     // Track which entities belong to which file (by index in files vec)
     let mut all_entities = Vec::new();
     let mut file_entity_ranges: Vec<(usize, usize)> = Vec::new(); // (start, end) into all_entities
+    // Sources kept for the reference pass, None where reading or parsing failed
+    let mut sources: Vec<Option<String>> = Vec::new();
 
     for (i, file_path) in files.iter().enumerate() {
-        let source = match std::fs::read_to_string(file_path) {
+        let source = match source_tree.read(file_path) {
             Ok(s) => s,
             Err(e) => {
                 eprintln!("[fode] skip file (read error): {:?}: {}", file_path, e);
                 file_entity_ranges.push((all_entities.len(), all_entities.len()));
+                sources.push(None);
                 continue;
             }
         };
@@ -953,15 +2392,12 @@ pub fn parse_repo(repo_path: &Path) -> Option<(RepoInfo, EntityGraph)> {
             None => {
                 eprintln!("[fode] skip file (parse error): {:?}", file_path);
                 file_entity_ranges.push((all_entities.len(), all_entities.len()));
+                sources.push(None);
                 continue;
             }
         };
 
-        let rel_path = file_path
-            .strip_prefix(repo_path)
-            .unwrap_or(file_path)
-            .to_string_lossy()
-            .to_string();
+        let rel_path = file_path.to_string_lossy().to_string();
 
         let start = all_entities.len();
         let entities = match lang {
@@ -972,7 +2408,16 @@ pub fn parse_repo(repo_path: &Path) -> Option<(RepoInfo, EntityGraph)> {
             _ => extract_entities_generic(&source, &tree, &rel_path, &lang),
         };
         all_entities.extend(entities);
+        for e in &mut all_entities[start..] {
+            if let Some(node) = entity_node(tree.root_node(), source.as_bytes(), e) {
+                e.metrics = metrics::measure(node, source.as_bytes());
+                if matches!(e.kind, EntityKind::Function | EntityKind::Method) {
+                    e.shape = duplicates::shape(node);
+                }
+            }
+        }
         file_entity_ranges.push((start, all_entities.len()));
+        sources.push(Some(source));
 
         if (i + 1) % 100 == 0 {
             eprintln!("[fode] parsed {}/{} files, {} entities so far", i + 1, files.len(), all_entities.len());
@@ -983,19 +2428,14 @@ pub fn parse_repo(repo_path: &Path) -> Option<(RepoInfo, EntityGraph)> {
 
     // For Go, read the module path for import resolution
     let module_path = if matches!(lang, DetectedLanguage::Go) {
-        get_go_module_name(repo_path)
+        get_go_module_name(source_tree)
     } else {
         String::new()
     };
 
     // Build global name lookup once
-    let name_to_ids: HashMap<String, Vec<String>> = {
-        let mut map: HashMap<String, Vec<String>> = HashMap::new();
-        for e in &all_entities {
-            map.entry(e.name.clone()).or_default().push(e.id.clone());
-        }
-        map
-    };
+    let name_to_ids = build_name_index(&all_entities);
+    let type_ids = build_type_index(&all_entities);
 
     // Build entity metadata: id -> pkg_dir (repo-relative directory path)
     let entity_meta: HashMap<String, EntityMeta> = all_entities
@@ -1004,26 +2444,26 @@ pub fn parse_repo(repo_path: &Path) -> Option<(RepoInfo, EntityGraph)> {
         .collect();
 
     // Extract cross-references, scoped per file
-    let mut all_relations = Vec::new();
+    let mut all_relations = link_owned_entities(&all_entities);
     let mut all_external_deps: HashMap<String, Vec<String>> = HashMap::new();
+    let mut all_references: Vec<(usize, Vec<SourceReference>)> = Vec::new();
     for (i, file_path) in files.iter().enumerate() {
         let (start, end) = file_entity_ranges[i];
         if start == end {
             continue; // no entities in this file
         }
 
-        let source = match std::fs::read_to_string(file_path) {
-            Ok(s) => s,
-            Err(_) => continue,
+        let Some(source) = &sources[i] else {
+            continue;
         };
-        let tree = match parse_file(&source, ts_lang.clone()) {
+        let tree = match parse_file(source, ts_lang.clone()) {
             Some(t) => t,
             None => continue,
         };
 
         // Parse raw imports (qualifier -> full import path)
         let raw_imports: HashMap<String, String> = if matches!(lang, DetectedLanguage::Go) {
-            parse_go_imports(&source, &tree)
+            parse_go_imports(source, &tree)
         } else {
             HashMap::new()
         };
@@ -1042,19 +2482,23 @@ pub fn parse_repo(repo_path: &Path) -> Option<(RepoInfo, EntityGraph)> {
             })
             .collect();
 
-        let rel_path = file_path
-            .strip_prefix(repo_path)
-            .unwrap_or(file_path)
-            .to_string_lossy()
-            .to_string();
+        let rel_path = file_path.to_string_lossy().to_string();
         let caller_pkg_dir = file_dir(&rel_path);
 
+        let bases = extract_type_bases(source, &tree, &lang);
+        all_relations.extend(link_type_bases(&bases, &lang, &caller_pkg_dir, &file_import_dirs, &type_ids));
+
         let file_entities = &all_entities[start..end];
-        let relations = extract_references(
-            &source, &tree, file_entities, &name_to_ids, &entity_meta,
+        let (relations, references) = extract_references(
+            source, &tree, &lang, file_entities, &name_to_ids, &entity_meta,
             &file_import_dirs, &caller_pkg_dir,
         );
-        all_relations.extend(relations);
+        // An index replaces the heuristic edges of the files it covers; the
+        // located names still make the source clickable
+        if !index.is_some_and(|idx| idx.covers(&rel_path)) {
+            all_relations.extend(relations);
+        }
+        all_references.extend((start..end).zip(references));
 
         // Collect external deps for entities in this file
         if matches!(lang, DetectedLanguage::Go) {
@@ -1063,6 +2507,16 @@ pub fn parse_repo(repo_path: &Path) -> Option<(RepoInfo, EntityGraph)> {
         }
     }
 
+    for (i, references) in all_references {
+        all_entities[i].references = references;
+    }
+
+    if let Some(idx) = index {
+        let indexed = idx.build_relations(&all_entities);
+        eprintln!("[fode] {} relations from index {:?}", indexed.len(), idx.source);
+        all_relations.extend(indexed);
+    }
+
     eprintln!("[fode] found {} relations", all_relations.len());
 
     let graph = EntityGraph {
@@ -1071,7 +2525,567 @@ pub fn parse_repo(repo_path: &Path) -> Option<(RepoInfo, EntityGraph)> {
         external_deps: all_external_deps,
     };
 
-    let info = build_repo_info(repo_path, &lang, &graph);
+    let mut info = build_repo_info(source_tree, &lang, &graph, files.len());
+    if let Some(idx) = index {
+        let name = idx
+            .source
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("index");
+        info.attributes.push(RepoAttribute {
+            label: "Index".to_string(),
+            value: format!("{} ({} files)", name, idx.files.len()),
+            link: Some(idx.source.to_string_lossy().to_string()),
+        });
+    }
     eprintln!("[fode] done: {} entities, {} relations", info.total_entities, graph.relations.len());
     Some((info, graph))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GROUPED_GO: &str = include_str!("../testdata/go/grouped.go");
+    const MEMBERS_GO: &str = include_str!("../testdata/go/members.go");
+    const METHODS_GO: &str = include_str!("../testdata/go/methods.go");
+    const LOCALS_GO: &str = include_str!("../testdata/go/locals.go");
+    const STORE_GO: &str = include_str!("../testdata/go/store/store.go");
+    const LOCALS_RS: &str = include_str!("../testdata/rust/locals.rs");
+    const MODELS_PY: &str = include_str!("../testdata/python/models.py");
+    const EMBED_GO: &str = include_str!("../testdata/go/embed.go");
+    const TRAITS_RS: &str = include_str!("../testdata/rust/traits.rs");
+    const SHAPES_TS: &str = include_str!("../testdata/js/shapes.ts");
+    const HANDLERS_JS: &str = include_str!("../testdata/js/handlers.js");
+
+    /// Parse fixture files as if they were one repo and resolve references.
+    /// `imports` maps Go import qualifiers to repo-relative directories.
+    fn build_graph(
+        lang: DetectedLanguage,
+        files: &[(&str, &str)],
+        imports: &[(&str, &str)],
+    ) -> (Vec<Entity>, Vec<Relation>) {
+        let parsed: Vec<(&str, &str, Tree)> = files
+            .iter()
+            .map(|(path, source)| {
+                let tree = parse_file(source, lang.tree_sitter_language()).unwrap();
+                (*path, *source, tree)
+            })
+            .collect();
+
+        let mut entities = Vec::new();
+        let mut ranges = Vec::new();
+        for (path, source, tree) in &parsed {
+            let start = entities.len();
+            entities.extend(match lang {
+                DetectedLanguage::Go => {
+                    extract_entities_go(source, tree, path, &get_go_package(source, tree))
+                }
+                _ => extract_entities_generic(source, tree, path, &lang),
+            });
+            ranges.push(start..entities.len());
+        }
+
+        let name_to_ids = build_name_index(&entities);
+        let entity_meta: HashMap<String, EntityMeta> = entities
+            .iter()
+            .map(|e| (e.id.clone(), EntityMeta { pkg_dir: file_dir(&e.file) }))
+            .collect();
+        let import_dirs: ImportMap = imports
+            .iter()
+            .map(|(q, dir)| (q.to_string(), dir.to_string()))
+            .collect();
+
+        let type_ids = build_type_index(&entities);
+        let mut relations = link_owned_entities(&entities);
+        let mut all_references = Vec::new();
+        for ((path, source, tree), range) in parsed.iter().zip(ranges) {
+            let bases = extract_type_bases(source, tree, &lang);
+            relations.extend(link_type_bases(&bases, &lang, &file_dir(path), &import_dirs, &type_ids));
+            let (found, references) = extract_references(
+                source,
+                tree,
+                &lang,
+                &entities[range.clone()],
+                &name_to_ids,
+                &entity_meta,
+                &import_dirs,
+                &file_dir(path),
+            );
+            relations.extend(found);
+            all_references.extend(range.zip(references));
+        }
+        for (i, references) in all_references {
+            entities[i].references = references;
+        }
+        (entities, relations)
+    }
+
+    fn build_go(source: &str) -> (Vec<Entity>, Vec<Relation>) {
+        build_graph(DetectedLanguage::Go, &[("pkg/fixture.go", source)], &[])
+    }
+
+    fn entity<'a>(entities: &'a [Entity], name: &str) -> &'a Entity {
+        entities
+            .iter()
+            .find(|e| e.name == name)
+            .unwrap_or_else(|| panic!("no entity named {}", name))
+    }
+
+    fn has_relation(relations: &[Relation], from: &Entity, to: &Entity, kind: RelationKind) -> bool {
+        relations
+            .iter()
+            .any(|r| r.from_id == from.id && r.to_id == to.id && r.kind == kind)
+    }
+
+    #[test]
+    fn struct_fields_are_owned_by_their_type() {
+        let (entities, relations) = build_go(MEMBERS_GO);
+
+        let cache = entity(&entities, "Cache");
+        let timeout = entity(&entities, "Cache.Timeout");
+        assert_eq!(timeout.kind, EntityKind::Field);
+        assert_eq!(timeout.owner.as_deref(), Some("Cache"));
+        assert_eq!(timeout.signature, "Timeout int");
+        assert_eq!(timeout.doc_comment, "// Timeout is how long entries live.");
+        assert!(has_relation(&relations, cache, timeout, RelationKind::Contains));
+
+        // Embedded fields are named after their type
+        let mutex = entity(&entities, "Cache.Mutex");
+        assert_eq!(mutex.owner.as_deref(), Some("Cache"));
+        assert!(has_relation(&relations, cache, mutex, RelationKind::Contains));
+    }
+
+    #[test]
+    fn interface_methods_are_owned_by_their_interface() {
+        let (entities, relations) = build_go(MEMBERS_GO);
+
+        let store = entity(&entities, "Store");
+        for name in ["Store.Load", "Store.Save"] {
+            let method = entity(&entities, name);
+            assert_eq!(method.kind, EntityKind::Method);
+            assert_eq!(method.owner.as_deref(), Some("Store"));
+            assert!(has_relation(&relations, store, method, RelationKind::Contains));
+        }
+        assert_eq!(entity(&entities, "Store.Load").doc_comment, "// Load reads an entry.");
+    }
+
+    #[test]
+    fn typed_receiver_selectors_link_to_members() {
+        let (entities, relations) = build_go(MEMBERS_GO);
+
+        let evict = entity(&entities, "Cache.Evict");
+        let timeout = entity(&entities, "Cache.Timeout");
+        assert!(has_relation(&relations, evict, timeout, RelationKind::References));
+        assert!(has_relation(&relations, evict, entity(&entities, "Cache.items"), RelationKind::References));
+        // `c.onEvict(key)` is a call through the receiver, resolved to the member
+        let on_evict = entity(&entities, "Cache.onEvict");
+        assert!(has_relation(&relations, evict, on_evict, RelationKind::Calls));
+        // Same-named members of other types are not linked
+        assert!(!relations
+            .iter()
+            .any(|r| r.from_id == evict.id && r.to_id == entity(&entities, "Store.Load").id));
+    }
+
+    #[test]
+    fn grouped_type_specs_are_scoped_per_spec() {
+        let (entities, _) = build_go(GROUPED_GO);
+
+        let point = entity(&entities, "Point");
+        assert_eq!(point.kind, EntityKind::Struct);
+        assert!(point.source.starts_with("Point struct {"));
+        assert!(!point.source.contains("Drawer"));
+        assert_eq!((point.line, point.end_line), (8, 10));
+        assert_eq!(point.doc_comment, "// Point is a 2D coordinate.");
+
+        let drawer = entity(&entities, "Drawer");
+        assert_eq!(drawer.kind, EntityKind::Interface);
+        assert_eq!((drawer.line, drawer.end_line), (13, 15));
+        assert_eq!(drawer.doc_comment, "// Drawer renders shapes.");
+
+        let label = entity(&entities, "Label");
+        assert_eq!(label.kind, EntityKind::TypeAlias);
+        assert_eq!(label.source, "Label string");
+        assert_eq!(label.doc_comment, "");
+    }
+
+    #[test]
+    fn single_type_declaration_keeps_full_source() {
+        let (entities, _) = build_go(GROUPED_GO);
+
+        let single = entity(&entities, "Single");
+        assert!(single.source.starts_with("type Single struct {"));
+        assert_eq!((single.line, single.end_line), (21, 23));
+        assert_eq!(single.doc_comment, "// Single is declared on its own.");
+    }
+
+    #[test]
+    fn grouped_consts_have_own_docs_and_owner() {
+        let (entities, _) = build_go(GROUPED_GO);
+
+        let red = entity(&entities, "Red");
+        assert_eq!(red.source, "Red Color = iota");
+        assert_eq!(red.doc_comment, "// Red is the default color.");
+        assert_eq!(red.owner.as_deref(), Some("Color"));
+
+        // Trailing comments stay with their own spec
+        assert_eq!(entity(&entities, "Green").doc_comment, "");
+        assert_eq!(entity(&entities, "Blue").doc_comment, "");
+        assert_eq!(entity(&entities, "Blue").owner.as_deref(), Some("Color"));
+
+        let max_size = entity(&entities, "MaxSize");
+        assert_eq!(max_size.line, 32);
+        assert_eq!(max_size.owner, None);
+
+        let version = entity(&entities, "Version");
+        assert_eq!(version.kind, EntityKind::Constant);
+        assert_eq!(version.doc_comment, "");
+    }
+
+    #[test]
+    fn grouped_vars_are_extracted() {
+        let (entities, _) = build_go(GROUPED_GO);
+
+        let drawer = entity(&entities, "DefaultDrawer");
+        assert_eq!(drawer.kind, EntityKind::Variable);
+        assert_eq!(drawer.line, 41);
+        assert_eq!(drawer.doc_comment, "// DefaultDrawer is used when none is configured.");
+
+        for name in ["origin", "limit"] {
+            let var = entity(&entities, name);
+            assert_eq!(var.kind, EntityKind::Variable);
+            assert_eq!(var.line, 42);
+        }
+    }
+
+    #[test]
+    fn grouped_specs_reference_only_their_own_body() {
+        let (entities, relations) = build_go(GROUPED_GO);
+
+        let point = entity(&entities, "Point");
+        let drawer = entity(&entities, "Drawer");
+        let label = entity(&entities, "Label");
+        let color = entity(&entities, "Color");
+
+        assert!(has_relation(&relations, drawer, point, RelationKind::References));
+        assert!(!has_relation(&relations, point, drawer, RelationKind::References));
+        assert!(!relations.iter().any(|r| r.from_id == label.id));
+
+        let red = entity(&entities, "Red");
+        assert!(has_relation(&relations, color, red, RelationKind::Contains));
+        assert!(has_relation(&relations, point, entity(&entities, "Point.X"), RelationKind::Contains));
+    }
+
+    #[test]
+    fn methods_are_named_after_receiver_type() {
+        let (entities, relations) = build_go(METHODS_GO);
+
+        let server = entity(&entities, "Server");
+        let start = entity(&entities, "Server.Start");
+        assert_eq!(start.id, "pkg/fixture.go::method::Server.Start");
+        assert_eq!(start.owner.as_deref(), Some("Server"));
+        assert_eq!(start.receiver_kind, Some(ReceiverKind::Pointer));
+        assert_eq!(start.doc_comment, "// Start begins listening.");
+        assert!(has_relation(&relations, server, start, RelationKind::Contains));
+
+        // Generic receivers are stripped to the base type
+        let len = entity(&entities, "List.Len");
+        assert_eq!(len.receiver_kind, Some(ReceiverKind::Value));
+        assert!(has_relation(&relations, entity(&entities, "List"), len, RelationKind::Contains));
+
+        // Unnamed receivers still resolve their type
+        assert_eq!(entity(&entities, "Server.Close").owner.as_deref(), Some("Server"));
+    }
+
+    #[test]
+    fn receiver_selectors_resolve_to_members() {
+        let (entities, relations) = build_go(METHODS_GO);
+
+        let start = entity(&entities, "Server.Start");
+        let listen = entity(&entities, "Server.listen");
+        assert!(has_relation(&relations, start, listen, RelationKind::Calls));
+        assert!(has_relation(&relations, start, entity(&entities, "Server.addr"), RelationKind::References));
+        assert!(has_relation(
+            &relations,
+            entity(&entities, "List.Len"),
+            entity(&entities, "List.items"),
+            RelationKind::References,
+        ));
+    }
+
+    #[test]
+    fn go_method_calls_resolve_through_local_types() {
+        let (entities, relations) = build_graph(
+            DetectedLanguage::Go,
+            &[("app/locals.go", LOCALS_GO), ("store/store.go", STORE_GO)],
+            &[("store", "store")],
+        );
+
+        let cache_get = entity(&entities, "Cache.Get");
+        let start = entity(&entities, "Server.Start");
+        let stop = entity(&entities, "Server.Stop");
+
+        // Parameter of a cross-package type
+        assert!(has_relation(&relations, entity(&entities, "Lookup"), cache_get, RelationKind::Calls));
+
+        let run = entity(&entities, "Run");
+        assert!(has_relation(&relations, run, entity(&entities, "NewCache"), RelationKind::Calls));
+        assert!(has_relation(&relations, run, cache_get, RelationKind::Calls));
+        assert!(has_relation(&relations, run, start, RelationKind::Calls));
+        assert!(has_relation(&relations, run, stop, RelationKind::Calls));
+
+        // A same-named package function is not a method call target
+        let get = entity(&entities, "Get");
+        assert!(!has_relation(&relations, run, get, RelationKind::Calls));
+        let unknown = entity(&entities, "Unknown");
+        assert!(!relations.iter().any(|r| r.from_id == unknown.id && r.to_id == get.id));
+    }
+
+    #[test]
+    fn resolved_names_are_located_in_the_source() {
+        let (entities, _) = build_graph(
+            DetectedLanguage::Go,
+            &[("app/locals.go", LOCALS_GO), ("store/store.go", STORE_GO)],
+            &[("store", "store")],
+        );
+
+        let run = entity(&entities, "Run");
+        let located: Vec<(&str, &str)> = run
+            .references
+            .iter()
+            .map(|r| (&run.source[r.start..r.end], r.target_id.as_str()))
+            .collect();
+        for (name, target) in [
+            ("NewCache", "NewCache"),
+            ("Get", "Cache.Get"),
+            ("Start", "Server.Start"),
+            ("Stop", "Server.Stop"),
+            ("Server", "Server"),
+            ("NewServer", "NewServer"),
+        ] {
+            let target = &entity(&entities, target).id;
+            assert!(located.contains(&(name, target.as_str())), "{} -> {} in {:?}", name, target, located);
+        }
+        // Every occurrence, not just the first
+        assert_eq!(located.iter().filter(|(name, _)| *name == "Start").count(), 2);
+        assert!(run.references.windows(2).all(|w| w[0].end <= w[1].start));
+    }
+
+    #[test]
+    fn rust_method_calls_resolve_through_local_types() {
+        let (entities, relations) =
+            build_graph(DetectedLanguage::Rust, &[("src/locals.rs", LOCALS_RS)], &[]);
+
+        let store = entity(&entities, "Store");
+        let store_len = entity(&entities, "Store.len");
+        let index_len = entity(&entities, "Index.len");
+        assert_eq!(store_len.kind, EntityKind::Method);
+        assert!(has_relation(&relations, store, store_len, RelationKind::Contains));
+
+        // `self` is the impl type
+        let is_empty = entity(&entities, "Store.is_empty");
+        assert!(has_relation(&relations, is_empty, store_len, RelationKind::Calls));
+        assert!(!has_relation(&relations, is_empty, index_len, RelationKind::Calls));
+
+        // Parameter types
+        let count = entity(&entities, "count");
+        assert!(has_relation(&relations, count, store_len, RelationKind::Calls));
+        assert!(!has_relation(&relations, count, index_len, RelationKind::Calls));
+
+        // `let` from a constructor and from a type annotation
+        let build = entity(&entities, "build");
+        assert!(has_relation(&relations, build, entity(&entities, "Store.new"), RelationKind::Calls));
+        assert!(has_relation(&relations, build, store_len, RelationKind::Calls));
+        assert!(has_relation(&relations, build, index_len, RelationKind::Calls));
+
+        // Calls and references inside a method chain's receiver
+        let total = entity(&entities, "total");
+        assert!(has_relation(&relations, total, entity(&entities, "is_small"), RelationKind::Calls));
+        assert!(has_relation(&relations, total, count, RelationKind::References));
+    }
+
+    #[test]
+    fn python_methods_and_nested_classes_are_owned() {
+        let (entities, relations) =
+            build_graph(DetectedLanguage::Python, &[("app/models.py", MODELS_PY)], &[]);
+
+        let user = entity(&entities, "User");
+        let meta = entity(&entities, "User.Meta");
+        let greet = entity(&entities, "User.greet");
+        assert_eq!(meta.kind, EntityKind::Class);
+        assert_eq!(greet.kind, EntityKind::Method);
+        assert_eq!(greet.owner.as_deref(), Some("User"));
+        assert!(has_relation(&relations, user, meta, RelationKind::Contains));
+        assert!(has_relation(&relations, user, greet, RelationKind::Contains));
+        assert!(has_relation(&relations, meta, entity(&entities, "User.Meta.describe"), RelationKind::Contains));
+
+        // Nested helpers stay plain functions
+        assert_eq!(entity(&entities, "fmt").kind, EntityKind::Function);
+
+        // `self.x` resolves to the owning class
+        let display_name = entity(&entities, "User.display_name");
+        assert!(has_relation(&relations, greet, display_name, RelationKind::References));
+        assert!(has_relation(
+            &relations,
+            entity(&entities, "list_users"),
+            entity(&entities, "User.create"),
+            RelationKind::Calls,
+        ));
+    }
+
+    #[test]
+    fn python_decorators_and_docstrings() {
+        let (entities, _) =
+            build_graph(DetectedLanguage::Python, &[("app/models.py", MODELS_PY)], &[]);
+
+        let display_name = entity(&entities, "User.display_name");
+        assert_eq!(display_name.decorators, vec!["property"]);
+        assert_eq!(display_name.doc_comment, "Name shown in the UI.");
+        assert!(display_name.source.starts_with("@property"));
+        assert_eq!(display_name.signature.trim(), "def display_name(self):");
+
+        assert_eq!(entity(&entities, "User.create").decorators, vec!["staticmethod"]);
+        assert_eq!(entity(&entities, "list_users").decorators, vec!["route(\"/users\")"]);
+        assert_eq!(
+            entity(&entities, "User").doc_comment,
+            "A registered user.\n\nUsers own sessions."
+        );
+        assert!(entity(&entities, "User.greet").decorators.is_empty());
+    }
+
+    #[test]
+    fn supertypes_become_inheritance_relations() {
+        // Python: positional bases only, not `metaclass=`
+        let (entities, relations) =
+            build_graph(DetectedLanguage::Python, &[("app/models.py", MODELS_PY)], &[]);
+        let admin = entity(&entities, "Admin");
+        assert!(has_relation(&relations, admin, entity(&entities, "User"), RelationKind::Extends));
+        let admin_bases = relations.iter().filter(|r| r.from_id == admin.id && r.kind == RelationKind::Extends);
+        assert_eq!(admin_bases.count(), 1);
+
+        // Go: struct and interface embedding, through imports but not into external packages
+        let (entities, relations) = build_graph(
+            DetectedLanguage::Go,
+            &[("shapes/embed.go", EMBED_GO), ("store/store.go", STORE_GO)],
+            &[("store", "store")],
+        );
+        let circle = entity(&entities, "Circle");
+        assert!(has_relation(&relations, circle, entity(&entities, "Base"), RelationKind::Embeds));
+        assert!(has_relation(&relations, circle, entity(&entities, "Cache"), RelationKind::Embeds));
+        assert!(has_relation(&relations, entity(&entities, "Shape"), entity(&entities, "Named"), RelationKind::Embeds));
+        assert_eq!(relations.iter().filter(|r| r.kind == RelationKind::Embeds).count(), 3);
+
+        // Rust: trait impls and supertraits
+        let (entities, relations) =
+            build_graph(DetectedLanguage::Rust, &[("src/traits.rs", TRAITS_RS)], &[]);
+        let circle = entity(&entities, "Circle");
+        let named = entity(&entities, "Named");
+        let shape = entity(&entities, "Shape");
+        assert!(has_relation(&relations, circle, named, RelationKind::Implements));
+        assert!(has_relation(&relations, circle, shape, RelationKind::Implements));
+        assert!(has_relation(&relations, shape, named, RelationKind::Extends));
+
+        // TypeScript: `extends` and `implements` clauses
+        let (entities, relations) =
+            build_graph(DetectedLanguage::JavaScript, &[("src/shapes.ts", SHAPES_TS)], &[]);
+        let circle = entity(&entities, "Circle");
+        assert!(has_relation(&relations, circle, entity(&entities, "Shape"), RelationKind::Extends));
+        assert!(has_relation(&relations, circle, entity(&entities, "Named"), RelationKind::Implements));
+        assert!(has_relation(&relations, circle, entity(&entities, "Sized"), RelationKind::Implements));
+    }
+
+    #[test]
+    fn js_bindings_and_class_members() {
+        let (entities, relations) =
+            build_graph(DetectedLanguage::JavaScript, &[("src/handlers.js", HANDLERS_JS)], &[]);
+
+        // Function-valued bindings are functions; the name sits on the declarator
+        let list_users = entity(&entities, "listUsers");
+        assert_eq!(list_users.kind, EntityKind::Function);
+        assert_eq!(list_users.doc_comment, "// Handles GET /users.");
+        assert!(list_users.source.starts_with("export const listUsers"));
+        assert_eq!(entity(&entities, "format").kind, EntityKind::Function);
+        assert_eq!(entity(&entities, "TIMEOUT").kind, EntityKind::Variable);
+        assert_eq!(entity(&entities, "RETRIES").source, "RETRIES = 3");
+        assert!(!entities.iter().any(|e| e.name == "local" || e.name == "rows"));
+
+        // Class members are owned
+        let router = entity(&entities, "Router");
+        let size = entity(&entities, "Router.size");
+        let add = entity(&entities, "Router.add");
+        assert_eq!(size.kind, EntityKind::Method);
+        assert_eq!(entity(&entities, "Router.onError").kind, EntityKind::Method);
+        assert_eq!(entity(&entities, "Router.routes").kind, EntityKind::Field);
+        assert!(has_relation(&relations, router, add, RelationKind::Contains));
+        assert!(has_relation(&relations, add, size, RelationKind::References));
+
+        // Bindings on one line don't reference each other
+        let timeout = entity(&entities, "TIMEOUT");
+        assert!(!relations.iter().any(|r| r.from_id == timeout.id));
+    }
+
+    #[test]
+    fn js_exports_are_public() {
+        let (entities, _) =
+            build_graph(DetectedLanguage::JavaScript, &[("src/handlers.js", HANDLERS_JS)], &[]);
+        let exported: HashSet<&str> = entities
+            .iter()
+            .filter(|e| e.visibility == Visibility::Public)
+            .map(|e| e.name.as_str())
+            .collect();
+        assert_eq!(
+            exported,
+            HashSet::from([
+                "listUsers", "format", "internal", "Router",
+                "Router.routes", "Router.onError", "Router.size", "Router.add",
+            ])
+        );
+    }
+
+    #[test]
+    fn visibility_follows_language_rules() {
+        let vis = |entities: &[Entity], name: &str| entity(entities, name).visibility;
+
+        let (entities, _) = build_go(METHODS_GO);
+        assert_eq!(vis(&entities, "Server.Start"), Visibility::Public);
+        assert_eq!(vis(&entities, "Server.listen"), Visibility::Private);
+        assert_eq!(vis(&entities, "Server.addr"), Visibility::Private);
+
+        let (entities, _) =
+            build_graph(DetectedLanguage::Rust, &[("src/traits.rs", TRAITS_RS)], &[]);
+        assert_eq!(vis(&entities, "Circle"), Visibility::Public);
+        assert_eq!(vis(&entities, "unit"), Visibility::Internal);
+        assert_eq!(vis(&entities, "Circle.diameter"), Visibility::Public);
+        assert_eq!(vis(&entities, "Circle.scale"), Visibility::Private);
+        // Trait impl methods are as visible as the trait
+        assert_eq!(vis(&entities, "Circle.area"), Visibility::Public);
+
+        let (entities, _) =
+            build_graph(DetectedLanguage::Python, &[("app/models.py", MODELS_PY)], &[]);
+        assert_eq!(vis(&entities, "User"), Visibility::Public);
+        assert_eq!(vis(&entities, "User.__init__"), Visibility::Public);
+        assert_eq!(vis(&entities, "User._touch"), Visibility::Private);
+        // Not listed in `__all__`
+        assert_eq!(vis(&entities, "list_users"), Visibility::Private);
+    }
+
+    #[test]
+    fn test_functions_are_tagged() {
+        let tests = |entities: &[Entity]| -> Vec<String> {
+            entities.iter().filter(|e| e.is_test).map(|e| e.name.clone()).collect()
+        };
+
+        let rust = "fn helper() {}\n\n#[cfg(test)]\nmod tests {\n    #[test]\n    fn adds() {}\n\n    \
+                    #[tokio::test]\n    // async\n    async fn fetches() {}\n\n    #[allow(dead_code)]\n    fn fixture() {}\n}\n";
+        let (entities, _) = build_graph(DetectedLanguage::Rust, &[("src/lib.rs", rust)], &[]);
+        assert_eq!(tests(&entities), vec!["adds", "fetches"]);
+
+        let python = "def test_add():\n    pass\n\ndef helper():\n    pass\n\n\
+                      class TestUser:\n    def test_name(self):\n        pass\n\n    def setup(self):\n        pass\n";
+        let (entities, _) = build_graph(DetectedLanguage::Python, &[("tests/test_user.py", python)], &[]);
+        assert_eq!(tests(&entities), vec!["test_add", "TestUser.test_name"]);
+        // Outside test files only `Test*` classes count
+        let (entities, _) = build_graph(DetectedLanguage::Python, &[("app/user.py", python)], &[]);
+        assert_eq!(tests(&entities), vec!["TestUser.test_name"]);
+    }
+}
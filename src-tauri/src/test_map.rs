@@ -0,0 +1,252 @@
+//! Which production entities each test reaches through calls and
+//! references, and the tests to run for a set of changed entities.
+
+use crate::parser::{Entity, EntityGraph, RelationKind};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// A test and the entities of interest it reaches.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestTarget {
+    pub id: String,
+    pub name: String,
+    pub file: String,
+    pub line: usize,
+    pub package: String,
+    /// IDs of the entities this test reaches, e.g. the changed ones.
+    pub reaches: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestSelection {
+    /// Entities the selection was made for.
+    pub changed: Vec<String>,
+    /// Tests reaching at least one changed entity, most reaching first.
+    pub tests: Vec<TestTarget>,
+    /// Changed production entities no test reaches.
+    pub untested: Vec<String>,
+}
+
+/// Calls/References adjacency, keyed by target when `incoming`; self-loops dropped.
+fn edges(graph: &EntityGraph, incoming: bool) -> HashMap<&str, Vec<&str>> {
+    let mut map: HashMap<&str, Vec<&str>> = HashMap::new();
+    for r in &graph.relations {
+        if matches!(r.kind, RelationKind::Calls | RelationKind::References) && r.from_id != r.to_id {
+            let (key, value) = if incoming { (&r.to_id, &r.from_id) } else { (&r.from_id, &r.to_id) };
+            map.entry(key.as_str()).or_default().push(value.as_str());
+        }
+    }
+    map
+}
+
+fn reachable<'a>(start: &'a str, edges: &HashMap<&'a str, Vec<&'a str>>) -> HashSet<&'a str> {
+    let mut seen: HashSet<&str> = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(id) = queue.pop_front() {
+        for &next in edges.get(id).into_iter().flatten() {
+            if seen.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    seen.remove(start);
+    seen
+}
+
+fn target(test: &Entity, reaches: Vec<String>) -> TestTarget {
+    TestTarget {
+        id: test.id.clone(),
+        name: test.name.clone(),
+        file: test.file.clone(),
+        line: test.line,
+        package: test.package.clone(),
+        reaches,
+    }
+}
+
+/// Every test with the production entities it reaches, transitively through
+/// helpers and other test code.
+pub fn test_map(graph: &EntityGraph) -> Vec<TestTarget> {
+    let by_id: HashMap<&str, &Entity> = graph.entities.iter().map(|e| (e.id.as_str(), e)).collect();
+    let outgoing = edges(graph, false);
+    graph
+        .entities
+        .iter()
+        .filter(|e| e.is_test)
+        .map(|test| {
+            let mut reaches: Vec<String> = reachable(&test.id, &outgoing)
+                .into_iter()
+                .filter(|id| by_id.get(id).is_some_and(|e| !e.is_test_code()))
+                .map(str::to_string)
+                .collect();
+            reaches.sort();
+            target(test, reaches)
+        })
+        .collect()
+}
+
+/// Tests that reach `entity_id` through calls and references.
+pub fn tests_reaching(graph: &EntityGraph, entity_id: &str) -> Vec<TestTarget> {
+    select_tests(graph, &[entity_id.to_string()]).tests
+}
+
+/// The tests worth running for `changed`: those from which some changed
+/// entity is reachable. Walks incoming edges from each changed entity, so
+/// only the affected part of the graph is visited.
+pub fn select_tests(graph: &EntityGraph, changed: &[String]) -> TestSelection {
+    let by_id: HashMap<&str, &Entity> = graph.entities.iter().map(|e| (e.id.as_str(), e)).collect();
+    let incoming = edges(graph, true);
+
+    let mut reached: HashMap<&str, Vec<String>> = HashMap::new();
+    let mut untested = Vec::new();
+    for id in changed {
+        let Some(entity) = by_id.get(id.as_str()) else {
+            continue;
+        };
+        let mut tests: Vec<&str> = reachable(id, &incoming)
+            .into_iter()
+            .filter(|t| by_id.get(t).is_some_and(|e| e.is_test))
+            .collect();
+        // A changed test selects itself
+        if entity.is_test {
+            tests.push(id);
+        }
+        if tests.is_empty() && !entity.is_test_code() {
+            untested.push(id.clone());
+        }
+        for test in tests {
+            reached.entry(test).or_default().push(id.clone());
+        }
+    }
+
+    let mut tests: Vec<TestTarget> = reached
+        .into_iter()
+        .filter_map(|(id, reaches)| by_id.get(id).map(|test| target(test, reaches)))
+        .collect();
+    tests.sort_by(|a, b| {
+        b.reaches
+            .len()
+            .cmp(&a.reaches.len())
+            .then_with(|| (&a.file, a.line).cmp(&(&b.file, b.line)))
+    });
+    TestSelection { changed: changed.to_vec(), tests, untested }
+}
+
+/// Plain-text report: the selected tests with the changed entities each
+/// reaches, then the changed entities left untested.
+pub fn render_report(selection: &TestSelection) -> String {
+    let name = |id: &str| id.rsplit("::").next().unwrap_or(id).to_string();
+    let mut out = format!(
+        "{} tests for {} changed entities, {} untested\n",
+        selection.tests.len(),
+        selection.changed.len(),
+        selection.untested.len()
+    );
+    if !selection.tests.is_empty() {
+        out.push_str("\ntests\n");
+        for t in &selection.tests {
+            let reaches: Vec<String> = t.reaches.iter().map(|id| name(id)).collect();
+            out.push_str(&format!("  {}  {}:{}  ({})\n", t.name, t.file, t.line, reaches.join(", ")));
+        }
+    }
+    if !selection.untested.is_empty() {
+        out.push_str("\nuntested\n");
+        for id in &selection.untested {
+            out.push_str(&format!("  {}\n", id));
+        }
+    }
+    out
+}
+
+/// Plain-text test map: each test with the production entities it reaches.
+pub fn render_map(map: &[TestTarget]) -> String {
+    let name = |id: &str| id.rsplit("::").next().unwrap_or(id).to_string();
+    let mut out = format!("{} tests\n", map.len());
+    for t in map {
+        let reaches: Vec<String> = t.reaches.iter().map(|id| name(id)).collect();
+        out.push_str(&format!("\n{}  {}:{}\n", t.name, t.file, t.line));
+        if reaches.is_empty() {
+            out.push_str("  (reaches nothing)\n");
+        }
+        for r in reaches {
+            out.push_str(&format!("  {}\n", r));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{self, DetectedLanguage, Relation};
+
+    const STORE_GO: &str = "package store
+
+func Get(k string) string { return lookup(k) }
+
+func lookup(k string) string { return k }
+
+func Put(k string) {}
+
+func Unused() {}
+";
+
+    const STORE_TEST_GO: &str = "package store
+
+import \"testing\"
+
+func TestGet(t *testing.T) { check(t) }
+
+func TestPut(t *testing.T) { Put(\"a\") }
+
+func check(t *testing.T) { Get(\"a\") }
+
+func BenchmarkGet(b *testing.B) { Get(\"b\") }
+
+func Testhelper() {}
+";
+
+    fn go_graph() -> EntityGraph {
+        let mut entities = Vec::new();
+        for (file, source) in [("store/store.go", STORE_GO), ("store/store_test.go", STORE_TEST_GO)] {
+            let tree = parser::parse_file(source, DetectedLanguage::Go.tree_sitter_language()).unwrap();
+            entities.extend(parser::extract_entities_go(source, &tree, file, "store"));
+        }
+        let id = |name: &str| entities.iter().find(|e| e.name == name).unwrap().id.clone();
+        let calls = |from: &str, to: &str| Relation { from_id: id(from), to_id: id(to), kind: RelationKind::Calls };
+        let relations = vec![
+            calls("Get", "lookup"),
+            calls("TestGet", "check"),
+            calls("check", "Get"),
+            calls("TestPut", "Put"),
+            calls("BenchmarkGet", "Get"),
+        ];
+        EntityGraph { entities, relations, external_deps: Default::default() }
+    }
+
+    #[test]
+    fn go_tests_are_tagged_and_mapped() {
+        let graph = go_graph();
+        let tests: Vec<&str> = graph.entities.iter().filter(|e| e.is_test).map(|e| e.name.as_str()).collect();
+        assert_eq!(tests, vec!["TestGet", "TestPut", "BenchmarkGet"]);
+
+        let map = test_map(&graph);
+        let test_get = map.iter().find(|t| t.name == "TestGet").unwrap();
+        let reached: Vec<&str> = test_get.reaches.iter().map(|id| id.rsplit("::").next().unwrap()).collect();
+        assert_eq!(reached, vec!["Get", "lookup"]);
+    }
+
+    #[test]
+    fn selection_covers_changed_entities() {
+        let graph = go_graph();
+        let id = |name: &str| graph.entities.iter().find(|e| e.name == name).unwrap().id.clone();
+
+        let selection = select_tests(&graph, &[id("lookup"), id("Unused")]);
+        let names: Vec<&str> = selection.tests.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["TestGet", "BenchmarkGet"]);
+        assert_eq!(selection.untested, vec![id("Unused")]);
+
+        let names: Vec<String> = tests_reaching(&graph, &id("Put")).into_iter().map(|t| t.name).collect();
+        assert_eq!(names, vec!["TestPut"]);
+    }
+}
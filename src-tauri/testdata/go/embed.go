@@ -0,0 +1,30 @@
+package shapes
+
+import (
+	"sync"
+
+	"example.com/app/store"
+)
+
+// Named has a name.
+type Named interface {
+	Name() string
+}
+
+// Shape is a named area.
+type Shape interface {
+	Named
+	Area() float64
+}
+
+type Base struct {
+	name string
+}
+
+// Circle embeds Base and a remote store.Cache.
+type Circle struct {
+	*Base
+	store.Cache
+	sync.Mutex
+	radius float64
+}
@@ -0,0 +1,47 @@
+package shapes
+
+import "fmt"
+
+// Shape types.
+type (
+	// Point is a 2D coordinate.
+	Point struct {
+		X, Y int
+	}
+
+	// Drawer renders shapes.
+	Drawer interface {
+		Draw(p Point) error
+	}
+
+	Label string
+)
+
+// Single is declared on its own.
+type Single struct {
+	Origin Point
+}
+
+// Colors.
+const (
+	// Red is the default color.
+	Red Color = iota
+	Green // trailing comment
+	Blue
+
+	MaxSize = 64
+)
+
+type Color int
+
+const Version = "1.0"
+
+var (
+	// DefaultDrawer is used when none is configured.
+	DefaultDrawer Drawer
+	origin, limit = Point{}, 10
+)
+
+func (p Point) String() string {
+	return fmt.Sprintf("%d,%d", p.X, p.Y)
+}
@@ -0,0 +1,39 @@
+package app
+
+import "example.com/app/store"
+
+type Server struct{}
+
+func NewServer() *Server {
+	return &Server{}
+}
+
+func (s *Server) Start() {}
+
+func (s Server) Stop() {}
+
+func Get() string {
+	return ""
+}
+
+func Lookup(c *store.Cache) string {
+	return c.Get("a")
+}
+
+func Run() {
+	cache := store.NewCache()
+	cache.Get("b")
+
+	s := &Server{}
+	s.Start()
+
+	var t Server
+	t.Stop()
+
+	u, _ := NewServer(), 1
+	u.Start()
+}
+
+func Unknown(v interface{ Get() string }) string {
+	return v.Get()
+}
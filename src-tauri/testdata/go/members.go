@@ -0,0 +1,26 @@
+package cache
+
+import "sync"
+
+// Cache holds entries in memory.
+type Cache struct {
+	sync.Mutex
+	// Timeout is how long entries live.
+	Timeout int
+	items   map[string]string
+	onEvict func(key string)
+}
+
+// Store persists entries.
+type Store interface {
+	// Load reads an entry.
+	Load(key string) (string, error)
+	Save(key, value string) error
+}
+
+func (c *Cache) Evict(key string) {
+	if c.Timeout > 0 {
+		delete(c.items, key)
+		c.onEvict(key)
+	}
+}
@@ -0,0 +1,24 @@
+package server
+
+type Server struct {
+	addr string
+}
+
+type List[T any] struct {
+	items []T
+}
+
+// Start begins listening.
+func (s *Server) Start() error {
+	return s.listen(s.addr)
+}
+
+func (s *Server) listen(addr string) error {
+	return nil
+}
+
+func (l List[T]) Len() int {
+	return len(l.items)
+}
+
+func (*Server) Close() {}
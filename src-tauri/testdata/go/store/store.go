@@ -0,0 +1,13 @@
+package store
+
+type Cache struct {
+	entries map[string]string
+}
+
+func NewCache() *Cache {
+	return &Cache{entries: map[string]string{}}
+}
+
+func (c *Cache) Get(key string) string {
+	return c.entries[key]
+}
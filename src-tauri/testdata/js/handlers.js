@@ -0,0 +1,41 @@
+import { db } from './db';
+
+const TIMEOUT = 30, RETRIES = 3;
+
+// Handles GET /users.
+export const listUsers = async (req, res) => {
+  const rows = await db.query('users');
+  res.json(rows.map(format));
+};
+
+const format = function (row) {
+  return { id: row.id };
+};
+
+function internal() {
+  const local = () => 1;
+  return local();
+}
+
+export class Router {
+  routes = [];
+  onError = (err) => console.error(err);
+  #secret = 1;
+
+  get size() {
+    return this.routes.length;
+  }
+
+  add(path, handler) {
+    this.routes.push({ path, handler });
+    return this.size;
+  }
+}
+
+class Hidden {
+  run() {
+    return internal();
+  }
+}
+
+module.exports = { format, helper: internal };
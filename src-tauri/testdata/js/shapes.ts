@@ -0,0 +1,14 @@
+class Shape {
+  area() {
+    return 0;
+  }
+}
+
+class Circle extends Shape implements Named, Sized {
+  area() {
+    return 3;
+  }
+}
+
+class Named {}
+class Sized {}
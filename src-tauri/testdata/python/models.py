@@ -0,0 +1,52 @@
+"""Account models."""
+
+from app import route
+
+__all__ = ["User", "Admin"]
+
+
+class User:
+    """A registered user.
+
+    Users own sessions.
+    """
+
+    class Meta:
+        table = "users"
+
+        def describe(self):
+            return self.table
+
+    def __init__(self, name):
+        self.name = name
+
+    @property
+    def display_name(self):
+        """Name shown in the UI."""
+        return self.name.title()
+
+    @staticmethod
+    def create(name):
+        return User(name)
+
+    def greet(self):
+        return "hi " + self.display_name
+
+    def _touch(self):
+        pass
+
+
+@route("/users")
+def list_users():
+    # Helper functions nested in a body are not methods
+    def fmt(user):
+        return user.greet()
+
+    return [fmt(User.create("a"))]
+
+
+class Admin(User, metaclass=Registry):
+    """A user with elevated rights."""
+
+    def greet(self):
+        return "hello " + self.name
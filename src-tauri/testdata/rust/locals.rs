@@ -0,0 +1,43 @@
+pub struct Store {
+    items: Vec<String>,
+}
+
+impl Store {
+    pub fn new() -> Self {
+        Store { items: Vec::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+pub struct Index;
+
+impl Index {
+    pub fn len(&self) -> usize {
+        0
+    }
+}
+
+pub fn count(store: &Store) -> usize {
+    store.len()
+}
+
+pub fn build() -> usize {
+    let s = Store::new();
+    let mut other: Index = Index;
+    s.len() + other.len()
+}
+
+pub fn total(stores: &[Store]) -> usize {
+    stores.iter().map(count).filter(|n| is_small(*n)).sum()
+}
+
+fn is_small(n: usize) -> bool {
+    n < 10
+}
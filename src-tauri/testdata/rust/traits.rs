@@ -0,0 +1,46 @@
+use std::fmt;
+
+pub trait Named {
+    fn name(&self) -> String;
+}
+
+pub trait Shape: Named + fmt::Debug {
+    fn area(&self) -> f64;
+}
+
+#[derive(Debug)]
+pub struct Circle {
+    radius: f64,
+}
+
+impl Named for Circle {
+    fn name(&self) -> String {
+        "circle".to_string()
+    }
+}
+
+impl Shape for Circle {
+    fn area(&self) -> f64 {
+        3.14 * self.radius * self.radius
+    }
+}
+
+impl fmt::Display for Circle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+pub(crate) fn unit() -> Circle {
+    Circle { radius: 1.0 }
+}
+
+impl Circle {
+    pub fn diameter(&self) -> f64 {
+        2.0 * self.radius
+    }
+
+    fn scale(&mut self, by: f64) {
+        self.radius *= by;
+    }
+}
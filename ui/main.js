@@ -6,6 +6,8 @@ const { open } = window.__TAURI__.dialog;
 const $landing = document.getElementById('landing');
 const $main = document.getElementById('main');
 const $openRepoBtn = document.getElementById('open-repo-btn');
+const $openRevInput = document.getElementById('open-rev-input');
+const $repoRevInput = document.getElementById('repo-rev-input');
 const $repoName = document.getElementById('repo-name');
 const $repoAttributes = document.getElementById('repo-attributes');
 const $repoStats = document.getElementById('repo-stats');
@@ -27,6 +29,8 @@ const $graphCanvas = document.getElementById('graph-canvas');
 const $graphTooltip = document.getElementById('graph-tooltip');
 const $graphFilters = document.getElementById('graph-filters');
 const $kindFilters = document.getElementById('kind-filters');
+const $btnUnusedFilter = document.getElementById('btn-unused-filter');
+const $metricSort = document.getElementById('metric-sort');
 const $pkgDropdownBtn = document.getElementById('pkg-dropdown-btn');
 const $pkgDropdownCount = document.getElementById('pkg-dropdown-count');
 const $pkgDropdownMenu = document.getElementById('pkg-dropdown-menu');
@@ -34,9 +38,16 @@ const $pkgDropdownList = document.getElementById('pkg-dropdown-list');
 
 // === State ===
 let repoInfo = null;
+let repoPath = null; // kept to reopen the repo at another git revision
 let searchTimeout = null;
 let currentView = 'grid'; // 'grid' or 'graph'
 let graphState = null; // holds the force simulation state
+let hierarchyOnly = false; // graph shows only type hierarchies
+let visibilityFilter = null; // null = all, else e.g. ['Public', 'Internal']
+let unusedOnly = false; // browse grid: only dead code
+let metricSort = null; // browse grid: metric to sort by, e.g. 'complexity'
+let heatScores = null; // graph heat overlay: entity ID -> hotspot score in 0..1, null = off
+let heatDetails = new Map(); // entity ID -> tooltip line for the heat overlay
 
 // === Helpers ===
 function kindBadge(kind) {
@@ -44,12 +55,14 @@ function kindBadge(kind) {
     Function: 'fn', Method: 'me', Struct: 'st', Interface: 'if',
     TypeAlias: 'ty', Constant: 'co', Variable: 'va', Import: 'im',
     Package: 'pk', Class: 'cl', Enum: 'en', Trait: 'tr', Module: 'mo',
+    Field: 'fd',
   };
   const classes = {
     Function: 'kind-function', Method: 'kind-method', Struct: 'kind-struct',
     Interface: 'kind-interface', TypeAlias: 'kind-type', Constant: 'kind-const',
     Variable: 'kind-var', Import: 'kind-import', Package: 'kind-package',
     Class: 'kind-class', Enum: 'kind-enum', Trait: 'kind-trait', Module: 'kind-module',
+    Field: 'kind-field',
   };
   const label = labels[kind] || '??';
   const cls = classes[kind] || '';
@@ -69,7 +82,11 @@ function showView(name) {
 async function openRepo() {
   const selected = await open({ directory: true, multiple: false, title: 'Open Repository' });
   if (!selected) return;
+  await loadRepo(selected, $openRevInput.value.trim() || null);
+}
 
+// Parse `path` as of git revision `rev` (null for the working tree)
+async function loadRepo(path, rev) {
   showView('main');
   $entityGrid.innerHTML = '<div class="loading">Parsing repository</div>';
   $focusContainer.classList.add('hidden');
@@ -81,9 +98,13 @@ async function openRepo() {
   $btnGridView.classList.add('active');
   $btnGraphView.classList.remove('active');
   $graphFilters.classList.add('hidden');
+  $btnUnusedFilter.classList.remove('hidden');
+  $metricSort.classList.remove('hidden');
 
   try {
-    repoInfo = await invoke('open_repo', { path: selected });
+    repoInfo = await invoke('open_repo', { path, rev });
+    repoPath = path;
+    $repoRevInput.value = rev || '';
     renderRepoHeader(repoInfo);
     await loadBrowseView();
   } catch (err) {
@@ -112,19 +133,29 @@ function renderRepoHeader(info) {
 // === Browse View (grid of all entities) ===
 async function loadBrowseView() {
   try {
-    const entities = await invoke('get_all_entities');
-    renderEntityGrid(entities);
+    const entities = await invoke('get_all_entities', { visibility: visibilityFilter, deadOnly: unusedOnly });
+    if (metricSort) {
+      const report = await invoke('get_metrics', { visibility: visibilityFilter });
+      const byId = new Map(report.entities.map(m => [m.id, m]));
+      const value = e => (byId.get(e.id) || {})[metricSort] || 0;
+      entities.sort((a, b) => value(b) - value(a));
+      renderEntityGrid(entities, value);
+    } else {
+      renderEntityGrid(entities);
+    }
   } catch (err) {
     $entityGrid.innerHTML = `<div class="loading" style="color:var(--red)">Error: ${escapeHtml(String(err))}</div>`;
   }
 }
 
-function renderEntityGrid(entities) {
+// `metricValue`, when given, labels each card with the sorted-by metric
+function renderEntityGrid(entities, metricValue) {
   $entityGrid.innerHTML = entities.map(e => `
     <div class="entity-card" data-id="${escapeHtml(e.id)}" onclick="focusEntity('${escapeHtml(e.id)}')">
       <div class="entity-card-header">
         ${kindBadge(e.kind)}
         <span class="entity-card-name">${escapeHtml(e.name)}</span>
+        ${metricValue ? `<span class="entity-card-metric">${metricValue(e)}</span>` : ''}
         <span class="entity-card-meta">${escapeHtml(e.package)}</span>
       </div>
       <div class="entity-card-sig">${escapeHtml(e.signature)}</div>
@@ -132,6 +163,29 @@ function renderEntityGrid(entities) {
   `).join('');
 }
 
+function setMetricSort(value) {
+  metricSort = value || null;
+  loadBrowseView();
+}
+window.setMetricSort = setMetricSort;
+
+// Applies to search, browse grid, graph and focus view alike
+function setVisibilityFilter(value) {
+  visibilityFilter = value ? value.split(',') : null;
+  loadBrowseView();
+  graphState = null;
+  if (currentView === 'graph') loadGraphView();
+}
+window.setVisibilityFilter = setVisibilityFilter;
+
+// Browse grid only: entities with no callers outside tests and roots
+function toggleUnusedFilter(btn) {
+  unusedOnly = !unusedOnly;
+  btn.classList.toggle('active', unusedOnly);
+  loadBrowseView();
+}
+window.toggleUnusedFilter = toggleUnusedFilter;
+
 // === Search ===
 $searchInput.addEventListener('input', () => {
   clearTimeout(searchTimeout);
@@ -164,7 +218,7 @@ document.addEventListener('click', (e) => {
 
 async function doSearch(query) {
   try {
-    const results = await invoke('search_entities', { query });
+    const results = await invoke('search_entities', { query, visibility: visibilityFilter });
     renderSearchResults(results);
   } catch (err) {
     console.error('Search error:', err);
@@ -199,7 +253,7 @@ async function focusEntity(entityId) {
   $focusContainer.classList.remove('hidden');
 
   try {
-    const focus = await invoke('get_entity_focus', { entityId });
+    const focus = await invoke('get_entity_focus', { entityId, visibility: visibilityFilter });
     renderFocusView(focus);
   } catch (err) {
     $centerEntity.innerHTML = `<div class="loading" style="color:var(--red)">Error: ${escapeHtml(String(err))}</div>`;
@@ -210,19 +264,26 @@ async function focusEntity(entityId) {
 window.focusEntity = focusEntity;
 
 function renderFocusView(focus) {
-  const { center, incoming, same_pkg, same_module, external_deps } = focus;
+  const { center, incoming, same_pkg, same_module, external_deps, ancestors, descendants, tested_by, similar } = focus;
 
   // Center entity
   $centerEntity.innerHTML = `
     <div class="center-entity-header">
       ${kindBadge(center.kind)}
       <span class="center-entity-name">${escapeHtml(center.name)}</span>
+      ${(center.decorators || []).map(d => `<span class="center-entity-decorator">@${escapeHtml(d)}</span>`).join('')}
+      ${center.is_test ? '<span class="center-entity-test">test</span>' : ''}
       <span class="center-entity-meta">${escapeHtml(center.package)} &middot; ${escapeHtml(center.file)}:${center.line}</span>
+      <span class="center-entity-owners" id="entity-owners"></span>
       <button class="back-btn" onclick="showBrowse()">back</button>
     </div>
     ${center.doc_comment ? `<div class="center-entity-doc">${escapeHtml(center.doc_comment)}</div>` : ''}
-    <div class="center-entity-source"><pre>${escapeHtml(center.source)}</pre></div>
+    <div class="center-entity-source"><pre id="entity-source" data-entity-id="${escapeHtml(center.id)}">${escapeHtml(center.source)}</pre></div>
+    <div class="center-entity-history" id="entity-history"></div>
   `;
+  loadEntitySource(center.id);
+  loadEntityHistory(center.id);
+  loadEntityOwners(center.id);
 
   // Left column: incoming references
   $relatedIncoming.innerHTML = '';
@@ -240,8 +301,39 @@ function renderFocusView(focus) {
       `).join('');
   }
 
-  // Right column: three-tier references
+  // Left column: subtypes
+  if (descendants.length > 0) {
+    $relatedIncoming.innerHTML += `<div class="related-section-label">subtypes</div>` +
+      renderHierarchy(descendants);
+  }
+
+  // Left column: tests reaching this entity
+  if (tested_by.length > 0) {
+    $relatedIncoming.innerHTML += `<div class="related-section-label">tested by</div>` +
+      tested_by.map(t => `
+        <div class="compact-sig" onclick="focusEntity('${escapeHtml(t.id)}')">
+          ${escapeHtml(t.name)} <span class="pkg-summary-dir">${escapeHtml(t.file)}:${t.line}</span>
+        </div>
+      `).join('');
+  }
+
+  // Near-duplicate bodies, likely copy-pasted
+  if (similar.length > 0) {
+    $relatedIncoming.innerHTML += `<div class="related-section-label">similar entities</div>` +
+      similar.map(d => `
+        <div class="compact-sig" onclick="focusEntity('${escapeHtml(d.id)}')">
+          ${escapeHtml(d.name)} <span class="similar-score">${Math.round(d.similarity * 100)}%</span>
+          <span class="pkg-summary-dir">${escapeHtml(d.file)}:${d.line}</span>
+        </div>
+      `).join('');
+  }
+
+  // Right column: supertypes, then three-tier references
   $relatedOutgoing.innerHTML = '';
+  if (ancestors.length > 0) {
+    $relatedOutgoing.innerHTML += `<div class="related-section-label">supertypes</div>` +
+      renderHierarchy(ancestors);
+  }
 
   // Tier 1: Same package — compact signature-only entries
   if (same_pkg.length > 0) {
@@ -279,6 +371,137 @@ function renderFocusView(focus) {
   }
 }
 
+// Syntax-colored source from the grammar's highlight query, with every name that resolved
+// to another entity clickable; the plain text stays on failure
+async function loadEntitySource(entityId) {
+  let result;
+  try {
+    result = await invoke('get_entity_source', { entityId, highlight: true });
+  } catch (err) {
+    return;
+  }
+  const $source = document.getElementById('entity-source');
+  if (!$source || $source.dataset.entityId !== entityId) return;
+  $source.innerHTML = renderSourceSpans(result.source, result.highlights, result.references);
+}
+
+// Wrap highlights of `source` in <span class="hl-keyword"> etc., by the first segment of
+// the capture name, and references in links that focus their target. Both lists are
+// ordered and disjoint, in UTF-8 byte offsets as tree-sitter reports them.
+function renderSourceSpans(source, highlights, references) {
+  const bytes = new TextEncoder().encode(source);
+  const decoder = new TextDecoder();
+  const text = (from, to) => escapeHtml(decoder.decode(bytes.subarray(from, to)));
+
+  // Cut the source wherever a highlight or reference starts or ends
+  const cuts = new Set([0, bytes.length]);
+  for (const s of [...highlights, ...references]) {
+    cuts.add(s.start);
+    cuts.add(s.end);
+  }
+  const offsets = [...cuts].sort((a, b) => a - b);
+
+  let html = '';
+  let h = 0;
+  let r = 0;
+  for (let i = 0; i + 1 < offsets.length; i++) {
+    const from = offsets[i];
+    const to = offsets[i + 1];
+    while (h < highlights.length && highlights[h].end <= from) h++;
+    while (r < references.length && references[r].end <= from) r++;
+
+    let piece = text(from, to);
+    const hl = highlights[h];
+    if (hl && hl.start <= from) {
+      piece = `<span class="hl-${hl.capture.split('.')[0]}">${piece}</span>`;
+    }
+    const ref = references[r];
+    if (ref && ref.start <= from) {
+      if (from === ref.start) {
+        html += `<span class="source-ref" title="${escapeHtml(ref.target_id)}" onclick="focusEntity('${escapeHtml(ref.target_id)}')">`;
+      }
+      html += piece;
+      if (to === ref.end) html += '</span>';
+    } else {
+      html += piece;
+    }
+  }
+  return html;
+}
+
+// Git history and blame summary, loaded after the rest of the focus view
+async function loadEntityHistory(entityId) {
+  let history;
+  try {
+    history = await invoke('get_entity_history', { entityId });
+  } catch (err) {
+    return; // not a git repo, or the file is untracked
+  }
+  const $history = document.getElementById('entity-history');
+  if (!$history || history.entity_id !== entityId) return;
+
+  const total = history.blame.reduce((n, b) => n + b.lines, 0) + history.uncommitted_lines;
+  const owners = history.blame.map(b =>
+    `<span class="history-owner" title="${escapeHtml(b.email)}">${escapeHtml(b.author)} ${Math.round(b.lines * 100 / total)}%</span>`
+  );
+  if (history.uncommitted_lines > 0) {
+    owners.push(`<span class="history-owner">${history.uncommitted_lines} uncommitted</span>`);
+  }
+
+  $history.innerHTML = `
+    <div class="related-section-label">history</div>
+    ${owners.length > 0 ? `<div class="history-blame">${owners.join(' &middot; ')}</div>` : ''}
+    ${history.commits.map(c => `
+      <details class="history-commit">
+        <summary>
+          <span class="history-hash">${escapeHtml(c.hash.slice(0, 8))}</span>
+          <span class="history-summary">${escapeHtml(c.summary)}</span>
+          <span class="history-meta">${escapeHtml(c.author)} &middot; ${new Date(c.time * 1000).toLocaleDateString()}</span>
+        </summary>
+        <pre class="history-snippet">${escapeHtml(c.snippet)}</pre>
+      </details>
+    `).join('')}
+  `;
+}
+
+// Primary owners and bus factor of the entity and its package, next to the header meta
+async function loadEntityOwners(entityId) {
+  let owners;
+  try {
+    owners = await invoke('get_entity_owners', { entityId });
+  } catch (err) {
+    return; // not a git repo
+  }
+  const $owners = document.getElementById('entity-owners');
+  if (!$owners || owners.entity.id !== entityId) return;
+
+  const { entity, package: pkg } = owners;
+  const primary = entity.primary_owners.length > 0 ? entity.primary_owners : pkg.primary_owners;
+  if (primary.length === 0) return;
+  const shares = entity.authors.map(a => `${a.author} <${a.email}>: ${a.lines} lines`);
+  const title = [
+    entity.code_owners.length > 0 ? `CODEOWNERS: ${entity.code_owners.join(', ')}` : 'No CODEOWNERS entry',
+    ...shares,
+    `Package ${pkg.package}: bus factor ${pkg.bus_factor}, owners ${pkg.primary_owners.join(', ') || 'none'}`,
+  ].join('\n');
+  $owners.title = title;
+  $owners.innerHTML =
+    `${escapeHtml(primary.join(', '))}` +
+    ` &middot; <span class="${entity.bus_factor === 1 ? 'bus-factor-low' : ''}">bus factor ${entity.bus_factor}</span>` +
+    ` / pkg ${pkg.bus_factor}`;
+}
+
+// Depth-first hierarchy entries rendered as an indented tree
+function renderHierarchy(entries) {
+  return entries.map(h => `
+    <div class="hierarchy-entry" style="padding-left:${(h.depth - 1) * 0.9 + 0.5}rem" onclick="focusEntity('${escapeHtml(h.id)}')">
+      ${kindBadge(h.kind)}
+      <span class="hierarchy-name">${escapeHtml(h.name)}</span>
+      <span class="hierarchy-relation">${escapeHtml(h.relation)}</span>
+    </div>
+  `).join('');
+}
+
 function showBrowse() {
   $focusContainer.classList.add('hidden');
   if (currentView === 'grid') {
@@ -299,6 +522,8 @@ function switchToGrid() {
   $browseContainer.style.display = '';
   $graphContainer.classList.add('hidden');
   $graphFilters.classList.add('hidden');
+  $btnUnusedFilter.classList.remove('hidden');
+  $metricSort.classList.remove('hidden');
 }
 window.switchToGrid = switchToGrid;
 
@@ -309,6 +534,8 @@ function switchToGraph() {
   $browseContainer.style.display = 'none';
   $graphContainer.classList.remove('hidden');
   $graphFilters.classList.remove('hidden');
+  $btnUnusedFilter.classList.add('hidden');
+  $metricSort.classList.add('hidden');
   $focusContainer.classList.add('hidden');
   loadGraphView();
 }
@@ -323,13 +550,14 @@ const KIND_COLORS = {
   interface: '#d29922', type:      '#d29922', const:     '#f85149',
   var:       '#f778ba', import:    '#76e3ea', package:   '#76e3ea',
   class:     '#3fb950', enum:      '#d29922', trait:     '#d29922',
-  module:    '#76e3ea',
+  module:    '#76e3ea', field:     '#8b949e',
 };
 
 const KIND_LABELS = {
   function: 'fn', method: 'me', struct: 'st', interface: 'if',
   type: 'ty', const: 'co', var: 'va', import: 'im',
   package: 'pk', class: 'cl', enum: 'en', trait: 'tr', module: 'mo',
+  field: 'fd',
 };
 
 // Precompute RGB channels for each kind color
@@ -367,7 +595,7 @@ async function loadGraphView() {
     return;
   }
   try {
-    const data = await invoke('get_graph_data');
+    const data = await invoke('get_graph_data', { visibility: visibilityFilter });
     initGraph(data);
   } catch (err) {
     console.error('Graph load error:', err);
@@ -831,6 +1059,7 @@ function renderGraph() {
   ctx.lineCap = 'round';
   for (const e of edges) {
     if (!e.source.visible || !e.target.visible) continue;
+    if (hierarchyOnly && !INHERITANCE_KINDS.has(e.kind)) continue;
 
     const isHighlighted = activeNode && (
       e.source.id === activeNode.id || e.target.id === activeNode.id
@@ -871,6 +1100,7 @@ function renderGraph() {
 
     for (const e of highlightedEdges) {
       if (!e.source.visible || !e.target.visible) continue;
+      if (hierarchyOnly && !INHERITANCE_KINDS.has(e.kind)) continue;
 
       const other = e.source.id === activeNode.id ? e.target : e.source;
       const color = KIND_COLORS[other.kind] || '#8b949e';
@@ -932,7 +1162,7 @@ function renderGraph() {
     if (!inViewport(n.x, n.y, n.radius * 5)) continue; // viewport cull with glow margin
 
     const color = KIND_COLORS[n.kind] || '#8b949e';
-    const rgb = KIND_RGB[n.kind] || { r: 139, g: 148, b: 158 };
+    const rgb = heatScores ? heatRgb(heatScores.get(n.id) || 0) : (KIND_RGB[n.kind] || { r: 139, g: 148, b: 158 });
     const isActive = activeNode && activeNode.id === n.id;
     const isConnected = connectedSet.has(n.id);
     const ha = n.highlightAlpha;
@@ -1285,7 +1515,8 @@ function setupCanvasInteraction(canvas) {
         $graphTooltip.innerHTML =
           `<span class="tooltip-kind" style="color:${kindColor}">${kind}</span>` +
           `<span class="tooltip-name">${escapeHtml(node.name)}</span>` +
-          `<span class="tooltip-meta">${escapeHtml(node.package)} · ${escapeHtml(node.file)}:${node.line}</span>`;
+          `<span class="tooltip-meta">${escapeHtml(node.package)} · ${escapeHtml(node.file)}:${node.line}</span>` +
+          (heatScores && heatScores.has(node.id) ? `<span class="tooltip-meta">${heatDetails.get(node.id)}</span>` : '');
         $graphTooltip.classList.remove('hidden');
       } else {
         $graphTooltip.classList.add('hidden');
@@ -1318,6 +1549,7 @@ function setupCanvasInteraction(canvas) {
         } else {
           graphState.selectedNode = dragNode;
         }
+        if (hierarchyOnly) applyFilters();
       } else {
         // Drag completed — pin the node
         dragNode.pinned = true;
@@ -1334,6 +1566,7 @@ function setupCanvasInteraction(canvas) {
       // Click on empty space — deselect
       if (elapsed < 200) {
         graphState.selectedNode = null;
+        if (hierarchyOnly) applyFilters();
       }
     }
   });
@@ -1440,6 +1673,73 @@ function setupFilters(packageInfo, nodes) {
   updatePkgCount();
 }
 
+const INHERITANCE_KINDS = new Set(['Extends', 'Implements', 'Embeds']);
+
+// Hierarchy mode: with a selected node, its full ancestor/descendant tree;
+// otherwise every type that takes part in inheritance.
+function toggleHierarchyFilter(btn) {
+  hierarchyOnly = !hierarchyOnly;
+  btn.classList.toggle('active', hierarchyOnly);
+  applyFilters();
+}
+window.toggleHierarchyFilter = toggleHierarchyFilter;
+
+// Recolor graph nodes by hotspot score (churn × complexity): grey when cold, red at the top
+async function toggleHeatOverlay(btn) {
+  if (heatScores) {
+    heatScores = null;
+    btn.classList.remove('active');
+    return;
+  }
+  try {
+    const report = await invoke('get_hotspots', { since: null, limit: null });
+    const max = Math.max(1, ...report.hotspots.map(h => h.score));
+    heatScores = new Map(report.hotspots.map(h => [h.id, h.score / max]));
+    heatDetails = new Map(report.hotspots.map(h =>
+      [h.id, `${h.commits} commits · complexity ${h.complexity} · score ${h.score}`]));
+    btn.classList.add('active');
+  } catch (err) {
+    console.error('Hotspots error:', err);
+  }
+}
+window.toggleHeatOverlay = toggleHeatOverlay;
+
+function heatRgb(t) {
+  const cold = { r: 72, g: 79, b: 88 }, warm = { r: 210, g: 153, b: 34 }, hot = { r: 248, g: 81, b: 73 };
+  const [a, b, f] = t < 0.5 ? [cold, warm, t * 2] : [warm, hot, (t - 0.5) * 2];
+  return {
+    r: Math.round(a.r + (b.r - a.r) * f),
+    g: Math.round(a.g + (b.g - a.g) * f),
+    b: Math.round(a.b + (b.b - a.b) * f),
+  };
+}
+
+function hierarchyNodeIds() {
+  const ids = new Set();
+  const inheritance = graphState.edges.filter(e => INHERITANCE_KINDS.has(e.kind));
+  const selected = graphState.selectedNode;
+  if (!selected) {
+    for (const e of inheritance) { ids.add(e.source.id); ids.add(e.target.id); }
+    return ids;
+  }
+  // Walk up and down separately so siblings are not pulled in
+  for (const [from, to] of [['source', 'target'], ['target', 'source']]) {
+    const queue = [selected.id];
+    const seen = new Set(queue);
+    while (queue.length > 0) {
+      const id = queue.shift();
+      for (const e of inheritance) {
+        if (e[from].id === id && !seen.has(e[to].id)) {
+          seen.add(e[to].id);
+          queue.push(e[to].id);
+        }
+      }
+    }
+    seen.forEach(id => ids.add(id));
+  }
+  return ids;
+}
+
 function toggleKindFilter(btn) {
   btn.classList.toggle('active');
   applyFilters();
@@ -1473,9 +1773,17 @@ function applyFilters() {
   const activePkgs = new Set();
   $pkgDropdownList.querySelectorAll('input[type="checkbox"]:checked').forEach(c => activePkgs.add(c.dataset.pkg));
 
-  graphState.nodes.forEach(n => {
-    n.visible = activeKinds.has(n.kind) && activePkgs.has(n.package);
-  });
+  if (hierarchyOnly) {
+    // Hierarchies cross packages, so the package filter does not apply
+    const ids = hierarchyNodeIds();
+    graphState.nodes.forEach(n => {
+      n.visible = ids.has(n.id) && activeKinds.has(n.kind);
+    });
+  } else {
+    graphState.nodes.forEach(n => {
+      n.visible = activeKinds.has(n.kind) && activePkgs.has(n.package);
+    });
+  }
 
   updatePkgCount();
   reheat(0.5);
@@ -1515,3 +1823,8 @@ document.addEventListener('keydown', (e) => {
 
 // === Init ===
 $openRepoBtn.addEventListener('click', openRepo);
+$repoRevInput.addEventListener('keydown', (e) => {
+  if (e.key === 'Enter' && repoPath) {
+    loadRepo(repoPath, $repoRevInput.value.trim() || null);
+  }
+});